@@ -1,7 +1,7 @@
 use nightmaregl::events::{Event, EventLoop, LoopAction};
 use nightmaregl::texture::Texture;
 use nightmaregl::{
-    Animation, Color, Context, Position, Renderer, Result ,
+    Animation, PlayMode, Color, Context, Position, Renderer, Result ,
     VertexData, Viewport, Transform, Rotation
 };
 
@@ -17,8 +17,8 @@ fn main() -> Result<()> {
     let texture = Texture::<f32>::from_disk("examples/anim.png")?;
 
     let mut animation = Animation::from_texture(&texture, 1, 3, 32, 40);
-    animation.fps = 4.0;
-    animation.repeat = true;
+    animation.set_fps(4.0);
+    animation.mode = PlayMode::Loop;
 
     let mut transform = Transform::default();
     let position = (*viewport.size() / 2).to_vector() / renderer.pixel_size;
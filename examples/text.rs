@@ -26,7 +26,7 @@ fn main() -> Result<()> {
                 context.clear(Color::black());
 
                 let _ = renderer.render(
-                    &text.texture(),
+                    text.texture(),
                     &text.vertex_data(),
                     &viewport,
                     &mut context,
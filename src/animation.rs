@@ -5,20 +5,110 @@ use nalgebra::Scalar;
 use num_traits::cast::NumCast;
 use num_traits::Zero;
 
+use crate::atlas::{SpriteSheet, TagDirection};
 use crate::sprite::Sprite;
 use crate::texture::Texture;
-use crate::{Point, Rect, Size};
+use crate::{Point, Position, Rect, Size, UvRotation};
 
+/// Frame duration used when none is given: 10 frames per second.
+const DEFAULT_DURATION: f32 = 0.1;
+
+// -----------------------------------------------------------------------------
+//     - Play mode -
+// -----------------------------------------------------------------------------
+/// How an animation plays its frames.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayMode {
+    /// Play from the first to the last frame, then stop.
+    Once,
+    /// Play from the first to the last frame, forever.
+    Loop,
+    /// Play from the first to the last frame and back again, forever.
+    PingPong,
+    /// Play from the last to the first frame, forever.
+    Reverse,
+    /// Play from the last to the first frame and back again, forever.
+    PingPongReverse,
+}
+
+// -----------------------------------------------------------------------------
+//     - Frame -
+// -----------------------------------------------------------------------------
+/// A single frame of an animation.
+///
+/// The size, anchor and uv rotation are set on the sprite when the
+/// frame is shown. When they are `None` the sprite keeps its own.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame<T> {
+    /// The area of the texture to show.
+    pub rect: Rect<T>,
+    /// How long the frame is shown, in seconds.
+    pub duration: f32,
+    /// The size of the sprite, e.g. for frames trimmed to different sizes.
+    pub size: Option<Size<T>>,
+    /// The anchor of the sprite, e.g. to place a trimmed frame
+    /// where it would be if it wasn't trimmed.
+    pub anchor: Option<Position<T>>,
+    /// The uv rotation of the sprite, e.g. for frames stored rotated.
+    pub uv_rotation: Option<UvRotation>,
+}
+
+// -----------------------------------------------------------------------------
+//     - Clip -
+// -----------------------------------------------------------------------------
+/// A named range of frames, e.g. "walk" or "idle".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    /// Name of the clip
+    pub name: String,
+    /// Index of the first frame
+    pub from: usize,
+    /// Index of the last frame (inclusive)
+    pub to: usize,
+    /// How the clip plays
+    pub mode: PlayMode,
+}
+
+// -----------------------------------------------------------------------------
+//     - Animation event -
+// -----------------------------------------------------------------------------
+/// Something that happened during [`Animation::update`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEvent {
+    /// The animation moved to a frame.
+    /// This is also reported for the first frame, on the first
+    /// update after the animation (re)started.
+    FrameEntered(usize),
+    /// The animation wrapped around and started over.
+    /// For [`PlayMode::PingPong`] this is when it returns to the first frame,
+    /// and for [`PlayMode::PingPongReverse`] when it returns to the last.
+    Looped,
+    /// The animation reached the end and stopped.
+    Finished,
+    /// The animation moved to a frame with a marker.
+    Marker {
+        /// The frame the marker is attached to
+        frame: usize,
+        /// The name of the marker
+        name: String,
+    },
+}
+
+// -----------------------------------------------------------------------------
+//     - Animation -
+// -----------------------------------------------------------------------------
 /// Represent a sprite as an animation.
 ///
-/// To make the animation loop set the `repeat` variable;
+/// An animation is a list of frames, each with its own duration,
+/// and optionally a set of named clips (ranges of frames).
+/// Without a clip the animation plays all the frames.
 ///
 /// ```
-/// use nightmaregl::{Sprite, Animation, Point, Size};
+/// use nightmaregl::{Sprite, Animation, PlayMode, Point, Size};
 /// let sprite = Sprite::from_size(Size::new(32, 64));
 /// let mut animation = Animation::from_sprite(sprite, 1, 3, 32, 32);
-/// animation.repeat = false;
-/// animation.fps = 1.0;
+/// animation.mode = PlayMode::Once;
+/// animation.set_fps(1.0);
 ///
 /// // first frame is at 0, 0
 /// assert_eq!(animation.sprite.texture_rect.origin, Point::zero());
@@ -26,7 +116,6 @@ use crate::{Point, Rect, Size};
 ///
 /// // Second frame is at 32, 0
 /// animation.update(1.0);
-/// eprintln!("origin be all like: {:?}", animation.sprite.texture_rect.origin);
 /// assert_eq!(animation.sprite.texture_rect.origin, Point::new(32, 0));
 /// assert_eq!(animation.current_frame(), 1);
 ///
@@ -34,25 +123,92 @@ use crate::{Point, Rect, Size};
 /// animation.update(1.0);
 /// assert_eq!(animation.sprite.texture_rect.origin, Point::new(64, 0));
 /// assert_eq!(animation.current_frame(), 2);
+///
+/// // The animation stays on the last frame
+/// animation.update(10.0);
+/// assert_eq!(animation.current_frame(), 2);
+/// assert!(animation.is_finished());
+/// ```
+///
+/// # Upgrading
+///
+/// This replaces the fixed frame rate animation, and breaks code using it:
+///
+/// * `animation.fps = 12.0` is now `animation.set_fps(12.0)`, which sets the
+///   duration of every frame. Frames can also have durations of their own.
+/// * `animation.repeat = true` is now `animation.mode = PlayMode::Loop`,
+///   and `false` is `PlayMode::Once` (the default).
+/// * [`Animation::current_frame`] returns a `usize` rather than a `u16`.
+/// * `Animation` is no longer `Copy`, as it owns its frames. Use `clone`.
+///
+/// Markers attached to frames are reported by [`Animation::update`],
+/// along with every frame entered during the update:
+///
+/// ```
+/// use nightmaregl::{Sprite, Animation, AnimationEvent, Size};
+/// let sprite = Sprite::from_size(Size::new(128, 32));
+/// let mut animation = Animation::from_sprite(sprite, 1, 4, 32, 32);
+/// animation.add_marker(2, "hitbox on");
+/// animation.set_fps(10.0);
+///
+/// let events = animation.update(0.25);
+/// assert_eq!(events, vec![
+///     AnimationEvent::FrameEntered(0),
+///     AnimationEvent::FrameEntered(1),
+///     AnimationEvent::FrameEntered(2),
+///     AnimationEvent::Marker { frame: 2, name: "hitbox on".into() },
+/// ]);
 /// ```
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Animation<T> {
-    cols: u16,
-    stride_w: u16,
-    stride_h: u16,
-    current_frame: u16,
-    max_frame: u16,
-    /// Should this animation repeat forever?
-    pub repeat: bool,
+    frames: Vec<Frame<T>>,
+    clips: Vec<Clip>,
+    markers: Vec<(usize, String)>,
+    events: Vec<AnimationEvent>,
+    clip: Option<usize>,
+    from: usize,
+    to: usize,
+    current_frame: usize,
+    // Direction when playing ping pong
+    forward: bool,
+    // The first frame hasn't been reported yet
+    started: bool,
+    finished: bool,
+    elapsed: f32,
+    /// How the animation plays.
+    /// This is set by [`Animation::play`] to the mode of the clip.
+    pub mode: PlayMode,
     /// The sprite the animation is acting upon
     pub sprite: Sprite<T>,
-    /// Number of frames per second
-    pub fps: f32,
-    elapsed: f32,
 }
 
 impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>> Animation<T> {
-    /// Document me not for I shall not be here long...
+    /// Create an animation from a list of frames.
+    /// The sprite is set to show the first frame.
+    pub fn new(sprite: Sprite<T>, frames: Vec<Frame<T>>) -> Self {
+        let mut inst = Self {
+            to: frames.len().saturating_sub(1),
+            frames,
+            clips: Vec::new(),
+            markers: Vec::new(),
+            events: Vec::new(),
+            clip: None,
+            from: 0,
+            current_frame: 0,
+            forward: true,
+            started: false,
+            finished: false,
+            elapsed: 0.0,
+            mode: PlayMode::Once,
+            sprite,
+        };
+
+        inst.apply_frame();
+        inst
+    }
+
+    /// Create an animation from a texture containing a grid of
+    /// frames, where `stride` is the distance between frames.
     pub fn from_texture(
         texture: &Texture<T>,
         rows: u16,
@@ -69,6 +225,8 @@ impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>>
     /// Create a new animations, where `stride` is the distance between
     /// frames. This means that a sprite sheet has to contain frames that are all
     /// of the same size.
+    ///
+    /// Every frame is shown for a tenth of a second.
     pub fn from_sprite(
         sprite: Sprite<T>,
         rows: u16,
@@ -76,52 +234,332 @@ impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>>
         stride_w: u16,
         stride_h: u16,
     ) -> Self {
-        let max_frame = rows * cols;
+        let frames = (0..rows * cols)
+            .map(|frame| {
+                let x = frame % cols;
+                let y = frame / cols;
+                let origin = Point::new(x * stride_w, y * stride_h).cast();
 
-        Self {
-            cols,
-            stride_w,
-            stride_h,
-            current_frame: 0,
-            repeat: false,
-            max_frame,
-            sprite,
-            fps: 10.0,
-            elapsed: 0.,
+                Frame {
+                    rect: Rect::new(origin, sprite.texture_rect.size),
+                    duration: DEFAULT_DURATION,
+                    size: None,
+                    anchor: None,
+                    uv_rotation: None,
+                }
+            })
+            .collect();
+
+        Self::new(sprite, frames)
+    }
+
+    /// Create an animation from the frames of a sprite sheet.
+    /// Every tag in the sheet becomes a clip.
+    ///
+    /// Frames without a duration are shown for a tenth of a second.
+    /// Rotated and trimmed frames are shown the same way as
+    /// [`SpriteSheet::sprite`] shows them: every frame sets the size,
+    /// anchor and uv rotation of the sprite.
+    pub fn from_sheet(sprite: Sprite<T>, sheet: &SpriteSheet) -> Self {
+        let frames = sheet
+            .frames
+            .iter()
+            .map(|frame| {
+                let (size, anchor, uv_rotation) = frame.placement();
+                Frame {
+                    rect: frame.rect.cast(),
+                    duration: frame.duration.unwrap_or(DEFAULT_DURATION),
+                    size: Some(size),
+                    anchor: Some(anchor),
+                    uv_rotation: Some(uv_rotation),
+                }
+            })
+            .collect();
+
+        let mut inst = Self::new(sprite, frames);
+
+        for tag in &sheet.tags {
+            let mode = match tag.direction {
+                TagDirection::Forward => PlayMode::Loop,
+                TagDirection::Reverse => PlayMode::Reverse,
+                TagDirection::PingPong => PlayMode::PingPong,
+                TagDirection::PingPongReverse => PlayMode::PingPongReverse,
+            };
+
+            inst.add_clip(Clip {
+                name: tag.name.clone(),
+                from: tag.from,
+                to: tag.to,
+                mode,
+            });
         }
+
+        inst
+    }
+
+    /// Add a named clip.
+    /// The range of the clip is clamped to the available frames.
+    pub fn add_clip(&mut self, mut clip: Clip) {
+        let last = self.frames.len().saturating_sub(1);
+        clip.to = clip.to.min(last);
+        clip.from = clip.from.min(clip.to);
+        self.clips.push(clip);
+    }
+
+    /// Attach a named marker to a frame.
+    /// An [`AnimationEvent::Marker`] is reported every time the frame is entered.
+    pub fn add_marker(&mut self, frame: usize, name: impl Into<String>) {
+        self.markers.push((frame, name.into()));
+    }
+
+    /// Play a clip by name.
+    ///
+    /// Playing the clip that is already playing does nothing,
+    /// unless it has finished, in which case it starts over.
+    /// Returns false if there is no clip with the given name.
+    pub fn play(&mut self, name: &str) -> bool {
+        let index = match self.clips.iter().position(|c| c.name == name) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        if self.clip == Some(index) && !self.finished {
+            return true;
+        }
+
+        let clip = &self.clips[index];
+        self.clip = Some(index);
+        self.from = clip.from;
+        self.to = clip.to;
+        self.mode = clip.mode;
+        self.restart();
+        true
+    }
+
+    /// Play all the frames rather than a clip.
+    pub fn play_all(&mut self) {
+        self.clip = None;
+        self.from = 0;
+        self.to = self.frames.len().saturating_sub(1);
+        self.restart();
+    }
+
+    /// Start the current clip (or all the frames) over.
+    pub fn restart(&mut self) {
+        self.current_frame = match self.mode {
+            PlayMode::Reverse | PlayMode::PingPongReverse => self.to,
+            _ => self.from,
+        };
+        self.forward = self.mode != PlayMode::PingPongReverse;
+        self.started = false;
+        self.finished = false;
+        self.elapsed = 0.0;
+        self.apply_frame();
+    }
+
+    /// The name of the clip that is playing, if any.
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.map(|index| self.clips[index].name.as_str())
+    }
+
+    /// All the clips of the animation.
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    /// All the frames of the animation.
+    pub fn frames(&self) -> &[Frame<T>] {
+        &self.frames
+    }
+
+    /// The time it takes to play the current clip (or all the frames)
+    /// from the first to the last frame once.
+    pub fn duration(&self) -> f32 {
+        self.frames
+            .get(self.from..=self.to)
+            .map(|frames| frames.iter().map(|f| f.duration).sum())
+            .unwrap_or(0.0)
+    }
+
+    /// Show every frame for the same amount of time.
+    /// This overwrites the duration of every frame, including the ones
+    /// outside the current clip and the ones read from a sprite sheet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fps` is not a positive number.
+    pub fn set_fps(&mut self, fps: f32) {
+        assert!(fps > 0.0 && fps.is_finite(), "invalid frames per second: {}", fps);
+        let duration = 1.0 / fps;
+        self.frames.iter_mut().for_each(|frame| frame.duration = duration);
+    }
+
+    /// Update the time of the animation, and return
+    /// the events that happened, in order.
+    ///
+    /// If `dt` is longer than the current frame, the animation
+    /// advances as many frames as `dt` covers, and the events of
+    /// every frame are reported. A looping animation skips whole
+    /// loops, so at most one loop and the frames after it are reported.
+    /// A `dt` that is not finite is ignored.
+    pub fn update(&mut self, dt: f32) -> &[AnimationEvent] {
+        self.events.clear();
+
+        if self.finished || self.frames.is_empty() {
+            return &self.events;
+        }
+
+        if !self.started {
+            self.started = true;
+            self.enter_frame();
+        }
+
+        if dt.is_finite() {
+            self.elapsed += dt;
+        }
+
+        // Frames without a duration are skipped, but if no frame has
+        // a duration the loop would never end.
+        let clip = self.frames.get(self.from..=self.to).unwrap_or(&[]);
+        if clip.iter().all(|frame| frame.duration <= 0.0) {
+            return &self.events;
+        }
+
+        if let Some(period) = self.period() {
+            if self.elapsed > period {
+                self.elapsed = period + self.elapsed % period;
+            }
+        }
+
+        loop {
+            let duration = self.frames[self.current_frame].duration.max(0.0);
+            if self.elapsed < duration {
+                break;
+            }
+
+            self.elapsed -= duration;
+            if !self.next() {
+                self.elapsed = 0.0;
+                break;
+            }
+        }
+
+        &self.events
+    }
+
+    /// The events of the last call to [`Animation::update`].
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
     }
 
-    /// Update the time of the animation.
-    pub fn update(&mut self, dt: f32) {
-        self.elapsed += dt;
-        let sec = 1.0 / self.fps;
+    // The time it takes a looping animation to get back to the same frame,
+    // going the same way. Animations that don't loop have no period.
+    fn period(&self) -> Option<f32> {
+        let clip = self.frames.get(self.from..=self.to)?;
+        let duration = |frame: &Frame<T>| frame.duration.max(0.0);
+        let once = clip.iter().map(duration).sum::<f32>();
 
-        if self.elapsed >= sec {
-            self.elapsed -= sec;
-            self.next();
+        match self.mode {
+            PlayMode::Once => None,
+            PlayMode::Loop | PlayMode::Reverse => Some(once),
+            // Every frame but the first and last is shown twice
+            PlayMode::PingPong | PlayMode::PingPongReverse => match clip {
+                [first, .., last] => Some(once * 2.0 - duration(first) - duration(last)),
+                _ => Some(once),
+            },
         }
     }
 
     /// Get the current frame, starting from zero.
-    pub fn current_frame(&self) -> u16 {
+    /// This is the index of the frame among all the frames,
+    /// not within the current clip.
+    pub fn current_frame(&self) -> usize {
         self.current_frame
     }
 
-    fn next(&mut self) {
-        if self.current_frame == self.max_frame - 1 {
-            match self.repeat {
-                true => self.current_frame = 0,
-                false => return,
+    /// Returns true if the animation played to the end and stopped.
+    /// Only animations playing [`PlayMode::Once`] finish.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Advance one frame.
+    // Returns false if the animation is finished.
+    fn next(&mut self) -> bool {
+        let (from, to, current) = (self.from, self.to, self.current_frame);
+
+        self.current_frame = match self.mode {
+            PlayMode::Once if current >= to => {
+                self.finished = true;
+                self.events.push(AnimationEvent::Finished);
+                return false;
             }
-        } else {
-            self.current_frame += 1;
-        }
+            PlayMode::Once => current + 1,
+            PlayMode::Loop if current >= to => {
+                self.events.push(AnimationEvent::Looped);
+                from
+            }
+            PlayMode::Loop => current + 1,
+            PlayMode::Reverse if current <= from => {
+                self.events.push(AnimationEvent::Looped);
+                to
+            }
+            PlayMode::Reverse => current - 1,
+            PlayMode::PingPong | PlayMode::PingPongReverse if from == to => {
+                self.events.push(AnimationEvent::Looped);
+                current
+            }
+            PlayMode::PingPong | PlayMode::PingPongReverse => {
+                if self.forward && current >= to || !self.forward && current <= from {
+                    self.forward = !self.forward;
+                }
+                let next = match self.forward {
+                    true => current + 1,
+                    false => current - 1,
+                };
+                // Back where it started
+                let start = match self.mode {
+                    PlayMode::PingPongReverse => to,
+                    _ => from,
+                };
+                if next == start {
+                    self.events.push(AnimationEvent::Looped);
+                }
+                next
+            }
+        };
+
+        self.apply_frame();
+        self.enter_frame();
+        true
+    }
 
-        let x = self.current_frame % self.cols;
-        let y = self.current_frame / self.cols;
+    fn enter_frame(&mut self) {
+        let frame = self.current_frame;
+        self.events.push(AnimationEvent::FrameEntered(frame));
 
-        let offset = Point::new(x * self.stride_w, y * self.stride_h).cast();
-        self.sprite.texture_rect.origin = offset;
+        let markers = self.markers.iter().filter(|(f, _)| *f == frame);
+        for (_, name) in markers {
+            self.events.push(AnimationEvent::Marker {
+                frame,
+                name: name.clone(),
+            });
+        }
+    }
+
+    fn apply_frame(&mut self) {
+        if let Some(frame) = self.frames.get(self.current_frame) {
+            self.sprite.texture_rect = frame.rect;
+            if let Some(size) = frame.size {
+                self.sprite.size = size;
+            }
+            if let Some(anchor) = frame.anchor {
+                self.sprite.anchor = anchor;
+            }
+            if let Some(uv_rotation) = frame.uv_rotation {
+                self.sprite.uv_rotation = uv_rotation;
+            }
+        }
     }
 }
 
@@ -137,12 +575,21 @@ mod test {
         sprite
     }
 
+    fn frames_played(animation: &mut Animation<u16>, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                animation.next();
+                animation.current_frame()
+            })
+            .collect()
+    }
+
     #[test]
     fn test_looping_animation_offset() {
         let stride = 32;
         let sprite = make_sprite();
         let mut animation = Animation::from_sprite(sprite, 2, 2, stride, stride);
-        animation.repeat = true;
+        animation.mode = PlayMode::Loop;
 
         // Second frame
         animation.next();
@@ -169,18 +616,217 @@ mod test {
         assert_eq!(expected, actual);
     }
 
-    // #[test]
-    // fn test_animation_ends() {
-    //     let stride = 32;
-    //     let sprite = make_sprite();
-    //     let mut animation = Animation::new(sprite, 2, 2, stride);
+    #[test]
+    fn test_animation_ends() {
+        let stride = 32;
+        let sprite = make_sprite();
+        let mut animation = Animation::from_sprite(sprite, 2, 2, stride, stride);
+
+        assert_eq!(frames_played(&mut animation, 5), vec![1, 2, 3, 3, 3]);
+        assert!(animation.is_finished());
+
+        let expected = Point::new(32, 32);
+        let actual = animation.sprite.texture_rect.origin;
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn play_modes() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+
+        animation.mode = PlayMode::PingPong;
+        animation.restart();
+        assert_eq!(frames_played(&mut animation, 7), vec![1, 2, 3, 2, 1, 0, 1]);
+
+        animation.mode = PlayMode::Reverse;
+        animation.restart();
+        assert_eq!(animation.current_frame(), 3);
+        assert_eq!(frames_played(&mut animation, 4), vec![2, 1, 0, 3]);
+
+        animation.mode = PlayMode::PingPongReverse;
+        animation.restart();
+        assert_eq!(animation.current_frame(), 3);
+        assert_eq!(frames_played(&mut animation, 7), vec![2, 1, 0, 1, 2, 3, 2]);
+    }
 
-    //     for _ in 0..2*2 {
-    //         animation.next();
-    //     }
+    #[test]
+    fn large_dt_skips_frames() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+        animation.mode = PlayMode::Loop;
+        animation.set_fps(4.0);
+        animation.frames[1].duration = 0.5;
+
+        // 0.25 + 0.5 + 0.25 = 1.0, with 0.125 left over
+        animation.update(1.125);
+        assert_eq!(animation.current_frame(), 3);
 
-    //     let expected = Position::new(32, 32);
-    //     let actual = animation.sprite.texture_offset;
-    //     assert_eq!(expected, actual);
-    // }
+        animation.update(0.125);
+        assert_eq!(animation.current_frame(), 0);
+    }
+
+    #[test]
+    fn zero_length_frames_are_skipped() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+        animation.mode = PlayMode::Loop;
+        animation.set_fps(4.0);
+        animation.frames[1].duration = 0.0;
+        animation.frames[2].duration = -1.0;
+
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), 3);
+
+        // Nothing to show for any time at all, so it stays put
+        animation.frames.iter_mut().for_each(|frame| frame.duration = 0.0);
+        animation.update(1.0);
+        assert_eq!(animation.current_frame(), 3);
+    }
+
+    #[test]
+    fn events_during_one_update() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+        animation.mode = PlayMode::Loop;
+        animation.set_fps(4.0);
+        animation.add_marker(0, "step");
+        animation.update(0.0);
+
+        // Three frames in one update: 1, 2, 3, then back to 0
+        let events = animation.update(1.0);
+        assert_eq!(events, vec![
+            AnimationEvent::FrameEntered(1),
+            AnimationEvent::FrameEntered(2),
+            AnimationEvent::FrameEntered(3),
+            AnimationEvent::Looped,
+            AnimationEvent::FrameEntered(0),
+            AnimationEvent::Marker { frame: 0, name: "step".into() },
+        ]);
+    }
+
+    #[test]
+    fn finished_is_reported_once() {
+        let mut animation = Animation::from_sprite(make_sprite(), 1, 2, 32, 32);
+        animation.set_fps(4.0);
+
+        let events = animation.update(1.0);
+        assert_eq!(events, vec![
+            AnimationEvent::FrameEntered(0),
+            AnimationEvent::FrameEntered(1),
+            AnimationEvent::Finished,
+        ]);
+        assert!(animation.update(1.0).is_empty());
+    }
+
+    #[test]
+    fn ping_pong_loops_at_the_first_frame() {
+        let mut animation = Animation::from_sprite(make_sprite(), 1, 3, 32, 32);
+        animation.mode = PlayMode::PingPong;
+        animation.set_fps(4.0);
+
+        let events = animation.update(1.0);
+        assert_eq!(events, vec![
+            AnimationEvent::FrameEntered(0),
+            AnimationEvent::FrameEntered(1),
+            AnimationEvent::FrameEntered(2),
+            AnimationEvent::FrameEntered(1),
+            AnimationEvent::Looped,
+            AnimationEvent::FrameEntered(0),
+        ]);
+    }
+
+    #[test]
+    fn long_updates_skip_whole_loops() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+        animation.mode = PlayMode::Loop;
+        animation.set_fps(4.0);
+        animation.update(0.0);
+
+        // 3600.25 seconds is 14401 frames, or a frame past the first
+        animation.update(3600.25);
+        let looped = animation.events().iter().filter(|e| **e == AnimationEvent::Looped);
+        assert_eq!(looped.count(), 1);
+        assert_eq!(animation.current_frame(), 1);
+
+        // Six frames in a ping pong loop: 0, 1, 2, 3, 2, 1
+        animation.mode = PlayMode::PingPong;
+        animation.restart();
+        animation.update(0.0);
+        animation.update(1000.0 * 1.5 + 0.75);
+        assert_eq!(animation.current_frame(), 3);
+
+        animation.update(f32::NAN);
+        animation.update(f32::INFINITY);
+        assert_eq!(animation.current_frame(), 3);
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_fps() {
+        Animation::from_sprite(make_sprite(), 2, 2, 32, 32).set_fps(0.0);
+    }
+
+    #[test]
+    fn clips() {
+        let mut animation = Animation::from_sprite(make_sprite(), 2, 2, 32, 32);
+        animation.add_clip(Clip { name: "idle".into(), from: 2, to: 3, mode: PlayMode::Loop });
+
+        assert!(!animation.play("walk"));
+        assert!(animation.play("idle"));
+        assert_eq!(animation.clip(), Some("idle"));
+        assert_eq!(animation.current_frame(), 2);
+        assert_eq!(frames_played(&mut animation, 3), vec![3, 2, 3]);
+
+        // Playing the same clip again doesn't restart it
+        animation.play("idle");
+        assert_eq!(animation.current_frame(), 3);
+    }
+
+    #[test]
+    fn rotated_and_trimmed_sheet_frames() {
+        use crate::atlas::SheetFrame;
+
+        let frame = |name: &str, rect, rotated, trimmed| SheetFrame {
+            name: name.into(),
+            rect,
+            rotated,
+            trimmed,
+            source_size: Size::new(16, 24),
+            source_offset: Point::new(3, 1),
+            duration: Some(0.25),
+        };
+
+        // A 10x20 frame stored rotated as 20x10, and a trimmed 10x20 frame
+        let sheet = SpriteSheet {
+            image: None,
+            size: Size::new(64, 64),
+            frames: vec![
+                frame("rotated", Rect::new(Point::new(2, 2), Size::new(20, 10)), true, false),
+                frame("trimmed", Rect::new(Point::new(30, 2), Size::new(10, 20)), false, true),
+            ],
+            tags: Vec::new(),
+        };
+
+        let sprite = Sprite::<f32>::from_size(Size::new(64.0, 64.0));
+        let mut animation = Animation::from_sheet(sprite, &sheet);
+        animation.mode = PlayMode::Loop;
+
+        for (dt, name) in &[(0.0, "rotated"), (0.25, "trimmed"), (0.25, "rotated")] {
+            animation.update(*dt);
+            let expected = sheet.sprite::<f32>(name).unwrap();
+            let sprite = &animation.sprite;
+            assert_eq!(sprite.texture_rect, expected.texture_rect, "{}", name);
+            assert_eq!(sprite.size, expected.size, "{}", name);
+            assert_eq!(sprite.anchor, expected.anchor, "{}", name);
+            assert_eq!(sprite.uv_rotation, expected.uv_rotation, "{}", name);
+        }
+
+        // Back on the rotated frame, which is untrimmed
+        assert_eq!(animation.sprite.size, Size::new(10.0, 20.0));
+        assert_eq!(animation.sprite.anchor, Position::zero());
+        assert_eq!(animation.sprite.uv_rotation, UvRotation::CounterClockwise90);
+
+        animation.update(0.25);
+        assert_eq!(animation.sprite.anchor, Position::new(-3.0, -3.0));
+        assert_eq!(animation.sprite.uv_rotation, UvRotation::None);
+    }
 }
@@ -0,0 +1,598 @@
+#![deny(missing_docs)]
+use std::ops::{Div, MulAssign};
+
+use nalgebra::Scalar;
+use num_traits::cast::NumCast;
+use num_traits::Zero;
+
+use crate::animation::{Animation, AnimationEvent};
+use crate::errors::{NightmareError, Result};
+use crate::sprite::Sprite;
+use crate::{Transform, VertexData, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Param -
+// -----------------------------------------------------------------------------
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Param {
+    Bool(bool),
+    // A bool that is reset once a transition uses it
+    Trigger(bool),
+    Float(f32),
+}
+
+// -----------------------------------------------------------------------------
+//     - Condition -
+// -----------------------------------------------------------------------------
+/// A condition on a parameter of an [`Animator`].
+/// A bool parameter that was never set is false, any other
+/// condition on a parameter that was never set is not met.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The bool parameter is true
+    If(String),
+    /// The bool parameter is false
+    IfNot(String),
+    /// The trigger parameter is set.
+    /// The trigger is reset when the transition is taken.
+    Trigger(String),
+    /// The float parameter is greater than the value
+    Greater(String, f32),
+    /// The float parameter is less than the value
+    Less(String, f32),
+}
+
+impl Condition {
+    fn param(&self) -> &str {
+        match self {
+            Condition::If(name)
+            | Condition::IfNot(name)
+            | Condition::Trigger(name)
+            | Condition::Greater(name, _)
+            | Condition::Less(name, _) => name,
+        }
+    }
+
+    fn is_met(&self, param: Option<Param>) -> bool {
+        match (self, param) {
+            (Condition::If(_), Some(Param::Bool(b))) => b,
+            (Condition::IfNot(_), Some(Param::Bool(b))) => !b,
+            (Condition::IfNot(_), None) => true,
+            (Condition::Trigger(_), Some(Param::Trigger(t))) => t,
+            (Condition::Greater(_, value), Some(Param::Float(f))) => f > *value,
+            (Condition::Less(_, value), Some(Param::Float(f))) => f < *value,
+            _ => false,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Transition -
+// -----------------------------------------------------------------------------
+/// A transition from one state of an [`Animator`] to another.
+///
+/// The transition is taken when all the conditions are met,
+/// and the exit time (if any) has passed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    from: Option<String>,
+    to: String,
+    conditions: Vec<Condition>,
+    exit_time: Option<f32>,
+    crossfade: f32,
+}
+
+impl Transition {
+    /// A transition from one state to another.
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: Some(from.into()),
+            ..Self::any(to)
+        }
+    }
+
+    /// A transition from any state (except the target state itself).
+    pub fn any(to: impl Into<String>) -> Self {
+        Self {
+            from: None,
+            to: to.into(),
+            conditions: Vec::new(),
+            exit_time: None,
+            crossfade: 0.0,
+        }
+    }
+
+    /// Add a condition to the transition.
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Only take the transition once the state has played for the
+    /// given number of clips, e.g. `1.0` to wait for the clip to
+    /// finish once, or `0.5` for halfway through.
+    pub fn exit_time(mut self, exit_time: f32) -> Self {
+        self.exit_time = Some(exit_time);
+        self
+    }
+
+    /// Fade from the old state to the new state over the given
+    /// number of seconds, rather than switching instantly.
+    pub fn crossfade(mut self, seconds: f32) -> Self {
+        self.crossfade = seconds;
+        self
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Fade -
+// -----------------------------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+struct Fade {
+    from: usize,
+    duration: f32,
+    elapsed: f32,
+}
+
+// -----------------------------------------------------------------------------
+//     - Animator -
+// -----------------------------------------------------------------------------
+/// A state machine of animations.
+///
+/// Every state plays its own [`Animation`]. The animator moves between
+/// states using transitions, driven by parameters set from gameplay code.
+///
+/// During a crossfade both the old and the new state are playing,
+/// and [`Animator::layers`] returns both sprites with their alpha.
+/// [`Animator::vertex_data`] does the same, with the alpha applied to the tint.
+///
+/// ```
+/// use nightmaregl::{Animation, Animator, Condition, Transition, Sprite, Size};
+///
+/// let sprite = Sprite::from_size(Size::new(128, 32));
+/// let walk = Animation::from_sprite(sprite, 1, 4, 32, 32);
+/// let idle = Animation::from_sprite(sprite, 1, 1, 32, 32);
+///
+/// let mut animator = Animator::new("idle", idle);
+/// animator.add_state("walk", walk);
+/// animator.add_transition(Transition::new("idle", "walk").when(Condition::Greater("speed".into(), 0.1))).unwrap();
+/// animator.add_transition(Transition::new("walk", "idle").when(Condition::Less("speed".into(), 0.1)).crossfade(0.2)).unwrap();
+///
+/// animator.set_float("speed", 1.0);
+/// animator.update(0.016);
+/// assert_eq!(animator.state(), "walk");
+///
+/// animator.set_float("speed", 0.0);
+/// animator.update(0.016);
+/// assert_eq!(animator.state(), "idle");
+/// assert_eq!(animator.layers().len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Animator<T> {
+    states: Vec<(String, Animation<T>)>,
+    transitions: Vec<Transition>,
+    params: Vec<(String, Param)>,
+    current: usize,
+    time_in_state: f32,
+    fade: Option<Fade>,
+}
+
+impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>> Animator<T> {
+    /// Create an animator, starting in the given state.
+    pub fn new(state: impl Into<String>, animation: Animation<T>) -> Self {
+        Self {
+            states: vec![(state.into(), animation)],
+            transitions: Vec::new(),
+            params: Vec::new(),
+            current: 0,
+            time_in_state: 0.0,
+            fade: None,
+        }
+    }
+
+    /// Create an animator with one state for every clip of the animation,
+    /// named after the clip.
+    /// Returns `None` if the animation has no clips.
+    pub fn from_clips(animation: &Animation<T>) -> Option<Self> {
+        let mut names = animation.clips().iter().map(|clip| clip.name.clone());
+
+        let state = |name: &str| {
+            let mut animation = animation.clone();
+            animation.play(name);
+            animation
+        };
+
+        let first = names.next()?;
+        let mut inst = Self::new(first.clone(), state(&first));
+        for name in names {
+            inst.add_state(name.clone(), state(&name));
+        }
+
+        Some(inst)
+    }
+
+    /// Add a state.
+    /// Adding a state with the name of an existing state replaces it.
+    pub fn add_state(&mut self, name: impl Into<String>, animation: Animation<T>) {
+        let name = name.into();
+        match self.state_index(&name) {
+            Some(index) => self.states[index].1 = animation,
+            None => self.states.push((name, animation)),
+        }
+    }
+
+    /// Add a transition.
+    /// Transitions are checked in the order they were added.
+    pub fn add_transition(&mut self, transition: Transition) -> Result<()> {
+        let states = transition.from.iter().chain(Some(&transition.to));
+        for name in states {
+            if self.state_index(name).is_none() {
+                return Err(NightmareError::UnknownState(name.clone()));
+            }
+        }
+
+        self.transitions.push(transition);
+        Ok(())
+    }
+
+    /// Set a bool parameter.
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.set_param(name, Param::Bool(value));
+    }
+
+    /// Set a float parameter.
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.set_param(name, Param::Float(value));
+    }
+
+    /// Set a trigger parameter.
+    /// The trigger stays set until a transition using it is taken.
+    pub fn trigger(&mut self, name: &str) {
+        self.set_param(name, Param::Trigger(true));
+    }
+
+    /// The name of the current state.
+    pub fn state(&self) -> &str {
+        &self.states[self.current].0
+    }
+
+    /// The animation of the current state.
+    pub fn animation(&self) -> &Animation<T> {
+        &self.states[self.current].1
+    }
+
+    /// The animation of the current state.
+    pub fn animation_mut(&mut self) -> &mut Animation<T> {
+        &mut self.states[self.current].1
+    }
+
+    /// Go to a state right away, without a transition.
+    pub fn set_state(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .state_index(name)
+            .ok_or_else(|| NightmareError::UnknownState(name.to_string()))?;
+        self.fade = None;
+        self.enter(index);
+        Ok(())
+    }
+
+    /// Advance the animations and take the first transition whose
+    /// conditions are met.
+    ///
+    /// Returns the events of the state that was playing during this update.
+    /// A `dt` that is NaN or infinite is ignored, as it would stop
+    /// transitions and cross-fades from ever finishing.
+    pub fn update(&mut self, dt: f32) -> &[AnimationEvent] {
+        if !dt.is_finite() {
+            return &[];
+        }
+
+        let current = self.current;
+        self.states[current].1.update(dt);
+        self.time_in_state += dt;
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += dt;
+            match fade.elapsed >= fade.duration {
+                true => self.fade = None,
+                false => {
+                    self.states[fade.from].1.update(dt);
+                }
+            }
+        }
+
+        if let Some(index) = self.next_transition() {
+            self.take(index);
+        }
+
+        self.states[current].1.events()
+    }
+
+    /// The sprites to draw and their alpha, back to front.
+    ///
+    /// This is a single sprite with an alpha of one,
+    /// unless the animator is fading between two states.
+    ///
+    /// Both sprites have the same z index, so with the default depth mode
+    /// the sprite in front fails the depth test wherever the one behind
+    /// was drawn. [`Animator::vertex_data`] moves the sprite behind
+    /// further away; do the same when drawing the layers some other way, or
+    /// render with `DepthMode::Test(DepthFunc::LessOrEqual)`.
+    pub fn layers(&self) -> Vec<(&Sprite<T>, f32)> {
+        let current = &self.states[self.current].1.sprite;
+
+        match self.fade {
+            Some(fade) => {
+                let alpha = (fade.elapsed / fade.duration).min(1.0);
+                vec![(&self.states[fade.from].1.sprite, 1.0 - alpha), (current, alpha)]
+            }
+            None => vec![(current, 1.0)],
+        }
+    }
+
+    /// Vertex data for the layers, back to front,
+    /// with the alpha of each layer multiplied into its tint.
+    ///
+    /// Each layer is one z index further away than the layer in front of it,
+    /// so the layer in front passes the depth test and blends
+    /// with the layer behind. The layer in front keeps the z index of its
+    /// sprite, unless that would push the layers behind it past
+    /// [`Viewport::FAR`], in which case the layers are moved closer instead.
+    pub fn vertex_data(&self, transform: &Transform<T>) -> Vec<VertexData> {
+        let layers = self.layers();
+        let behind = layers.len() - 1;
+
+        layers
+            .into_iter()
+            .enumerate()
+            .map(|(i, (sprite, alpha))| {
+                let mut vertex_data = VertexData::new(sprite, transform);
+                vertex_data.tint.a *= alpha;
+                let z_index = vertex_data.model[(2, 3)];
+                let offset = match z_index + behind as f32 > Viewport::FAR as f32 {
+                    true => -(i as f32),
+                    false => (behind - i) as f32,
+                };
+                vertex_data.model[(2, 3)] = z_index + offset;
+                vertex_data
+            })
+            .collect()
+    }
+
+    fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|(n, _)| n == name)
+    }
+
+    fn param(&self, name: &str) -> Option<Param> {
+        self.params.iter().find(|(n, _)| n == name).map(|(_, p)| *p)
+    }
+
+    fn set_param(&mut self, name: &str, param: Param) {
+        match self.params.iter_mut().find(|(n, _)| n == name) {
+            Some((_, p)) => *p = param,
+            None => self.params.push((name.to_string(), param)),
+        }
+    }
+
+    fn next_transition(&self) -> Option<usize> {
+        let state = self.state();
+        let animation = self.animation();
+
+        self.transitions.iter().position(|transition| {
+            let from = match &transition.from {
+                Some(from) => from == state,
+                None => transition.to != state,
+            };
+
+            let exit_time = match transition.exit_time {
+                Some(exit_time) => {
+                    let duration = animation.duration();
+                    animation.is_finished() || duration > 0.0 && self.time_in_state / duration >= exit_time
+                }
+                None => true,
+            };
+
+            from && exit_time && transition
+                .conditions
+                .iter()
+                .all(|condition| condition.is_met(self.param(condition.param())))
+        })
+    }
+
+    fn take(&mut self, index: usize) {
+        let transition = self.transitions[index].clone();
+
+        for condition in &transition.conditions {
+            if let Condition::Trigger(name) = condition {
+                self.set_param(name, Param::Trigger(false));
+            }
+        }
+
+        let to = match self.state_index(&transition.to) {
+            Some(to) => to,
+            None => return,
+        };
+
+        self.fade = match transition.crossfade > 0.0 {
+            true => Some(Fade {
+                from: self.current,
+                duration: transition.crossfade,
+                elapsed: 0.0,
+            }),
+            false => None,
+        };
+
+        self.enter(to);
+    }
+
+    fn enter(&mut self, index: usize) {
+        self.current = index;
+        self.time_in_state = 0.0;
+        self.states[index].1.restart();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PlayMode, Size};
+
+    fn animator() -> Animator<u16> {
+        let sprite = Sprite::from_size(Size::new(64, 32));
+        let mut attack = Animation::from_sprite(sprite, 1, 2, 32, 32);
+        attack.set_fps(4.0);
+        let mut idle = attack.clone();
+        idle.mode = PlayMode::Loop;
+
+        let mut animator = Animator::new("idle", idle);
+        animator.add_state("attack", attack);
+        animator
+    }
+
+    #[test]
+    fn trigger_is_consumed() {
+        let mut animator = animator();
+        animator
+            .add_transition(Transition::any("attack").when(Condition::Trigger("attack".into())))
+            .unwrap();
+
+        animator.trigger("attack");
+        animator.update(0.0);
+        assert_eq!(animator.state(), "attack");
+        assert_eq!(animator.param("attack"), Some(Param::Trigger(false)));
+    }
+
+    #[test]
+    fn exit_time() {
+        let mut animator = animator();
+        animator.set_state("attack").unwrap();
+        animator.add_transition(Transition::new("attack", "idle").exit_time(1.0)).unwrap();
+
+        // Two frames of 0.25 seconds
+        animator.update(0.25);
+        assert_eq!(animator.state(), "attack");
+        animator.update(0.25);
+        assert_eq!(animator.state(), "idle");
+    }
+
+    #[test]
+    fn non_finite_dt_is_ignored() {
+        let mut animator = animator();
+        animator.set_state("attack").unwrap();
+        animator
+            .add_transition(Transition::new("attack", "idle").exit_time(1.0).crossfade(0.5))
+            .unwrap();
+
+        animator.update(0.25);
+        animator.update(f32::NAN);
+        animator.update(f32::INFINITY);
+        assert_eq!(animator.state(), "attack");
+
+        // The exit time is still reached
+        animator.update(0.25);
+        assert_eq!(animator.state(), "idle");
+        assert_eq!(animator.layers().len(), 2);
+
+        // And the cross-fade still ends
+        animator.update(f32::NAN);
+        animator.update(0.5);
+        assert_eq!(animator.layers().len(), 1);
+    }
+
+    #[test]
+    fn crossfade_layers() {
+        let mut animator = animator();
+        animator
+            .add_transition(Transition::new("idle", "attack").when(Condition::If("go".into())).crossfade(1.0))
+            .unwrap();
+
+        animator.set_bool("go", true);
+        animator.update(0.0);
+        animator.update(0.25);
+
+        let alpha = animator.layers().iter().map(|(_, a)| *a).collect::<Vec<_>>();
+        assert_eq!(alpha, vec![0.75, 0.25]);
+
+        let vertex_data = animator.vertex_data(&Transform::default());
+        let tint = vertex_data.iter().map(|vd| vd.tint.a).collect::<Vec<_>>();
+        assert_eq!(tint, vec![0.75, 0.25]);
+        assert_eq!(vertex_data[0].model[(2, 3)], 51.0);
+        assert_eq!(vertex_data[1].model[(2, 3)], 50.0);
+
+        // Nothing is pushed past the far plane
+        animator.states[1].1.sprite.z_index = Viewport::FAR;
+        let vertex_data = animator.vertex_data(&Transform::default());
+        assert_eq!(vertex_data[1].model[(2, 3)], Viewport::FAR as f32 - 1.0);
+
+        animator.update(1.0);
+        assert_eq!(animator.layers().len(), 1);
+    }
+
+    #[test]
+    fn crossfade_blends_both_layers() {
+        use crate::pixels::{Pixel, Pixels};
+        use crate::renderer::software::{Renderer, Target};
+        use crate::{Color, Position, Viewport};
+
+        let mut animator = animator();
+        animator.states[0].1.sprite.tint = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+        animator.states[1].1.sprite.tint = Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 };
+        animator
+            .add_transition(Transition::new("idle", "attack").when(Condition::If("go".into())).crossfade(1.0))
+            .unwrap();
+
+        animator.set_bool("go", true);
+        animator.update(0.0);
+        animator.update(0.25);
+
+        let texture = Pixels::from_pixel(Pixel::white(), Size::new(64, 32));
+        let mut target = Target::new(Size::new(32, 32));
+        target.clear(Color::black());
+        let viewport = Viewport::new(Position::zero(), Size::new(32, 32));
+        let vertex_data = animator.vertex_data(&Transform::default());
+        Renderer::default().render(&texture, &vertex_data, &viewport, &mut target);
+
+        // 75% red over black, then 25% green over that
+        let pixel = target.pixel(Position::new(16, 16));
+        assert_eq!((pixel.r, pixel.g, pixel.b), (143, 64, 0));
+    }
+
+    #[test]
+    fn crossfade_at_the_near_z_index() {
+        use crate::pixels::{Pixel, Pixels};
+        use crate::renderer::software::{Renderer, Target};
+        use crate::{Color, Position};
+
+        let mut animator = animator();
+        for (_, state) in &mut animator.states {
+            state.sprite.z_index = Viewport::NEAR;
+        }
+        animator.states[0].1.sprite.tint = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+        animator.states[1].1.sprite.tint = Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 };
+        animator
+            .add_transition(Transition::new("idle", "attack").when(Condition::If("go".into())).crossfade(1.0))
+            .unwrap();
+
+        animator.set_bool("go", true);
+        animator.update(0.0);
+        animator.update(0.25);
+
+        let texture = Pixels::from_pixel(Pixel::white(), Size::new(64, 32));
+        let mut target = Target::new(Size::new(32, 32));
+        target.clear(Color::black());
+        let viewport = Viewport::new(Position::zero(), Size::new(32, 32));
+        let vertex_data = animator.vertex_data(&Transform::default());
+        Renderer::default().render(&texture, &vertex_data, &viewport, &mut target);
+
+        // The layer in front is not clipped
+        let pixel = target.pixel(Position::new(16, 16));
+        assert_eq!((pixel.r, pixel.g, pixel.b), (143, 64, 0));
+    }
+
+    #[test]
+    fn unknown_state() {
+        let mut animator = animator();
+        let res = animator.add_transition(Transition::new("idle", "run"));
+        assert!(matches!(res, Err(NightmareError::UnknownState(name)) if name == "run"));
+    }
+}
@@ -0,0 +1,421 @@
+#![deny(missing_docs)]
+//! # Texture atlas
+//!
+//! Pack many small images into a single texture, so they can be
+//! rendered with a single call to [`Renderer::render`](crate::Renderer::render).
+//!
+//! Packing happens on [`Pixels`], so it doesn't need a GPU.
+//! The packed atlas is uploaded as a texture with [`PackedAtlas::upload`].
+//!
+//! Sprite sheets exported from TexturePacker or Aseprite (json) are
+//! read and written with [`SpriteSheet`], with the `json` feature.
+//!
+//! ```
+//! use nightmaregl::atlas::AtlasBuilder;
+//! use nightmaregl::pixels::{Pixel, Pixels};
+//! use nightmaregl::{Point, Rect, Size};
+//!
+//! let mut builder = AtlasBuilder::new(Size::new(256, 256));
+//! builder
+//!     .padding(1)
+//!     .add("player", Pixels::from_pixel(Pixel::white(), Size::new(16, 32)))
+//!     .add("coin", Pixels::from_pixel(Pixel::black(), Size::new(8, 8)));
+//!
+//! let atlas = builder.pack().unwrap();
+//! let coin = atlas.handle("coin").unwrap();
+//! let sprite = atlas.sprite::<f32>(coin);
+//!
+//! assert_eq!(sprite.texture_rect, Rect::new(Point::new(17.0, 0.0), Size::new(8.0, 8.0)));
+//! ```
+use std::collections::HashMap;
+use std::ops::{Div, MulAssign};
+use std::path::Path;
+
+use nalgebra::Scalar;
+use num_traits::cast::NumCast;
+use num_traits::Zero;
+
+use crate::errors::{NightmareError, Result};
+use crate::pixels::{Pixel, Pixels};
+use crate::texture::{Format, Texture};
+use crate::{Point, Rect, Size, Sprite};
+
+mod packer;
+mod sheet;
+
+use packer::Skyline;
+pub use sheet::{SheetFrame, SheetTag, SpriteSheet, TagDirection};
+
+// -----------------------------------------------------------------------------
+//     - Atlas handle -
+// -----------------------------------------------------------------------------
+/// A handle to an image in an atlas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasHandle(usize);
+
+/// A named image in the atlas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasRegion {
+    /// The name the image was added with
+    pub name: String,
+    /// The position and size of the image in the atlas,
+    /// excluding padding and extrusion.
+    pub rect: Rect<u32>,
+}
+
+// -----------------------------------------------------------------------------
+//     - Atlas builder -
+// -----------------------------------------------------------------------------
+/// Collect images and pack them into a [`PackedAtlas`].
+///
+/// The images are sorted by height, width and name before packing,
+/// so the same set of images always produces the same atlas,
+/// regardless of the order they were added in.
+pub struct AtlasBuilder {
+    max_size: Size<u32>,
+    padding: u32,
+    extrude: u32,
+    images: Vec<(String, Pixels<Pixel>)>,
+}
+
+impl AtlasBuilder {
+    /// Create a new builder. The atlas will never be larger than `max_size`.
+    pub fn new(max_size: Size<u32>) -> Self {
+        Self {
+            max_size,
+            padding: 0,
+            extrude: 0,
+            images: Vec::new(),
+        }
+    }
+
+    /// Transparent pixels between two images.
+    /// Zero by default.
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Repeat the edge pixels of every image outwards.
+    /// This prevents neighbouring images from bleeding in
+    /// when using linear filtering.
+    /// Zero by default.
+    pub fn extrude(&mut self, extrude: u32) -> &mut Self {
+        self.extrude = extrude;
+        self
+    }
+
+    /// Add an image.
+    /// Names have to be unique: adding two images with the same
+    /// name makes [`AtlasBuilder::pack`] fail.
+    pub fn add(&mut self, name: impl Into<String>, pixels: Pixels<Pixel>) -> &mut Self {
+        self.images.push((name.into(), pixels));
+        self
+    }
+
+    /// Load a png from disk and add it.
+    pub fn add_path(&mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Result<&mut Self> {
+        let pixels = Pixels::from_disk(path)?;
+        Ok(self.add(name, pixels))
+    }
+
+    /// Pack all the images.
+    /// The atlas is cropped to the area used by the images.
+    ///
+    /// Returns [`NightmareError::AtlasDuplicate`] if two images have the same name,
+    /// and [`NightmareError::AtlasFull`] if the images don't fit.
+    pub fn pack(&self) -> Result<PackedAtlas> {
+        let mut lookup = HashMap::with_capacity(self.images.len());
+        for (index, (name, _)) in self.images.iter().enumerate() {
+            if lookup.insert(name.clone(), index).is_some() {
+                return Err(NightmareError::AtlasDuplicate(name.clone()));
+            }
+        }
+
+        let border = self.extrude * 2 + self.padding;
+
+        let mut order = (0..self.images.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            let (a_name, a) = &self.images[a];
+            let (b_name, b) = &self.images[b];
+            (b.size().height, b.size().width, a_name).cmp(&(a.size().height, a.size().width, b_name))
+        });
+
+        // Every rect is followed by padding, but the padding after the
+        // last rect on either edge is cropped away, so it may go past `max_size`
+        let bounds = Size::new(
+            self.max_size.width.saturating_add(self.padding),
+            self.max_size.height.saturating_add(self.padding),
+        );
+        let mut skyline = Skyline::new(bounds);
+        let mut positions = vec![Point::zero(); self.images.len()];
+        let mut used = Size::<u32>::zero();
+
+        for index in order {
+            let (name, pixels) = &self.images[index];
+            let size = pixels.size().cast::<u32>();
+            let padded = Size::new(size.width + border, size.height + border);
+
+            let pos = match skyline.insert(padded) {
+                Some(p) => p,
+                None => return Err(NightmareError::AtlasFull(name.clone())),
+            };
+
+            used.width = used.width.max(pos.x + padded.width - self.padding);
+            used.height = used.height.max(pos.y + padded.height - self.padding);
+            positions[index] = Point::new(pos.x + self.extrude, pos.y + self.extrude);
+        }
+
+        let mut atlas = Pixels::from_pixel(Pixel::transparent(), used.cast());
+        let mut regions = Vec::with_capacity(self.images.len());
+
+        for ((name, pixels), pos) in self.images.iter().zip(positions) {
+            blit(&mut atlas, pixels, pos, self.extrude);
+            regions.push(AtlasRegion {
+                name: name.clone(),
+                rect: Rect::new(pos, pixels.size().cast()),
+            });
+        }
+
+        let inst = PackedAtlas {
+            pixels: atlas,
+            regions,
+            lookup,
+        };
+
+        Ok(inst)
+    }
+}
+
+// Copy the image into the atlas, repeating the edges `extrude` times
+fn blit(atlas: &mut Pixels<Pixel>, image: &Pixels<Pixel>, pos: Point<u32>, extrude: u32) {
+    let size = image.size();
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
+
+    let atlas_width = atlas.size().width;
+    let extrude = extrude as i64;
+
+    for y in -extrude..size.height as i64 + extrude {
+        for x in -extrude..size.width as i64 + extrude {
+            let src_x = x.clamp(0, size.width as i64 - 1) as usize;
+            let src_y = y.clamp(0, size.height as i64 - 1) as usize;
+            let dst_x = (pos.x as i64 + x) as usize;
+            let dst_y = (pos.y as i64 + y) as usize;
+            atlas[dst_y * atlas_width + dst_x] = image[src_y * size.width + src_x];
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Packed atlas -
+// -----------------------------------------------------------------------------
+/// Images packed into a single set of pixels.
+#[derive(Debug)]
+pub struct PackedAtlas {
+    pixels: Pixels<Pixel>,
+    regions: Vec<AtlasRegion>,
+    lookup: HashMap<String, usize>,
+}
+
+impl PackedAtlas {
+    /// The pixels of the entire atlas.
+    pub fn pixels(&self) -> &Pixels<Pixel> {
+        &self.pixels
+    }
+
+    /// The size of the atlas.
+    pub fn size(&self) -> Size<u32> {
+        self.pixels.size().cast()
+    }
+
+    /// All images in the order they were added.
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.regions
+    }
+
+    /// Get the handle for a named image.
+    pub fn handle(&self, name: &str) -> Option<AtlasHandle> {
+        self.lookup.get(name).map(|i| AtlasHandle(*i))
+    }
+
+    /// Get the region of an image.
+    pub fn region(&self, handle: AtlasHandle) -> &AtlasRegion {
+        &self.regions[handle.0]
+    }
+
+    /// Create a sprite showing the image.
+    pub fn sprite<T>(&self, handle: AtlasHandle) -> Sprite<T>
+    where
+        T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>,
+    {
+        let rect = self.region(handle).rect.cast();
+        let mut sprite = Sprite::from_size(self.size().cast());
+        sprite.texture_rect = rect;
+        sprite.size = rect.size;
+        sprite
+    }
+
+    /// Upload the atlas to the GPU.
+    pub fn upload<T: Copy + NumCast>(self) -> TextureAtlas<T> {
+        let texture = Texture::<T>::new()
+            .with_format(Format::Rgba)
+            .with_data(self.pixels.as_bytes(), self.size().cast());
+
+        TextureAtlas {
+            texture,
+            packed: self,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Texture atlas -
+// -----------------------------------------------------------------------------
+/// A packed atlas uploaded as a texture.
+pub struct TextureAtlas<T: Copy + NumCast> {
+    texture: Texture<T>,
+    packed: PackedAtlas,
+}
+
+impl<T> TextureAtlas<T>
+where
+    T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>,
+{
+    /// The atlas texture.
+    pub fn texture(&self) -> &Texture<T> {
+        &self.texture
+    }
+
+    /// The packed atlas this texture was created from.
+    pub fn packed(&self) -> &PackedAtlas {
+        &self.packed
+    }
+
+    /// Get the handle for a named image.
+    pub fn handle(&self, name: &str) -> Option<AtlasHandle> {
+        self.packed.handle(name)
+    }
+
+    /// Create a sprite showing the image.
+    pub fn sprite(&self, handle: AtlasHandle) -> Sprite<T> {
+        self.packed.sprite(handle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn image(size: Size<usize>, r: u8) -> Pixels<Pixel> {
+        Pixels::from_pixel(Pixel { r, ..Default::default() }, size)
+    }
+
+    fn overlaps(a: Rect<u32>, b: Rect<u32>) -> bool {
+        a.intersects(&b)
+    }
+
+    #[test]
+    fn pack_is_deterministic() {
+        let mut a = AtlasBuilder::new(Size::new(64, 64));
+        a.padding(1)
+            .add("a", image(Size::new(10, 4), 1))
+            .add("b", image(Size::new(6, 12), 2))
+            .add("c", image(Size::new(6, 12), 3));
+
+        let mut b = AtlasBuilder::new(Size::new(64, 64));
+        b.padding(1)
+            .add("c", image(Size::new(6, 12), 3))
+            .add("a", image(Size::new(10, 4), 1))
+            .add("b", image(Size::new(6, 12), 2));
+
+        let a = a.pack().unwrap();
+        let b = b.pack().unwrap();
+
+        for name in &["a", "b", "c"] {
+            let rect_a = a.region(a.handle(name).unwrap()).rect;
+            let rect_b = b.region(b.handle(name).unwrap()).rect;
+            assert_eq!(rect_a, rect_b);
+        }
+        assert_eq!(a.pixels().as_bytes(), b.pixels().as_bytes());
+    }
+
+    #[test]
+    fn images_do_not_overlap() {
+        let mut builder = AtlasBuilder::new(Size::new(64, 64));
+        builder.padding(2).extrude(1);
+        for i in 0..8 {
+            builder.add(format!("{}", i), image(Size::new(3 + i, 7 - i / 2), i as u8));
+        }
+
+        let atlas = builder.pack().unwrap();
+        let grow = |r: Rect<u32>| Rect::new(r.origin - crate::Vector::new(1, 1), r.size + Size::new(3, 3));
+
+        for (i, a) in atlas.regions().iter().enumerate() {
+            for b in &atlas.regions()[i + 1..] {
+                assert!(!overlaps(grow(a.rect), grow(b.rect)), "{:?} {:?}", a, b);
+            }
+
+            // The image was copied into its region
+            let rect = a.rect;
+            let expected = a.name.parse::<u8>().unwrap();
+            let pixel = atlas.pixels()[rect.origin.y as usize * atlas.size().width as usize + rect.origin.x as usize];
+            assert_eq!(pixel.r, expected);
+        }
+    }
+
+    #[test]
+    fn extrude_repeats_the_edges() {
+        let pixels = vec![
+            Pixel { r: 1, ..Default::default() },
+            Pixel { r: 2, ..Default::default() },
+        ];
+        let mut builder = AtlasBuilder::new(Size::new(8, 8));
+        builder.extrude(1).add("a", Pixels::new(pixels, Size::new(2, 1)));
+
+        let atlas = builder.pack().unwrap();
+        assert_eq!(atlas.size(), Size::new(4, 3));
+        assert_eq!(atlas.region(atlas.handle("a").unwrap()).rect.origin, Point::new(1, 1));
+
+        let row = |y: usize| atlas.pixels().iter().skip(y * 4).take(4).map(|p| p.r).collect::<Vec<_>>();
+        assert_eq!(row(0), vec![1, 1, 2, 2]);
+        assert_eq!(row(1), vec![1, 1, 2, 2]);
+        assert_eq!(row(2), vec![1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn atlas_full() {
+        let mut builder = AtlasBuilder::new(Size::new(8, 8));
+        builder
+            .add("a", image(Size::new(8, 6), 0))
+            .add("b", image(Size::new(4, 4), 0));
+
+        assert!(matches!(builder.pack(), Err(NightmareError::AtlasFull(name)) if name == "b"));
+    }
+
+    #[test]
+    fn padding_is_not_needed_on_the_edges() {
+        let mut builder = AtlasBuilder::new(Size::new(8, 8));
+        builder.padding(2).add("a", image(Size::new(8, 8), 1));
+
+        let atlas = builder.pack().unwrap();
+        assert_eq!(atlas.size(), Size::new(8, 8));
+        assert_eq!(atlas.region(atlas.handle("a").unwrap()).rect, Rect::new(Point::zero(), Size::new(8, 8)));
+
+        // Padding is still needed between images
+        builder.add("b", image(Size::new(1, 1), 2));
+        assert!(matches!(builder.pack(), Err(NightmareError::AtlasFull(name)) if name == "b"));
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let mut builder = AtlasBuilder::new(Size::new(8, 8));
+        builder
+            .add("a", image(Size::new(2, 2), 0))
+            .add("b", image(Size::new(2, 2), 0))
+            .add("a", image(Size::new(4, 4), 0));
+
+        assert!(matches!(builder.pack(), Err(NightmareError::AtlasDuplicate(name)) if name == "a"));
+    }
+}
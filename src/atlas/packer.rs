@@ -0,0 +1,127 @@
+// -----------------------------------------------------------------------------
+//     - Skyline packer -
+//     Places rectangles as low as possible, then as far left as possible,
+//     on top of a "skyline" made up of the top edges of the rectangles
+//     placed so far.
+// -----------------------------------------------------------------------------
+use crate::{Point, Size};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+pub(super) struct Skyline {
+    size: Size<u32>,
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    pub(super) fn new(size: Size<u32>) -> Self {
+        Self {
+            size,
+            segments: vec![Segment { x: 0, y: 0, width: size.width }],
+        }
+    }
+
+    /// Find room for a rectangle and occupy it.
+    /// Returns `None` if there is not enough room.
+    pub(super) fn insert(&mut self, size: Size<u32>) -> Option<Point<u32>> {
+        let mut best: Option<(usize, Point<u32>)> = None;
+
+        for index in 0..self.segments.len() {
+            let pos = match self.fit(index, size) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            let better = match best {
+                None => true,
+                Some((_, b)) => (pos.y + size.height, pos.x) < (b.y + size.height, b.x),
+            };
+
+            if better {
+                best = Some((index, pos));
+            }
+        }
+
+        let (index, pos) = best?;
+        self.occupy(index, pos, size);
+        Some(pos)
+    }
+
+    // The position of a rectangle resting on the skyline, starting at the given segment.
+    fn fit(&self, index: usize, size: Size<u32>) -> Option<Point<u32>> {
+        let x = self.segments[index].x;
+        if x + size.width > self.size.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = size.width as i64;
+        for segment in &self.segments[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment.y);
+            remaining -= segment.width as i64;
+        }
+
+        match y + size.height > self.size.height {
+            true => None,
+            false => Some(Point::new(x, y)),
+        }
+    }
+
+    fn occupy(&mut self, index: usize, pos: Point<u32>, size: Size<u32>) {
+        let new = Segment { x: pos.x, y: pos.y + size.height, width: size.width };
+        self.segments.insert(index, new);
+
+        // Shrink or remove the segments that are now covered
+        let right = new.x + new.width;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let segment = &mut self.segments[next];
+            if segment.x >= right {
+                break;
+            }
+
+            let segment_right = segment.x + segment.width;
+            if segment_right <= right {
+                self.segments.remove(next);
+            } else {
+                segment.width = segment_right - right;
+                segment.x = right;
+                break;
+            }
+        }
+
+        // Merge neighbours of the same height
+        let mut i = 0;
+        while i + 1 < self.segments.len() {
+            if self.segments[i].y == self.segments[i + 1].y {
+                self.segments[i].width += self.segments[i + 1].width;
+                self.segments.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fill_a_row_then_stack() {
+        let mut skyline = Skyline::new(Size::new(4, 4));
+        assert_eq!(skyline.insert(Size::new(2, 2)), Some(Point::new(0, 0)));
+        assert_eq!(skyline.insert(Size::new(2, 1)), Some(Point::new(2, 0)));
+        assert_eq!(skyline.insert(Size::new(2, 1)), Some(Point::new(2, 1)));
+        assert_eq!(skyline.insert(Size::new(4, 2)), Some(Point::new(0, 2)));
+        assert_eq!(skyline.insert(Size::new(1, 1)), None);
+    }
+}
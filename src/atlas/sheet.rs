@@ -0,0 +1,606 @@
+#![deny(missing_docs)]
+use std::ops::{Div, MulAssign};
+#[cfg(feature = "json")]
+use std::path::Path;
+
+use nalgebra::Scalar;
+use num_traits::cast::NumCast;
+use num_traits::Zero;
+
+use super::PackedAtlas;
+#[cfg(feature = "json")]
+use crate::errors::{NightmareError, Result};
+#[cfg(feature = "json")]
+use crate::json::{self, json, Value};
+use crate::{Point, Position, Rect, Size, Sprite, UvRotation};
+
+// -----------------------------------------------------------------------------
+//     - Tag direction -
+// -----------------------------------------------------------------------------
+/// The direction a tagged animation plays in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TagDirection {
+    /// First to last frame
+    Forward,
+    /// Last to first frame
+    Reverse,
+    /// First to last, then back to the first frame
+    PingPong,
+    /// Last to first, then back to the last frame
+    PingPongReverse,
+}
+
+#[cfg(feature = "json")]
+impl TagDirection {
+    fn parse(s: &str) -> Self {
+        match s {
+            "reverse" => TagDirection::Reverse,
+            "pingpong" => TagDirection::PingPong,
+            "pingpong_reverse" => TagDirection::PingPongReverse,
+            _ => TagDirection::Forward,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            TagDirection::Forward => "forward",
+            TagDirection::Reverse => "reverse",
+            TagDirection::PingPong => "pingpong",
+            TagDirection::PingPongReverse => "pingpong_reverse",
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Sheet tag -
+// -----------------------------------------------------------------------------
+/// A named range of frames, e.g. "walk" or "idle".
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetTag {
+    /// Name of the tag
+    pub name: String,
+    /// Index of the first frame
+    pub from: usize,
+    /// Index of the last frame (inclusive)
+    pub to: usize,
+    /// Play direction
+    pub direction: TagDirection,
+}
+
+// -----------------------------------------------------------------------------
+//     - Sheet frame -
+// -----------------------------------------------------------------------------
+/// A single frame in a sprite sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetFrame {
+    /// The name (file name) of the frame.
+    pub name: String,
+    /// The area of the texture containing the frame.
+    /// For a rotated frame the width and height are those of the
+    /// rotated image in the texture.
+    pub rect: Rect<u32>,
+    /// The frame is stored rotated 90 degrees clockwise.
+    pub rotated: bool,
+    /// Transparent pixels were trimmed from the frame.
+    pub trimmed: bool,
+    /// The size of the frame before trimming.
+    pub source_size: Size<u32>,
+    /// The offset of the trimmed frame inside the untrimmed frame.
+    pub source_offset: Point<u32>,
+    /// How long the frame is shown, in seconds.
+    pub duration: Option<f32>,
+}
+
+impl SheetFrame {
+    // The size, anchor and uv rotation of a sprite showing the frame.
+    // See `SpriteSheet::sprite`.
+    pub(crate) fn placement<T: Copy + NumCast + Zero>(&self) -> (Size<T>, Position<T>, UvRotation) {
+        // The frame is stored rotated clockwise
+        let (size, uv_rotation) = match self.rotated {
+            true => (Size::new(self.rect.size.height, self.rect.size.width), UvRotation::CounterClockwise90),
+            false => (self.rect.size, UvRotation::None),
+        };
+
+        // The source offset is from the top left, the anchor from the bottom left
+        let anchor = match self.trimmed {
+            true => {
+                let offset = Position::new(
+                    self.source_offset.x as f32,
+                    self.source_size.height as f32 - self.source_offset.y as f32 - size.height as f32,
+                );
+                (-offset).try_cast().unwrap_or_else(Position::zero)
+            }
+            false => Position::zero(),
+        };
+
+        (size.cast(), anchor, uv_rotation)
+    }
+}
+
+#[cfg(feature = "json")]
+impl SheetFrame {
+    fn from_value(name: String, value: &Value) -> Result<Self> {
+        let field = |object: &Value, key: &str, f: &str| -> Result<u32> {
+            field(object, f, || invalid(&format!("frame {} has a missing or invalid {}.{}", name, key, f)))
+        };
+
+        let rect_of = |key: &str| -> Result<Rect<u32>> {
+            let r = value.get(key).ok_or_else(|| invalid(&format!("frame {} is missing {}", name, key)))?;
+            let origin = Point::new(field(r, key, "x")?, field(r, key, "y")?);
+            let size = Size::new(field(r, key, "w")?, field(r, key, "h")?);
+            Ok(Rect::new(origin, size))
+        };
+
+        let rotated = value.get("rotated").and_then(Value::as_bool).unwrap_or(false);
+        let trimmed = value.get("trimmed").and_then(Value::as_bool).unwrap_or(false);
+
+        // The frame size is the size before rotation
+        let mut rect = rect_of("frame")?;
+        if rotated {
+            rect.size = Size::new(rect.size.height, rect.size.width);
+        }
+
+        let source_offset = match value.get("spriteSourceSize") {
+            Some(_) => rect_of("spriteSourceSize")?.origin,
+            None => Point::zero(),
+        };
+
+        let source_size = match value.get("sourceSize") {
+            Some(size) => Size::new(field(size, "sourceSize", "w")?, field(size, "sourceSize", "h")?),
+            None if rotated => Size::new(rect.size.height, rect.size.width),
+            None => rect.size,
+        };
+
+        let duration = value.get("duration").and_then(Value::as_f64).map(|ms| ms as f32 / 1000.0);
+
+        let inst = Self {
+            name,
+            rect,
+            rotated,
+            trimmed,
+            source_size,
+            source_offset,
+            duration,
+        };
+
+        Ok(inst)
+    }
+
+    fn to_value(&self) -> Value {
+        let rect = |x: u32, y: u32, w: u32, h: u32| json!({ "x": x, "y": y, "w": w, "h": h });
+
+        let (w, h) = match self.rotated {
+            true => (self.rect.size.height, self.rect.size.width),
+            false => (self.rect.size.width, self.rect.size.height),
+        };
+
+        let mut value = json!({
+            "frame": rect(self.rect.origin.x, self.rect.origin.y, w, h),
+            "rotated": self.rotated,
+            "trimmed": self.trimmed,
+            "spriteSourceSize": rect(self.source_offset.x, self.source_offset.y, w, h),
+            "sourceSize": { "w": self.source_size.width, "h": self.source_size.height },
+        });
+
+        if let Some(duration) = self.duration {
+            value["duration"] = json!((duration * 1000.0).round() as u32);
+        }
+
+        value
+    }
+}
+
+#[cfg(feature = "json")]
+fn invalid(msg: &str) -> NightmareError {
+    NightmareError::SpriteSheet(msg.to_string())
+}
+
+// Missing, negative and fractional values are an error, not zero
+#[cfg(feature = "json")]
+fn field(object: &Value, f: &str, error: impl FnOnce() -> NightmareError) -> Result<u32> {
+    object.get(f).and_then(json::as_u32).ok_or_else(error)
+}
+
+// -----------------------------------------------------------------------------
+//     - Sprite sheet -
+// -----------------------------------------------------------------------------
+/// Sprite sheet metadata in the json format exported by
+/// TexturePacker and Aseprite.
+///
+/// Both the "hash" (frames as an object) and the "array"
+/// (frames as a list) layouts can be read. Sheets are always
+/// written in the "hash" layout, with the tags in `meta.frameTags`
+/// like Aseprite does.
+///
+/// ```
+/// use nightmaregl::atlas::SpriteSheet;
+/// use nightmaregl::{Point, Rect, Size};
+///
+/// let json = r#"{
+///     "frames": {
+///         "walk 0": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+///         "walk 1": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 200 }
+///     },
+///     "meta": {
+///         "image": "walk.png",
+///         "size": { "w": 32, "h": 16 },
+///         "frameTags": [ { "name": "walk", "from": 0, "to": 1, "direction": "forward" } ]
+///     }
+/// }"#;
+///
+/// let sheet = SpriteSheet::from_json(json).unwrap();
+/// let sprite = sheet.sprite::<f32>("walk 1").unwrap();
+/// assert_eq!(sprite.texture_rect, Rect::new(Point::new(16.0, 0.0), Size::new(16.0, 16.0)));
+///
+/// let walk = sheet.tag_frames("walk").unwrap();
+/// assert_eq!(walk[1].duration, Some(0.2));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSheet {
+    /// The image file the frames are stored in,
+    /// relative to the json file.
+    pub image: Option<String>,
+    /// The size of the image.
+    pub size: Size<u32>,
+    /// All the frames, in order.
+    pub frames: Vec<SheetFrame>,
+    /// Tagged frame ranges.
+    pub tags: Vec<SheetTag>,
+}
+
+impl SpriteSheet {
+    #[cfg(feature = "json")]
+    /// Parse a sprite sheet from json.
+    pub fn from_json(src: &str) -> Result<Self> {
+        let root = json::parse(src)?;
+
+        let frames = match root.get("frames") {
+            Some(Value::Object(frames)) => frames
+                .iter()
+                .map(|(name, value)| SheetFrame::from_value(name.clone(), value))
+                .collect::<Result<Vec<_>>>()?,
+            Some(Value::Array(frames)) => frames
+                .iter()
+                .map(|value| {
+                    let name = value.get("filename").and_then(Value::as_str).unwrap_or_default();
+                    SheetFrame::from_value(name.to_string(), value)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => return Err(invalid("missing frames")),
+        };
+
+        let meta = root.get("meta");
+
+        let image = meta
+            .and_then(|m| m.get("image"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let size = match meta.and_then(|m| m.get("size")) {
+            Some(size) => {
+                let field = |f: &str| field(size, f, || invalid(&format!("missing or invalid meta.size.{}", f)));
+                Size::new(field("w")?, field("h")?)
+            }
+            // Without a size, assume the image ends where the last frame ends
+            None => frames.iter().fold(Size::<u32>::zero(), |size, f| {
+                Size::new(size.width.max(f.rect.max_x()), size.height.max(f.rect.max_y()))
+            }),
+        };
+
+        let tags = meta
+            .and_then(|m| m.get("frameTags"))
+            .and_then(json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| {
+                let name = tag
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid("tag has a missing or invalid name"))?
+                    .to_string();
+                let range = |f: &str| {
+                    field(tag, f, || invalid(&format!("tag {} has a missing or invalid {}", name, f))).map(|i| i as usize)
+                };
+                let from = range("from")?;
+                let to = range("to")?;
+                if from > to || to >= frames.len() {
+                    return Err(invalid(&format!("tag {} is out of range", name)));
+                }
+
+                let direction = tag
+                    .get("direction")
+                    .and_then(Value::as_str)
+                    .map(TagDirection::parse)
+                    .unwrap_or(TagDirection::Forward);
+
+                Ok(SheetTag { name, from, to, direction })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let inst = Self {
+            image,
+            size,
+            frames,
+            tags,
+        };
+
+        Ok(inst)
+    }
+
+    #[cfg(feature = "json")]
+    /// Load a sprite sheet from a json file.
+    pub fn from_disk(path: impl AsRef<Path>) -> Result<Self> {
+        let src = std::fs::read_to_string(path)?;
+        Self::from_json(&src)
+    }
+
+    /// Create a sprite sheet describing a packed atlas.
+    /// Every image in the atlas becomes a frame.
+    pub fn from_atlas(atlas: &PackedAtlas, image: Option<String>) -> Self {
+        let frames = atlas
+            .regions()
+            .iter()
+            .map(|region| SheetFrame {
+                name: region.name.clone(),
+                rect: region.rect,
+                rotated: false,
+                trimmed: false,
+                source_size: region.rect.size,
+                source_offset: Point::zero(),
+                duration: None,
+            })
+            .collect();
+
+        Self {
+            image,
+            size: atlas.size(),
+            frames,
+            tags: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    /// Serialise the sprite sheet as json.
+    pub fn to_json(&self) -> String {
+        let frames = self
+            .frames
+            .iter()
+            .map(|frame| (frame.name.clone(), frame.to_value()))
+            .collect::<serde_json::Map<_, _>>();
+
+        let tags = self
+            .tags
+            .iter()
+            .map(|tag| {
+                json!({
+                    "name": tag.name,
+                    "from": tag.from,
+                    "to": tag.to,
+                    "direction": tag.direction.as_str(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut meta = json!({ "app": "nightmaregl" });
+        if let Some(image) = &self.image {
+            meta["image"] = json!(image);
+        }
+        meta["format"] = json!("RGBA8888");
+        meta["size"] = json!({ "w": self.size.width, "h": self.size.height });
+        meta["scale"] = json!("1");
+        meta["frameTags"] = json!(tags);
+
+        let root = json!({ "frames": frames, "meta": meta });
+        serde_json::to_string_pretty(&root).expect("a json value always serialises")
+    }
+
+    #[cfg(feature = "json")]
+    /// Write the sprite sheet to disk as json.
+    pub fn write_to_disk(&self, dst: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(dst, self.to_json())?;
+        Ok(())
+    }
+
+    /// Find a frame by name.
+    pub fn frame(&self, name: &str) -> Option<&SheetFrame> {
+        self.frames.iter().find(|f| f.name == name)
+    }
+
+    /// Find a tag by name.
+    pub fn tag(&self, name: &str) -> Option<&SheetTag> {
+        self.tags.iter().find(|t| t.name == name)
+    }
+
+    /// The frames of a tag, from first to last,
+    /// regardless of the direction of the tag.
+    pub fn tag_frames(&self, name: &str) -> Option<&[SheetFrame]> {
+        let tag = self.tag(name)?;
+        self.frames.get(tag.from..=tag.to)
+    }
+
+    /// Create a sprite showing a frame.
+    ///
+    /// A rotated frame is rotated back with the `uv_rotation` of the sprite.
+    /// A trimmed frame is offset with the anchor of the sprite, so it is
+    /// drawn where it would be if it wasn't trimmed. With an unsigned `T`
+    /// the anchor can't be negative, and the offset is left out.
+    pub fn sprite<T>(&self, name: &str) -> Option<Sprite<T>>
+    where
+        T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>,
+    {
+        let frame = self.frame(name)?;
+        let (size, anchor, uv_rotation) = frame.placement();
+
+        let mut sprite = Sprite::from_size(self.size.cast());
+        sprite.texture_rect = frame.rect.cast();
+        sprite.size = size;
+        sprite.anchor = anchor;
+        sprite.uv_rotation = uv_rotation;
+
+        Some(sprite)
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::atlas::AtlasBuilder;
+    use crate::pixels::{Pixel, Pixels};
+
+    const TEXTURE_PACKER_ARRAY: &str = r#"{"frames": [
+        {
+            "filename": "hero.png",
+            "frame": {"x":2,"y":2,"w":10,"h":20},
+            "rotated": true,
+            "trimmed": true,
+            "spriteSourceSize": {"x":3,"y":1,"w":10,"h":20},
+            "sourceSize": {"w":16,"h":24}
+        }],
+        "meta": {"image": "sheet.png", "size": {"w":64,"h":64}}
+    }"#;
+
+    #[test]
+    fn read_array_layout() {
+        let sheet = SpriteSheet::from_json(TEXTURE_PACKER_ARRAY).unwrap();
+        let frame = sheet.frame("hero.png").unwrap();
+
+        assert_eq!(frame.rect, Rect::new(Point::new(2, 2), Size::new(20, 10)));
+        assert!(frame.rotated);
+        assert!(frame.trimmed);
+        assert_eq!(frame.source_offset, Point::new(3, 1));
+        assert_eq!(frame.source_size, Size::new(16, 24));
+        assert_eq!(sheet.image.as_deref(), Some("sheet.png"));
+        assert_eq!(frame.duration, None);
+    }
+
+    #[test]
+    fn render_rotated_trimmed_frame() {
+        use crate::renderer::software::{Renderer, Target};
+        use crate::{Color, Transform, VertexData, Viewport};
+
+        let red = Pixel { r: 255, a: 255, ..Default::default() };
+        let green = Pixel { g: 255, a: 255, ..Default::default() };
+
+        // The 10x20 frame is stored rotated clockwise at 2x2 as 20x10,
+        // so its top left pixel ends up at the top right.
+        let mut texture = Pixels::from_pixel(Pixel::transparent(), Size::new(64, 64));
+        for y in 2..12 {
+            for x in 2..22 {
+                texture[y * 64 + x] = green;
+            }
+        }
+        texture[2 * 64 + 21] = red;
+
+        let sheet = SpriteSheet::from_json(TEXTURE_PACKER_ARRAY).unwrap();
+        let sprite = sheet.sprite::<f32>("hero.png").unwrap();
+        assert_eq!(sprite.size, Size::new(10.0, 20.0));
+
+        // Drawn inside the untrimmed 16x24 frame, 3 pixels from the left and the bottom
+        let size = Size::new(16, 24);
+        let mut target = Target::new(size);
+        target.clear(Color::black());
+        let viewport = Viewport::new(Position::zero(), size.cast());
+        let vertex_data = VertexData::new(&sprite, &Transform::default());
+        Renderer::default().render(&texture, &[vertex_data], &viewport, &mut target);
+
+        for y in 0..24 {
+            for x in 0..16 {
+                let expected = match (x, y) {
+                    (3, 22) => red,
+                    (3..=12, 3..=22) => green,
+                    _ => Pixel::black(),
+                };
+                assert_eq!(target.pixel(Position::new(x, y)), expected, "{}x{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut sheet = SpriteSheet::from_json(TEXTURE_PACKER_ARRAY).unwrap();
+        sheet.frames[0].duration = Some(0.15);
+        sheet.tags.push(SheetTag {
+            name: "idle".into(),
+            from: 0,
+            to: 0,
+            direction: TagDirection::PingPong,
+        });
+
+        let read = SpriteSheet::from_json(&sheet.to_json()).unwrap();
+        assert_eq!(read, sheet);
+    }
+
+    #[test]
+    fn tag_directions() {
+        let directions = [
+            TagDirection::Forward,
+            TagDirection::Reverse,
+            TagDirection::PingPong,
+            TagDirection::PingPongReverse,
+        ];
+
+        for direction in directions.iter().copied() {
+            assert_eq!(TagDirection::parse(direction.as_str()), direction);
+        }
+        assert_eq!(TagDirection::parse("pingpong_reverse"), TagDirection::PingPongReverse);
+        assert_eq!(TagDirection::parse("sideways"), TagDirection::Forward);
+    }
+
+    #[test]
+    fn from_atlas() {
+        let mut builder = AtlasBuilder::new(Size::new(32, 32));
+        builder
+            .add("a", Pixels::from_pixel(Pixel::white(), Size::new(4, 4)))
+            .add("b", Pixels::from_pixel(Pixel::white(), Size::new(8, 2)));
+        let atlas = builder.pack().unwrap();
+
+        let sheet = SpriteSheet::from_atlas(&atlas, Some("atlas.png".into()));
+        let read = SpriteSheet::from_json(&sheet.to_json()).unwrap();
+
+        assert_eq!(read.size, atlas.size());
+        for region in atlas.regions() {
+            assert_eq!(read.frame(&region.name).unwrap().rect, region.rect);
+        }
+    }
+
+    #[test]
+    fn invalid_frame_fields() {
+        let frames = [
+            (r#"{"x": 0, "y": 0, "w": 8}"#, "frame.h"),
+            (r#"{"x": -1, "y": 0, "w": 8, "h": 8}"#, "frame.x"),
+            (r#"{"x": 0, "y": 0.5, "w": 8, "h": 8}"#, "frame.y"),
+        ];
+
+        for (frame, field) in &frames {
+            let json = format!(r#"{{"frames": {{"a.png": {{"frame": {}}}}}}}"#, frame);
+            let err = SpriteSheet::from_json(&json).unwrap_err();
+            assert!(matches!(&err, NightmareError::SpriteSheet(msg) if msg.contains("a.png") && msg.contains(field)), "{}", err);
+        }
+
+        let json = r#"{"frames": {"a.png": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "sourceSize": {"w": "8", "h": 8}}}}"#;
+        let err = SpriteSheet::from_json(json).unwrap_err();
+        assert!(matches!(&err, NightmareError::SpriteSheet(msg) if msg.contains("sourceSize.w")), "{}", err);
+    }
+
+    #[test]
+    fn tag_out_of_range() {
+        let json = r#"{"frames": {}, "meta": {"frameTags": [{"name": "x", "from": 0, "to": 2}]}}"#;
+        assert!(SpriteSheet::from_json(json).is_err());
+    }
+
+    #[test]
+    fn invalid_tag_fields() {
+        let frames = r#"{"a.png": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}}"#;
+        let tags = [
+            (r#"{"name": "idle", "from": 0}"#, "to"),
+            (r#"{"name": "idle", "from": -1, "to": 0}"#, "from"),
+            (r#"{"from": 0, "to": 0}"#, "name"),
+        ];
+
+        for (tag, field) in &tags {
+            let json = format!(r#"{{"frames": {}, "meta": {{"frameTags": [{}]}}}}"#, frames, tag);
+            let err = SpriteSheet::from_json(&json).unwrap_err();
+            assert!(matches!(&err, NightmareError::SpriteSheet(msg) if msg.contains(field)), "{}", err);
+        }
+    }
+}
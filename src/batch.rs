@@ -0,0 +1,303 @@
+#![deny(missing_docs)]
+use std::ops::{Div, MulAssign, Range};
+
+use nalgebra::Scalar;
+use num_traits::cast::NumCast;
+use num_traits::Zero;
+
+use crate::{Context, Renderer, Result, Sprite, Texture, Transform, VertexData, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Batch stats -
+// -----------------------------------------------------------------------------
+/// Statistics from the last call to [`SpriteBatch::render`].
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct BatchStats {
+    /// Number of calls to [`Renderer::render`].
+    pub draw_calls: usize,
+    /// Total number of instances rendered.
+    pub instances: usize,
+    /// Number of times the texture changed between two draw calls,
+    /// including the first texture.
+    pub texture_switches: usize,
+}
+
+// -----------------------------------------------------------------------------
+//     - Entry -
+// -----------------------------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+struct Entry {
+    texture: usize,
+    z_index: i32,
+    vertex_data: VertexData,
+}
+
+// A range of vertex data sharing the same texture
+#[derive(Debug, PartialEq)]
+struct Run {
+    texture: usize,
+    range: Range<usize>,
+}
+
+// -----------------------------------------------------------------------------
+//     - Sprite batch -
+// -----------------------------------------------------------------------------
+/// Collect sprites in any order and render them with as few
+/// draw calls as possible.
+///
+/// * Opaque sprites are grouped by texture and drawn front to back
+///   (lowest `z_index` first) within each texture. As they are opaque
+///   the depth test takes care of the order between textures.
+/// * Translucent sprites are drawn after the opaque ones, back to front,
+///   so they blend with whatever is behind them. Translucent sprites
+///   with the same `z_index` are grouped by texture, and consecutive
+///   translucent sprites sharing a texture are drawn in a single call.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # fn run(mut context: Context, viewport: Viewport, renderer: Renderer<VertexData>, player: Texture<f32>, ghost: Texture<f32>) -> Result<()> {
+/// let mut batch = SpriteBatch::new();
+///
+/// let sprite = Sprite::new(&player);
+/// batch.add(&sprite, &Transform::default(), &player);
+///
+/// let sprite = Sprite::new(&ghost);
+/// batch.add_translucent(&sprite, &Transform::new(Position::new(10.0, 10.0)), &ghost);
+///
+/// batch.render(&renderer, &viewport, &mut context)?;
+/// assert_eq!(batch.stats().draw_calls, 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SpriteBatch<'a, T: Copy + NumCast> {
+    textures: Vec<&'a Texture<T>>,
+    opaque: Vec<Entry>,
+    translucent: Vec<Entry>,
+    stats: BatchStats,
+}
+
+impl<'a, T: Copy + NumCast> SpriteBatch<'a, T> {
+    /// Create an empty sprite batch.
+    pub fn new() -> Self {
+        Self {
+            textures: Vec::new(),
+            opaque: Vec::new(),
+            translucent: Vec::new(),
+            stats: BatchStats::default(),
+        }
+    }
+
+    /// Add an opaque sprite.
+    pub fn add<U>(&mut self, sprite: &Sprite<U>, transform: &Transform<U>, texture: &'a Texture<T>)
+    where
+        U: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = U>,
+    {
+        let texture = self.texture_index(texture);
+        let entries = VertexData::instances(sprite, transform).into_iter().map(|vertex_data| Entry {
+            texture,
+            z_index: sprite.z_index,
+            vertex_data,
+        });
+        self.opaque.extend(entries);
+    }
+
+    /// Add a sprite that has partially transparent pixels.
+    pub fn add_translucent<U>(&mut self, sprite: &Sprite<U>, transform: &Transform<U>, texture: &'a Texture<T>)
+    where
+        U: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = U>,
+    {
+        let texture = self.texture_index(texture);
+        let entries = VertexData::instances(sprite, transform).into_iter().map(|vertex_data| Entry {
+            texture,
+            z_index: sprite.z_index,
+            vertex_data,
+        });
+        self.translucent.extend(entries);
+    }
+
+    /// Number of instances waiting to be rendered.
+    /// A nine slice sprite is more than one instance.
+    pub fn len(&self) -> usize {
+        self.opaque.len() + self.translucent.len()
+    }
+
+    /// Returns true if there are no sprites to render.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Statistics from the last render.
+    pub fn stats(&self) -> BatchStats {
+        self.stats
+    }
+
+    /// Remove all sprites without rendering them.
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.translucent.clear();
+        self.textures.clear();
+    }
+
+    /// Render all the sprites and clear the batch.
+    pub fn render(
+        &mut self,
+        renderer: &Renderer<VertexData>,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        let (vertex_data, runs) = self.plan();
+
+        let mut res = Ok(());
+        for run in &runs {
+            let texture = self.textures[run.texture];
+            res = renderer.render(texture, &vertex_data[run.range.clone()], viewport, context);
+            if res.is_err() {
+                break;
+            }
+        }
+
+        self.stats = stats(&runs);
+        self.clear();
+        res
+    }
+
+    fn texture_index(&mut self, texture: &'a Texture<T>) -> usize {
+        match self.textures.iter().position(|t| t.id() == texture.id()) {
+            Some(index) => index,
+            None => {
+                self.textures.push(texture);
+                self.textures.len() - 1
+            }
+        }
+    }
+
+    // Sort the entries and split them up in runs of the same texture
+    fn plan(&mut self) -> (Vec<VertexData>, Vec<Run>) {
+        self.opaque.sort_by_key(|e| (e.texture, e.z_index));
+        // Sprites at the same depth have no back to front order,
+        // so they are grouped by texture
+        self.translucent.sort_by_key(|e| (std::cmp::Reverse(e.z_index), e.texture));
+
+        let mut vertex_data = Vec::with_capacity(self.len());
+        let mut runs: Vec<Run> = Vec::new();
+
+        for entries in [&self.opaque, &self.translucent].iter() {
+            let first_run = runs.len();
+
+            for entry in entries.iter() {
+                let index = vertex_data.len();
+                vertex_data.push(entry.vertex_data);
+
+                // Opaque and translucent sprites are never in the same run
+                match runs[first_run..].last_mut() {
+                    Some(run) if run.texture == entry.texture => run.range.end = index + 1,
+                    _ => runs.push(Run {
+                        texture: entry.texture,
+                        range: index..index + 1,
+                    }),
+                }
+            }
+        }
+
+        (vertex_data, runs)
+    }
+}
+
+impl<'a, T: Copy + NumCast> Default for SpriteBatch<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn stats(runs: &[Run]) -> BatchStats {
+    let texture_switches = runs
+        .iter()
+        .enumerate()
+        .filter(|(i, run)| *i == 0 || runs[i - 1].texture != run.texture)
+        .count();
+
+    BatchStats {
+        draw_calls: runs.len(),
+        instances: runs.iter().map(|r| r.range.len()).sum(),
+        texture_switches,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Size;
+
+    fn entry(texture: usize, z_index: i32) -> Entry {
+        let mut sprite = Sprite::<f32>::from_size(Size::new(1.0, 1.0));
+        sprite.z_index = z_index;
+        Entry {
+            texture,
+            z_index,
+            vertex_data: VertexData::new(&sprite, &Transform::default()),
+        }
+    }
+
+    fn z_order(vertex_data: &[VertexData]) -> Vec<i32> {
+        vertex_data.iter().map(|vd| vd.model[(2, 3)] as i32).collect()
+    }
+
+    #[test]
+    fn opaque_sprites_are_grouped_by_texture() {
+        let mut batch = SpriteBatch::<f32>::new();
+        batch.opaque = vec![entry(0, 3), entry(1, 2), entry(0, 1), entry(1, 4)];
+
+        let (vertex_data, runs) = batch.plan();
+
+        assert_eq!(z_order(&vertex_data), vec![1, 3, 2, 4]);
+        assert_eq!(runs, vec![
+            Run { texture: 0, range: 0..2 },
+            Run { texture: 1, range: 2..4 },
+        ]);
+    }
+
+    #[test]
+    fn translucent_sprites_are_drawn_back_to_front() {
+        let mut batch = SpriteBatch::<f32>::new();
+        batch.translucent = vec![entry(0, 1), entry(1, 5), entry(1, 4), entry(0, 3)];
+
+        let (vertex_data, runs) = batch.plan();
+
+        assert_eq!(z_order(&vertex_data), vec![5, 4, 3, 1]);
+        assert_eq!(runs, vec![
+            Run { texture: 1, range: 0..2 },
+            Run { texture: 0, range: 2..4 },
+        ]);
+    }
+
+    #[test]
+    fn translucent_sprites_are_drawn_after_opaque() {
+        let mut batch = SpriteBatch::<f32>::new();
+        batch.opaque = vec![entry(0, 1)];
+        batch.translucent = vec![entry(0, 2), entry(0, 0)];
+
+        let (_, runs) = batch.plan();
+        assert_eq!(runs, vec![
+            Run { texture: 0, range: 0..1 },
+            Run { texture: 0, range: 1..3 },
+        ]);
+
+        let stats = stats(&runs);
+        assert_eq!(stats, BatchStats { draw_calls: 2, instances: 3, texture_switches: 1 });
+    }
+
+    #[test]
+    fn translucent_sprites_at_the_same_depth_are_grouped_by_texture() {
+        let mut batch = SpriteBatch::<f32>::new();
+        batch.translucent = vec![entry(0, 1), entry(1, 1), entry(0, 1), entry(1, 1)];
+
+        let (_, runs) = batch.plan();
+        assert_eq!(runs, vec![
+            Run { texture: 0, range: 0..2 },
+            Run { texture: 1, range: 2..4 },
+        ]);
+
+        let stats = stats(&runs);
+        assert_eq!(stats, BatchStats { draw_calls: 2, instances: 4, texture_switches: 2 });
+    }
+}
@@ -0,0 +1,292 @@
+#![deny(missing_docs)]
+use gl33::global_loader::*;
+use gl33::*;
+
+use crate::Color;
+
+// -----------------------------------------------------------------------------
+//     - Blend factor -
+// -----------------------------------------------------------------------------
+/// What the source (the fragment being drawn) or the destination
+/// (what is already in the frame buffer) is multiplied with before
+/// the two are combined.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlendFactor {
+    /// 0
+    Zero,
+    /// 1
+    One,
+    /// The source colour
+    SrcColor,
+    /// 1 - the source colour
+    OneMinusSrcColor,
+    /// The destination colour
+    DstColor,
+    /// 1 - the destination colour
+    OneMinusDstColor,
+    /// The source alpha
+    SrcAlpha,
+    /// 1 - the source alpha
+    OneMinusSrcAlpha,
+    /// The destination alpha
+    DstAlpha,
+    /// 1 - the destination alpha
+    OneMinusDstAlpha,
+}
+
+impl BlendFactor {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendFactor::Zero => GL_ZERO,
+            BlendFactor::One => GL_ONE,
+            BlendFactor::SrcColor => GL_SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => GL_ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => GL_DST_COLOR,
+            BlendFactor::OneMinusDstColor => GL_ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => GL_SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => GL_ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => GL_DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => GL_ONE_MINUS_DST_ALPHA,
+        }
+    }
+
+    // The factor for one channel (0 to 3, where 3 is alpha)
+    fn weight(self, src: [f32; 4], dst: [f32; 4], channel: usize) -> f32 {
+        match self {
+            BlendFactor::Zero => 0.0,
+            BlendFactor::One => 1.0,
+            BlendFactor::SrcColor => src[channel],
+            BlendFactor::OneMinusSrcColor => 1.0 - src[channel],
+            BlendFactor::DstColor => dst[channel],
+            BlendFactor::OneMinusDstColor => 1.0 - dst[channel],
+            BlendFactor::SrcAlpha => src[3],
+            BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+            BlendFactor::DstAlpha => dst[3],
+            BlendFactor::OneMinusDstAlpha => 1.0 - dst[3],
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Blend equation -
+// -----------------------------------------------------------------------------
+/// How the source and destination are combined, after
+/// they have been multiplied by their factors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlendEquation {
+    /// source + destination
+    Add,
+    /// source - destination
+    Subtract,
+    /// destination - source
+    ReverseSubtract,
+    /// The smallest of the two. The factors are ignored.
+    Min,
+    /// The largest of the two. The factors are ignored.
+    Max,
+}
+
+impl BlendEquation {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendEquation::Add => GL_FUNC_ADD,
+            BlendEquation::Subtract => GL_FUNC_SUBTRACT,
+            BlendEquation::ReverseSubtract => GL_FUNC_REVERSE_SUBTRACT,
+            BlendEquation::Min => GL_MIN,
+            BlendEquation::Max => GL_MAX,
+        }
+    }
+}
+
+/// A blend function for either the colour or the alpha channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BlendFunc {
+    /// Multiplied with the source
+    pub src: BlendFactor,
+    /// Multiplied with the destination
+    pub dst: BlendFactor,
+    /// How the two are combined
+    pub equation: BlendEquation,
+}
+
+impl BlendFunc {
+    /// Add the source and destination, multiplied by their factors.
+    pub fn add(src: BlendFactor, dst: BlendFactor) -> Self {
+        Self {
+            src,
+            dst,
+            equation: BlendEquation::Add,
+        }
+    }
+
+    fn apply(&self, src: [f32; 4], dst: [f32; 4], channel: usize) -> f32 {
+        let s = src[channel] * self.src.weight(src, dst, channel);
+        let d = dst[channel] * self.dst.weight(src, dst, channel);
+
+        let value = match self.equation {
+            BlendEquation::Add => s + d,
+            BlendEquation::Subtract => s - d,
+            BlendEquation::ReverseSubtract => d - s,
+            BlendEquation::Min => src[channel].min(dst[channel]),
+            BlendEquation::Max => src[channel].max(dst[channel]),
+        };
+
+        value.clamp(0.0, 1.0)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Blend mode -
+// -----------------------------------------------------------------------------
+/// How a fragment is combined with what is already in the frame buffer.
+///
+/// Set it on a [`crate::Renderer`] (or a [`crate::ShapeRenderer`]), or
+/// pass it to [`crate::Renderer::render_blended`] for a single call.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # fn run(mut context: Context, mut renderer: Renderer<VertexData>, texture: Texture<f32>, lights: Vec<VertexData>, viewport: Viewport) -> Result<()> {
+/// // Lights brighten whatever is behind them
+/// renderer.render_blended(&texture, &lights, BlendMode::Additive, &viewport, &mut context)?;
+///
+/// // Or for every call
+/// renderer.blend_mode = BlendMode::Additive;
+/// renderer.render(&texture, &lights, &viewport, &mut context)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Draw on top, using the alpha of the source. This is the default.
+    #[default]
+    Alpha,
+    /// Same as `Alpha`, for colours that are already multiplied by their alpha.
+    PremultipliedAlpha,
+    /// Add the colour, scaled by its alpha. Good for lights, fire and sparks.
+    Additive,
+    /// Multiply with the colour in the frame buffer. Good for shadows and tinting.
+    Multiply,
+    /// The opposite of multiply: brightens without going past white.
+    Screen,
+    /// Overwrite the frame buffer, ignoring alpha.
+    /// This turns blending off.
+    Replace,
+    /// Separate functions for the colour and alpha channels.
+    Custom {
+        /// Used for red, green and blue
+        color: BlendFunc,
+        /// Used for alpha
+        alpha: BlendFunc,
+    },
+}
+
+impl BlendMode {
+    /// The colour and alpha functions of the mode,
+    /// or `None` if blending is turned off.
+    pub fn funcs(self) -> Option<(BlendFunc, BlendFunc)> {
+        use BlendFactor::*;
+
+        // Leave the alpha in the frame buffer alone
+        let keep_alpha = BlendFunc::add(Zero, One);
+
+        let funcs = match self {
+            BlendMode::Alpha => {
+                let func = BlendFunc::add(SrcAlpha, OneMinusSrcAlpha);
+                (func, func)
+            }
+            BlendMode::PremultipliedAlpha => {
+                let func = BlendFunc::add(One, OneMinusSrcAlpha);
+                (func, func)
+            }
+            BlendMode::Additive => (BlendFunc::add(SrcAlpha, One), keep_alpha),
+            BlendMode::Multiply => (BlendFunc::add(DstColor, Zero), keep_alpha),
+            BlendMode::Screen => (BlendFunc::add(One, OneMinusSrcColor), keep_alpha),
+            BlendMode::Replace => return None,
+            BlendMode::Custom { color, alpha } => (color, alpha),
+        };
+
+        Some(funcs)
+    }
+
+    /// Blend a colour with what is already in the frame buffer,
+    /// the same way the GPU does.
+    pub fn blend(self, src: Color, dst: Color) -> Color {
+        let (color, alpha) = match self.funcs() {
+            Some(funcs) => funcs,
+            None => return src,
+        };
+
+        let src = [src.r, src.g, src.b, src.a];
+        let dst = [dst.r, dst.g, dst.b, dst.a];
+
+        Color {
+            r: color.apply(src, dst, 0),
+            g: color.apply(src, dst, 1),
+            b: color.apply(src, dst, 2),
+            a: alpha.apply(src, dst, 3),
+        }
+    }
+
+    // Change the GL blend state from `current` (if known) to `self`.
+    pub(crate) fn apply(self, current: Option<BlendMode>) {
+        let current = current.map(BlendMode::funcs);
+        let funcs = self.funcs();
+
+        match (current, funcs) {
+            (Some(Some(_)), Some(_)) => {}
+            (_, Some(_)) => unsafe { glEnable(GL_BLEND) },
+            (_, None) => unsafe { glDisable(GL_BLEND) },
+        }
+
+        if let Some((color, alpha)) = funcs {
+            if current != Some(funcs) {
+                unsafe {
+                    glBlendFuncSeparate(color.src.gl(), color.dst.gl(), alpha.src.gl(), alpha.dst.gl());
+                    glBlendEquationSeparate(color.equation.gl(), alpha.equation.gl());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn color(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+
+    #[test]
+    fn built_in_modes() {
+        let src = color(1.0, 0.5, 0.0, 0.5);
+        let dst = color(0.0, 0.5, 1.0, 1.0);
+
+        assert_eq!(BlendMode::Alpha.blend(src, dst), color(0.5, 0.5, 0.5, 0.75));
+        assert_eq!(BlendMode::PremultipliedAlpha.blend(src, dst), color(1.0, 0.75, 0.5, 1.0));
+        assert_eq!(BlendMode::Additive.blend(src, dst), color(0.5, 0.75, 1.0, 1.0));
+        assert_eq!(BlendMode::Multiply.blend(src, dst), color(0.0, 0.25, 0.0, 1.0));
+        assert_eq!(BlendMode::Screen.blend(src, dst), color(1.0, 0.75, 1.0, 1.0));
+        assert_eq!(BlendMode::Replace.blend(src, dst), src);
+    }
+
+    #[test]
+    fn custom_mode() {
+        let darken = BlendMode::Custom {
+            color: BlendFunc {
+                src: BlendFactor::One,
+                dst: BlendFactor::One,
+                equation: BlendEquation::ReverseSubtract,
+            },
+            alpha: BlendFunc {
+                src: BlendFactor::One,
+                dst: BlendFactor::One,
+                equation: BlendEquation::Max,
+            },
+        };
+
+        let src = color(0.25, 0.5, 1.0, 0.5);
+        let dst = color(1.0, 1.0, 0.5, 0.25);
+        assert_eq!(darken.blend(src, dst), color(0.75, 0.5, 0.0, 0.5));
+    }
+}
@@ -0,0 +1,356 @@
+#![deny(missing_docs)]
+use nalgebra::Matrix3;
+
+use crate::{Affine, Point, Position, Rect, Rotation, Size, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Camera -
+// -----------------------------------------------------------------------------
+/// A 2D camera, driving the view matrix of a [`Viewport`].
+///
+/// The camera position is the point of the world shown in the middle
+/// of the viewport. World coordinates are the same as the coordinates
+/// of a [`crate::Transform`], before the renderer multiplies them with
+/// its `pixel_size`, so set [`Camera2D::pixel_size`] to the same value
+/// as the renderer.
+///
+/// ```
+/// use nightmaregl::{Camera2D, Point, Position, Size, Viewport};
+///
+/// let mut viewport = Viewport::new(Position::zero(), Size::new(800, 600));
+/// let mut camera = Camera2D::new(Position::new(1000.0, 1000.0));
+/// camera.zoom = 2.0;
+/// camera.apply(&mut viewport);
+///
+/// // The mouse in the middle of the window is over the camera position
+/// let world = camera.screen_to_world(Point::new(400.0, 300.0), 600, &viewport);
+/// assert_eq!(world, Point::new(1000.0, 1000.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Camera2D {
+    /// The point in the world at the middle of the viewport.
+    pub position: Position<f32>,
+    /// Values above one zoom in.
+    pub zoom: f32,
+    /// Rotation of the camera. A positive rotation turns
+    /// the world clockwise on the screen.
+    pub rotation: Rotation<f32>,
+    /// This should be the same as the `pixel_size` of the renderer.
+    pub pixel_size: i32,
+    /// Keep the visible area inside these bounds, in world coordinates.
+    /// If the bounds are smaller than the visible area, they are centred.
+    pub bounds: Option<Rect<f32>>,
+    /// The target can move inside this area around the camera position
+    /// without the camera following, in world coordinates.
+    pub deadzone: Size<f32>,
+    /// How smoothly the camera follows the target: roughly the number of
+    /// seconds to cover two thirds of the distance. Zero snaps to the target.
+    pub follow_smoothing: f32,
+    /// Largest offset of the screen shake, in world coordinates.
+    pub max_shake_offset: f32,
+    /// Largest rotation of the screen shake.
+    pub max_shake_angle: Rotation<f32>,
+    /// How much trauma is removed per second.
+    pub trauma_decay: f32,
+    /// How fast the screen shakes.
+    pub shake_frequency: f32,
+    /// Seed for the shake noise. Two cameras with the same
+    /// seed shake the same way.
+    pub seed: u32,
+    trauma: f32,
+    time: f32,
+}
+
+impl Camera2D {
+    /// Create a camera looking at `position`.
+    pub fn new(position: Position<f32>) -> Self {
+        Self {
+            position,
+            zoom: 1.0,
+            rotation: Rotation::zero(),
+            pixel_size: 1,
+            bounds: None,
+            deadzone: Size::zero(),
+            follow_smoothing: 0.0,
+            max_shake_offset: 10.0,
+            max_shake_angle: Rotation::degrees(5.0),
+            trauma_decay: 1.0,
+            shake_frequency: 15.0,
+            seed: 0,
+            trauma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Move towards the target, if it's outside the deadzone.
+    pub fn follow(&mut self, target: Position<f32>, dt: f32) {
+        let half = self.deadzone / 2.0;
+        let offset = target - self.position;
+
+        let outside = |offset: f32, half: f32| match offset {
+            o if o > half => o - half,
+            o if o < -half => o + half,
+            _ => 0.0,
+        };
+
+        let desired = self.position + Position::new(
+            outside(offset.x, half.width),
+            outside(offset.y, half.height),
+        );
+
+        let t = match self.follow_smoothing > 0.0 {
+            true => 1.0 - (-dt / self.follow_smoothing).exp(),
+            false => 1.0,
+        };
+
+        self.position = self.position.lerp(desired, t);
+    }
+
+    /// Add trauma (0.0 to 1.0) to shake the screen.
+    /// The shake grows with the square of the trauma,
+    /// and the trauma decays over time.
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    /// The current trauma.
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Advance the screen shake.
+    /// A `dt` that is NaN or infinite is ignored, as it would
+    /// break the shake for good.
+    pub fn update(&mut self, dt: f32) {
+        if !dt.is_finite() {
+            return;
+        }
+
+        self.time += dt;
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+    }
+
+    /// Set the view matrix of the viewport.
+    pub fn apply(&self, viewport: &mut Viewport) {
+        viewport.set_view(self.view(viewport).into());
+    }
+
+    /// Convert a position in the window (as given by `Event::MouseMoved`,
+    /// where 0, 0 is the top left corner) to the world.
+    pub fn screen_to_world(&self, point: Point<f32>, window_height: i32, viewport: &Viewport) -> Point<f32> {
+        let local = Point::new(
+            point.x - viewport.position.x as f32,
+            (window_height - viewport.position.y) as f32 - point.y,
+        );
+
+        let inverse = self.view(viewport).inverse().unwrap_or_default();
+        let scaled = inverse.transform_point(local);
+        (scaled.to_vector() / self.pixel_size as f32).to_point()
+    }
+
+    /// Convert a position in the world to the window,
+    /// where 0, 0 is the top left corner.
+    pub fn world_to_screen(&self, point: Point<f32>, window_height: i32, viewport: &Viewport) -> Point<f32> {
+        let scaled = (point.to_vector() * self.pixel_size as f32).to_point();
+        let local = self.view(viewport).transform_point(scaled);
+
+        Point::new(
+            local.x + viewport.position.x as f32,
+            (window_height - viewport.position.y) as f32 - local.y,
+        )
+    }
+
+    /// The area of the world that is visible in the viewport,
+    /// ignoring rotation and shake.
+    pub fn visible_rect(&self, viewport: &Viewport) -> Rect<f32> {
+        let size = self.visible_size(viewport);
+        let centre = self.clamped_position(viewport);
+        Rect::new((centre - size.to_vector() / 2.0).to_point(), size)
+    }
+
+    // The view as an affine transform from scaled world coordinates
+    // (world * pixel size) to the viewport.
+    fn view(&self, viewport: &Viewport) -> Affine {
+        let (offset, angle) = self.shake();
+        let position = (self.clamped_position(viewport) + offset) * self.pixel_size as f32;
+        let centre = viewport.size().to_f32() / 2.0;
+        let (sin, cos) = (-(self.rotation.radians + angle)).sin_cos();
+        let zoom = self.zoom;
+
+        // Translate to the camera, rotate, zoom, then move to the centre of the viewport
+        let rotate_zoom = Affine(Matrix3::new(
+            cos * zoom, -sin * zoom, centre.width,
+            sin * zoom, cos * zoom, centre.height,
+            0.0, 0.0, 1.0,
+        ));
+        let translate = Affine(Matrix3::new_translation(&nalgebra::Vector2::new(-position.x, -position.y)));
+
+        rotate_zoom * translate
+    }
+
+    fn visible_size(&self, viewport: &Viewport) -> Size<f32> {
+        let scale = self.zoom * self.pixel_size as f32;
+        viewport.size().to_f32() / scale
+    }
+
+    fn clamped_position(&self, viewport: &Viewport) -> Position<f32> {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return self.position,
+        };
+
+        let half = self.visible_size(viewport) / 2.0;
+        let clamp = |pos: f32, min: f32, max: f32, half: f32| match max - min < half * 2.0 {
+            true => (min + max) / 2.0,
+            false => pos.clamp(min + half, max - half),
+        };
+
+        Position::new(
+            clamp(self.position.x, bounds.min_x(), bounds.max_x(), half.width),
+            clamp(self.position.y, bounds.min_y(), bounds.max_y(), half.height),
+        )
+    }
+
+    fn shake(&self) -> (Position<f32>, f32) {
+        let shake = self.trauma * self.trauma;
+        if shake == 0.0 {
+            return (Position::zero(), 0.0);
+        }
+
+        let t = self.time * self.shake_frequency;
+        let offset = Position::new(noise(self.seed, 0, t), noise(self.seed, 1, t)) * self.max_shake_offset * shake;
+        let angle = self.max_shake_angle.radians * shake * noise(self.seed, 2, t);
+        (offset, angle)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Noise -
+// -----------------------------------------------------------------------------
+// Smooth value noise in the range -1.0 to 1.0.
+fn noise(seed: u32, channel: u32, t: f32) -> f32 {
+    let i = t.floor();
+    let f = t - i;
+    let a = hash(seed, channel, i as i32);
+    let b = hash(seed, channel, i as i32 + 1);
+    let f = f * f * (3.0 - 2.0 * f);
+    a + (b - a) * f
+}
+
+fn hash(seed: u32, channel: u32, i: i32) -> f32 {
+    let mut x = (i as u32)
+        .wrapping_mul(0x9e37_79b1)
+        ^ seed.wrapping_mul(0x85eb_ca6b)
+        ^ channel.wrapping_mul(0xc2b2_ae35);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(a: Point<f32>, b: Point<f32>) -> bool {
+        (a - b).length() < 1e-3
+    }
+
+    fn viewport() -> Viewport {
+        Viewport::new(Position::new(100, 50), Size::new(400, 300))
+    }
+
+    #[test]
+    fn screen_world_round_trip() {
+        let viewport = viewport();
+        let mut camera = Camera2D::new(Position::new(20.0, -5.0));
+        camera.zoom = 1.5;
+        camera.rotation = Rotation::radians(0.3);
+        camera.pixel_size = 4;
+
+        let screen = Point::new(123.0, 456.0);
+        let world = camera.screen_to_world(screen, 600, &viewport);
+        assert!(close(camera.world_to_screen(world, 600, &viewport), screen));
+    }
+
+    #[test]
+    fn screen_to_world_accounts_for_viewport_and_pixel_size() {
+        let viewport = viewport();
+        let mut camera = Camera2D::new(Position::new(0.0, 0.0));
+        camera.pixel_size = 2;
+
+        // The middle of the viewport is at 300, 200 in gl coordinates,
+        // which is 300, 400 in window coordinates.
+        let centre = camera.screen_to_world(Point::new(300.0, 400.0), 600, &viewport);
+        assert!(close(centre, Point::zero()));
+
+        // Ten pixels right and up in the window is five units in the world
+        let world = camera.screen_to_world(Point::new(310.0, 390.0), 600, &viewport);
+        assert!(close(world, Point::new(5.0, 5.0)));
+    }
+
+    #[test]
+    fn follow_with_deadzone() {
+        let mut camera = Camera2D::new(Position::zero());
+        camera.deadzone = Size::new(10.0, 10.0);
+
+        camera.follow(Position::new(4.0, -4.0), 0.1);
+        assert_eq!(camera.position, Position::zero());
+
+        camera.follow(Position::new(15.0, 0.0), 0.1);
+        assert_eq!(camera.position, Position::new(10.0, 0.0));
+
+        camera.follow_smoothing = 1.0;
+        camera.follow(Position::new(25.0, 0.0), 1.0);
+        let expected = 10.0 + 10.0 * (1.0 - (-1.0f32).exp());
+        assert!((camera.position.x - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bounds() {
+        let viewport = viewport();
+        let mut camera = Camera2D::new(Position::new(-100.0, 1000.0));
+        camera.bounds = Some(Rect::new(Point::zero(), Size::new(1000.0, 200.0)));
+
+        let visible = camera.visible_rect(&viewport);
+        assert_eq!(visible.origin.x, 0.0);
+        // The bounds are lower than the viewport, so they are centred
+        assert_eq!(visible.center().y, 100.0);
+    }
+
+    #[test]
+    fn shake_is_deterministic_and_decays() {
+        let viewport = viewport();
+        let mut a = Camera2D::new(Position::zero());
+        let mut b = Camera2D::new(Position::zero());
+        a.add_trauma(0.5);
+        b.add_trauma(0.5);
+        a.update(0.3);
+        b.update(0.1);
+        b.update(0.2);
+
+        let centre = Point::new(300.0, 400.0);
+        assert!(close(a.screen_to_world(centre, 600, &viewport), b.screen_to_world(centre, 600, &viewport)));
+        assert!(a.screen_to_world(centre, 600, &viewport) != Point::zero());
+
+        a.update(1.0);
+        assert_eq!(a.trauma(), 0.0);
+        assert!(close(a.screen_to_world(centre, 600, &viewport), Point::zero()));
+    }
+
+    #[test]
+    fn non_finite_dt_is_ignored() {
+        let viewport = viewport();
+        let mut camera = Camera2D::new(Position::zero());
+        camera.update(f32::NAN);
+        camera.update(f32::INFINITY);
+        camera.add_trauma(0.5);
+        camera.update(0.1);
+
+        let world = camera.screen_to_world(Point::new(300.0, 400.0), 600, &viewport);
+        assert!(world.x.is_finite() && world.y.is_finite());
+        assert!(camera.trauma() > 0.0);
+    }
+}
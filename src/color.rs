@@ -1,7 +1,8 @@
 #![deny(missing_docs)]
 use crate::pixels::Pixel;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
 /// A colour with values ranging from 0.0 to 1.0
 pub struct Color {
     /// Red
@@ -39,6 +40,21 @@ impl Color {
     pub fn black() -> Self {
         Self::default()
     }
+
+    /// Nothing at all
+    pub fn transparent() -> Self {
+        Self {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        }
+    }
+
+    /// The same colour with a different alpha
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
 }
 
 impl Default for Color {
@@ -1,15 +1,20 @@
 #![deny(missing_docs)]
+use std::os::raw::c_void;
+
 use num_traits::cast::NumCast;
 use gl33::global_loader::*;
 use gl33::*;
+use glutin::dpi::PhysicalSize;
 use glutin::event_loop::EventLoop;
 use glutin::window::Window;
 use glutin::window::WindowBuilder;
 use glutin::{
-    Api, ContextBuilder as GlutinContextBuilder, ContextWrapper, GlRequest, PossiblyCurrent,
+    Api, ContextBuilder as GlutinContextBuilder, ContextWrapper,
+    GlProfile, GlRequest, PossiblyCurrent,
 };
 
-use crate::{Color, Result, Size};
+use crate::errors::NightmareError;
+use crate::{BlendMode, Color, DepthMode, Result, Size, Viewport};
 
 /// Vertex array object
 #[derive(Debug, PartialEq)]
@@ -27,6 +32,155 @@ impl Drop for Vao {
     }
 }
 
+// -----------------------------------------------------------------------------
+//     - GL setup -
+//     Load the function pointers.
+//     This has to happen after the context is made current.
+//     The blend and depth state is set by the context, so it can track it.
+// -----------------------------------------------------------------------------
+fn load_gl(surface: &Surface) {
+    unsafe {
+        load_global_gl(&|ptr| {
+            let c_str = std::ffi::CStr::from_ptr(ptr as *const i8);
+            let r_str = c_str.to_str().unwrap();
+            surface.get_proc_address(r_str)
+        });
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Surface -
+//     Either a window or an offscreen context
+// -----------------------------------------------------------------------------
+enum Surface {
+    Windowed(ContextWrapper<PossiblyCurrent, Window>),
+    Headless {
+        context: headless::HeadlessContext,
+        size: Size<i32>,
+    },
+}
+
+impl Surface {
+    fn get_proc_address(&self, name: &str) -> *const c_void {
+        match self {
+            Surface::Windowed(context) => context.get_proc_address(name),
+            Surface::Headless { context, .. } => context.get_proc_address(name),
+        }
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod headless {
+    use std::os::raw::c_void;
+
+    use glutin::dpi::PhysicalSize;
+    use glutin::platform::unix::HeadlessContextExt;
+    use glutin::{Context, ContextBuilder, CreationError, NotCurrent, PossiblyCurrent};
+
+    use super::{Surface, DEPTH_BITS, STENCIL_BITS};
+    use crate::egl::EglContext;
+    use crate::{Result, Size};
+
+    pub(super) enum HeadlessContext {
+        Egl(EglContext),
+        OsMesa(Context<PossiblyCurrent>),
+    }
+
+    impl HeadlessContext {
+        pub(super) fn get_proc_address(&self, name: &str) -> *const c_void {
+            match self {
+                HeadlessContext::Egl(context) => context.get_proc_address(name),
+                HeadlessContext::OsMesa(context) => context.get_proc_address(name),
+            }
+        }
+    }
+
+    // Neither needs X11 nor Wayland.
+    // A surfaceless EGL context is tried first, as it works with any Mesa driver
+    // (including llvmpipe) and Mesa no longer ships OSMesa since 25.1.
+    pub(super) fn build(builder: ContextBuilder<NotCurrent>, size: PhysicalSize<u32>) -> Result<Surface> {
+        let size_i32 = Size::new(size.width, size.height).cast();
+
+        let context = match EglContext::new(size_i32, DEPTH_BITS, STENCIL_BITS) {
+            Ok(context) => HeadlessContext::Egl(context),
+            Err(egl_err) => {
+                let context = match builder.build_osmesa(size) {
+                    Ok(context) => context,
+                    Err(osmesa_err) => {
+                        return Err(CreationError::CreationErrors(vec![Box::new(egl_err), Box::new(osmesa_err)]).into())
+                    }
+                };
+                match unsafe { context.make_current() } {
+                    Ok(c) => HeadlessContext::OsMesa(c),
+                    Err((_, e)) => return Err(e.into()),
+                }
+            }
+        };
+
+        Ok(Surface::Headless {
+            context,
+            size: size_i32,
+        })
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+mod headless {
+    use std::os::raw::c_void;
+
+    use glutin::dpi::PhysicalSize;
+    use glutin::event_loop::EventLoop;
+    use glutin::{Context, ContextBuilder, NotCurrent, PossiblyCurrent};
+
+    use super::Surface;
+    use crate::{Result, Size};
+
+    pub(super) struct HeadlessContext {
+        context: Context<PossiblyCurrent>,
+        // The headless context is tied to the event loop,
+        // so it has to outlive the context.
+        _event_loop: EventLoop<()>,
+    }
+
+    impl HeadlessContext {
+        pub(super) fn get_proc_address(&self, name: &str) -> *const c_void {
+            self.context.get_proc_address(name)
+        }
+    }
+
+    // The event loop has to be created on the main thread (see `build_headless`).
+    pub(super) fn build(builder: ContextBuilder<NotCurrent>, size: PhysicalSize<u32>) -> Result<Surface> {
+        let event_loop = EventLoop::new();
+        let context = builder.build_headless(&event_loop, size)?;
+        let context = match unsafe { context.make_current() } {
+            Ok(c) => c,
+            Err((_, e)) => return Err(e.into()),
+        };
+
+        Ok(Surface::Headless {
+            context: HeadlessContext { context, _event_loop: event_loop },
+            size: Size::new(size.width, size.height).cast(),
+        })
+    }
+}
+
+// The default framebuffer is 24 bit depth and 8 bit stencil,
+// the same as the depth buffer of an `OitRenderer`, so depth can be blitted.
+const DEPTH_BITS: u8 = 24;
+const STENCIL_BITS: u8 = 8;
+
 // -----------------------------------------------------------------------------
 //     - Context builder -
 // -----------------------------------------------------------------------------
@@ -113,6 +267,8 @@ impl ContextBuilder {
         self
     }
 
+    /// Build a windowed [`Context`] from an existing glutin `WindowBuilder`.
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_builder<T>(&self, win_builder: WindowBuilder) -> Result<(EventLoop<T>, Context)> {
         let event_loop = EventLoop::<T>::with_user_event();
 
@@ -121,38 +277,82 @@ impl ContextBuilder {
             .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
             .with_vsync(self.vsync)
             .with_hardware_acceleration(Some(self.hardware_acceleration))
-            .build_windowed(win_builder, &event_loop)
-            .unwrap();
+            .with_depth_buffer(DEPTH_BITS)
+            .with_stencil_buffer(STENCIL_BITS)
+            .build_windowed(win_builder, &event_loop)?;
 
         let context = unsafe {
-            let context = match context.make_current() {
+            match context.make_current() {
                 Ok(c) => c,
                 Err((_, e)) => return Err(e.into()),
-            };
+            }
+        };
 
-            load_global_gl(&|ptr| {
-                let c_str = std::ffi::CStr::from_ptr(ptr as *const i8);
-                let r_str = c_str.to_str().unwrap();
-                context.get_proc_address(r_str) as _
-            });
+        let surface = Surface::Windowed(context);
+        load_gl(&surface);
 
-            context
+        let mut inst = Context {
+            surface,
+            current_vao_id: 0,
+            blend_mode: None,
+            depth_mode: None,
         };
+        inst.set_blend_mode(BlendMode::Alpha);
+        inst.set_depth_mode(DepthMode::default());
 
-        // Setup alpha blending and depth testing
-        unsafe {
-            glEnable(GL_BLEND);
-            glEnable(GL_DEPTH_TEST);
-            glDepthFunc(GL_LESS);
-            glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+        Ok((event_loop, inst))
+    }
+
+    /// Build a [`Context`] that renders offscreen and doesn't need
+    /// a window or a display server.
+    ///
+    /// On unix this is a surfaceless EGL context with a pbuffer, falling back to
+    /// OSMesa if EGL is not available. Both work with Mesa's software renderer (llvmpipe).
+    /// On other platforms this is a headless context provided by the driver.
+    /// It is tied to an event loop created for it there, so on those platforms
+    /// this has to be called from the main thread (winit panics otherwise on macOS).
+    ///
+    /// The context has a default framebuffer of `size`, so everything that works
+    /// with a windowed context works the same way, with the exception of
+    /// [`Context::swap_buffers`] which does nothing.
+    ///
+    /// Returns an error if the width or height is not positive.
+    ///
+    /// ```
+    /// # fn run() -> nightmaregl::Result<()> {
+    /// use nightmaregl::{Context, Renderer, VertexData, Size};
+    ///
+    /// let mut context = Context::builder("offscreen").build_headless(Size::new(256, 256))?;
+    /// let renderer = Renderer::<VertexData>::default(&mut context)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_headless(&self, size: Size<i32>) -> Result<Context> {
+        if size.width <= 0 || size.height <= 0 {
+            return Err(NightmareError::InvalidSize(size.width, size.height));
         }
 
-        let inst = Context {
-            inner: context,
+        let builder = GlutinContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .with_hardware_acceleration(Some(self.hardware_acceleration))
+            .with_depth_buffer(DEPTH_BITS)
+            .with_stencil_buffer(STENCIL_BITS);
+
+        let physical_size = PhysicalSize::new(size.width as u32, size.height as u32);
+        let surface = headless::build(builder, physical_size)?;
+        load_gl(&surface);
+
+        let mut inst = Context {
+            surface,
             current_vao_id: 0,
+            blend_mode: None,
+            depth_mode: None,
         };
+        inst.set_blend_mode(BlendMode::Alpha);
+        inst.set_depth_mode(DepthMode::default());
 
-        Ok((event_loop, inst))
+        Ok(inst)
     }
 
     /// Finalise the context builder and produce a [`Context`]
@@ -199,8 +399,11 @@ impl ContextBuilder {
 /// }
 /// ```
 pub struct Context {
-    inner: ContextWrapper<PossiblyCurrent, Window>,
+    surface: Surface,
     current_vao_id: u32,
+    // Unknown until they have been set once
+    blend_mode: Option<BlendMode>,
+    depth_mode: Option<DepthMode>,
 }
 
 impl Context {
@@ -211,9 +414,53 @@ impl Context {
         }
     }
 
+    /// Set how fragments are blended with the frame buffer.
+    /// Nothing is changed if the mode is already set.
+    ///
+    /// Renderers set their own blend mode when rendering, so this is only
+    /// needed when drawing with OpenGL directly.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        if self.blend_mode != Some(blend_mode) {
+            blend_mode.apply(self.blend_mode);
+            self.blend_mode = Some(blend_mode);
+        }
+    }
+
+    /// The current blend mode.
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        self.blend_mode
+    }
+
+    /// Set the depth testing.
+    /// Nothing is changed if the mode is already set.
+    ///
+    /// Like the blend mode, renderers set their own depth mode when rendering.
+    pub fn set_depth_mode(&mut self, depth_mode: DepthMode) {
+        if self.depth_mode != Some(depth_mode) {
+            depth_mode.apply(self.depth_mode);
+            self.depth_mode = Some(depth_mode);
+        }
+    }
+
+    /// The current depth mode.
+    pub fn depth_mode(&self) -> Option<DepthMode> {
+        self.depth_mode
+    }
+
+    /// Forget the blend mode and the depth mode, so they are set again
+    /// the next time they are used.
+    /// Use this after changing the blend or depth state with OpenGL directly.
+    pub fn reset_state(&mut self) {
+        self.blend_mode = None;
+        self.depth_mode = None;
+    }
+
     /// Swap the buffer on the current window, making all changes visible.
+    /// This does nothing for a headless context.
     pub fn swap_buffers(&self) {
-        let _ = self.inner.swap_buffers().unwrap();
+        if let Surface::Windowed(context) = &self.surface {
+            context.swap_buffers().unwrap();
+        }
     }
 
     /// Create a context builder. The title is the window title.
@@ -223,14 +470,48 @@ impl Context {
 
     /// Get the current window size.
     /// Useful when creating a [Viewport](crate::Viewport).
+    ///
+    /// For a headless context this is the size of the offscreen buffer.
     pub fn window_size<T : Copy + NumCast>(&self) -> Size<T> {
-        let size = self.inner.window().inner_size();
-        Size::new(size.width, size.height).cast()
+        match &self.surface {
+            Surface::Windowed(context) => {
+                let size = context.window().inner_size();
+                Size::new(size.width, size.height).cast()
+            }
+            Surface::Headless { size, .. } => size.cast(),
+        }
     }
 
-    /// Get the current window handle
+    /// Get the current window handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is headless. Use [`Context::try_window`]
+    /// for code that works with both kinds of context.
     pub fn window(&self) -> &Window {
-        self.inner.window()
+        self.try_window().expect("a headless context has no window")
+    }
+
+    /// Get the current window handle, or `None` for a headless context.
+    pub fn try_window(&self) -> Option<&Window> {
+        match &self.surface {
+            Surface::Windowed(context) => Some(context.window()),
+            Surface::Headless { .. } => None,
+        }
+    }
+
+    /// The scale factor of the window.
+    /// This is always 1.0 for a headless context.
+    pub fn scale_factor(&self) -> f64 {
+        match self.try_window() {
+            Some(window) => window.scale_factor(),
+            None => 1.0,
+        }
+    }
+
+    /// Returns true if the context is not attached to a window.
+    pub fn is_headless(&self) -> bool {
+        matches!(self.surface, Surface::Headless { .. })
     }
 
     /// Clear the frame buffer.
@@ -245,9 +526,40 @@ impl Context {
     /// # }
     /// ```
     pub fn clear(&self, color: Color) {
+        unsafe { glClearColor(color.r, color.g, color.b, color.a) };
+        self.clear_buffers(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+    }
+
+    /// Clear the depth buffer of the bound frame buffer, leaving the colour alone.
+    pub fn clear_depth(&self) {
+        self.clear_buffers(GL_DEPTH_BUFFER_BIT);
+    }
+
+    fn clear_buffers(&self, mask: GLbitfield) {
+        unsafe {
+            // The depth buffer is only cleared if depth writes are on
+            glDepthMask(GL_TRUE.0 as u8);
+            glClear(mask);
+
+            if let Some(DepthMode::Test(_)) = self.depth_mode {
+                glDepthMask(GL_FALSE.0 as u8);
+            }
+        }
+    }
+
+    /// Clear only the area covered by the viewport,
+    /// leaving the rest of the frame buffer alone.
+    pub fn clear_viewport(&self, viewport: &Viewport, color: Color) {
         unsafe {
-            glClearColor(color.r, color.g, color.b, color.a);
-            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+            glEnable(GL_SCISSOR_TEST);
+            glScissor(
+                viewport.position.x,
+                viewport.position.y,
+                viewport.size().width,
+                viewport.size().height,
+            );
+            self.clear(color);
+            glDisable(GL_SCISSOR_TEST);
         }
     }
 
@@ -259,3 +571,27 @@ impl Context {
         Vao(vao)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headless_size_must_be_positive() {
+        let builder = Context::builder("headless");
+        assert!(matches!(builder.build_headless(Size::new(-1, 8)), Err(NightmareError::InvalidSize(-1, 8))));
+        assert!(matches!(builder.build_headless(Size::new(8, 0)), Err(NightmareError::InvalidSize(8, 0))));
+    }
+
+    #[test]
+    fn headless_without_a_display() {
+        let context = Context::builder("headless").build_headless(Size::new(32, 16)).unwrap();
+        assert!(context.is_headless());
+        assert!(context.try_window().is_none());
+        assert_eq!(context.window_size::<i32>(), Size::new(32, 16));
+
+        let mut viewport = [0; 4];
+        unsafe { glGetIntegerv(GL_VIEWPORT, viewport.as_mut_ptr()) };
+        assert_eq!(viewport, [0, 0, 32, 16]);
+    }
+}
@@ -6,16 +6,22 @@ in vec2 tex_coords;
 in vec2 tex_pos;
 in vec2 tex_size;
 in vec2 tile_count;
+in vec4 tint;
+in vec4 flash;
+in mat2 uv_transform;
 
 uniform sampler2D tex;
 
 void main() {
     // wonderful intrets says:  and now you want tex_pos + tex_coords * tex_scale
     vec2 coords = fract(tex_coords);
+    coords = uv_transform * (coords - 0.5) + 0.5;
     vec2 the_final_coord = tex_pos + coords * tex_size;
-    colour = texture(tex, the_final_coord);
+    colour = texture(tex, the_final_coord) * tint;
 
     if (colour.a == 0.0) {
         discard;
     }
+
+    colour.rgb = min(colour.rgb + flash.rgb * flash.a, 1.0);
 }
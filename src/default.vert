@@ -7,6 +7,9 @@ layout (location = 3) in mat4 transform;
 layout (location = 10) in vec2 _tex_pos;
 layout (location = 11) in vec2 _tex_size;
 layout (location = 12) in vec2 _tile_count;
+layout (location = 7) in vec4 _tint;
+layout (location = 8) in vec4 _flash;
+layout (location = 13) in mat2 _uv_transform;
 
 uniform mat4 vp;
 uniform sampler2D tex;
@@ -16,6 +19,9 @@ out vec2 tex_coords;
 out vec2 tex_pos;
 out vec2 tex_size;
 out vec2 tile_count;
+out vec4 tint;
+out vec4 flash;
+out mat2 uv_transform;
 
 void main() {
     mat4 scaling_matrix = mat4(1.0);
@@ -28,5 +34,8 @@ void main() {
     tex_pos = _tex_pos;
     tex_size = _tex_size;
     tile_count = _tile_count;
+    tint = _tint;
+    flash = _flash;
+    uv_transform = _uv_transform;
     tex_coords = uv_coords * tile_count;
 }
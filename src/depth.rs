@@ -0,0 +1,161 @@
+#![deny(missing_docs)]
+use gl33::global_loader::*;
+use gl33::*;
+
+// -----------------------------------------------------------------------------
+//     - Depth function -
+// -----------------------------------------------------------------------------
+/// How the depth of a fragment is compared with the depth buffer.
+/// The fragment is drawn if the comparison passes.
+///
+/// See [`crate::Viewport`] for how a z index maps to a depth.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthFunc {
+    /// Never draw
+    Never,
+    /// Draw if in front (a lower z index)
+    Less,
+    /// Draw if in front, or at the same depth
+    LessOrEqual,
+    /// Draw if at the same depth
+    Equal,
+    /// Draw if not at the same depth
+    NotEqual,
+    /// Draw if at the same depth, or behind
+    GreaterOrEqual,
+    /// Draw if behind (a higher z index)
+    Greater,
+    /// Always draw
+    Always,
+}
+
+impl DepthFunc {
+    fn gl(self) -> GLenum {
+        match self {
+            DepthFunc::Never => GL_NEVER,
+            DepthFunc::Less => GL_LESS,
+            DepthFunc::LessOrEqual => GL_LEQUAL,
+            DepthFunc::Equal => GL_EQUAL,
+            DepthFunc::NotEqual => GL_NOTEQUAL,
+            DepthFunc::GreaterOrEqual => GL_GEQUAL,
+            DepthFunc::Greater => GL_GREATER,
+            DepthFunc::Always => GL_ALWAYS,
+        }
+    }
+
+    /// Compare the depth of a fragment with the depth in the depth buffer.
+    pub fn compare(self, depth: f32, buffer: f32) -> bool {
+        match self {
+            DepthFunc::Never => false,
+            DepthFunc::Less => depth < buffer,
+            DepthFunc::LessOrEqual => depth <= buffer,
+            DepthFunc::Equal => depth == buffer,
+            DepthFunc::NotEqual => depth != buffer,
+            DepthFunc::GreaterOrEqual => depth >= buffer,
+            DepthFunc::Greater => depth > buffer,
+            DepthFunc::Always => true,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Depth mode -
+// -----------------------------------------------------------------------------
+/// Depth testing for a renderer.
+///
+/// Sprites that are partly transparent should be drawn with a mode that
+/// doesn't write depth (after everything opaque, back to front), or
+/// with an [`crate::renderer::oit::OitRenderer`], so they don't hide
+/// what is drawn behind them later.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # fn run(mut context: Context, mut renderer: Renderer<VertexData>) {
+/// // Hidden behind opaque sprites, but never hiding anything
+/// renderer.depth_mode = DepthMode::Test(DepthFunc::Less);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthMode {
+    /// No depth testing. Everything is drawn in the order it's rendered,
+    /// and the depth buffer is left alone.
+    Off,
+    /// Test against the depth buffer, without writing to it.
+    Test(DepthFunc),
+    /// Test against the depth buffer, and write the depth of
+    /// every fragment that is drawn.
+    TestWrite(DepthFunc),
+}
+
+impl Default for DepthMode {
+    /// Test and write, drawing fragments with a lower z index in front.
+    fn default() -> Self {
+        DepthMode::TestWrite(DepthFunc::Less)
+    }
+}
+
+impl DepthMode {
+    /// Would a fragment pass the depth test, and should its
+    /// depth be written to the depth buffer.
+    pub fn test(self, depth: f32, buffer: f32) -> (bool, bool) {
+        match self {
+            DepthMode::Off => (true, false),
+            DepthMode::Test(func) => (func.compare(depth, buffer), false),
+            DepthMode::TestWrite(func) => {
+                let pass = func.compare(depth, buffer);
+                (pass, pass)
+            }
+        }
+    }
+
+    fn state(self) -> Option<(DepthFunc, bool)> {
+        match self {
+            DepthMode::Off => None,
+            DepthMode::Test(func) => Some((func, false)),
+            DepthMode::TestWrite(func) => Some((func, true)),
+        }
+    }
+
+    // Change the GL depth state from `current` (if known) to `self`.
+    pub(crate) fn apply(self, current: Option<DepthMode>) {
+        let current = current.map(DepthMode::state);
+
+        let (func, write) = match (current, self.state()) {
+            (_, None) => return unsafe { glDisable(GL_DEPTH_TEST) },
+            (Some(Some(_)), Some(state)) => state,
+            (_, Some(state)) => {
+                unsafe { glEnable(GL_DEPTH_TEST) };
+                state
+            }
+        };
+
+        let (current_func, current_write) = match current {
+            Some(Some((func, write))) => (Some(func), Some(write)),
+            _ => (None, None),
+        };
+
+        unsafe {
+            if current_func != Some(func) {
+                glDepthFunc(func.gl());
+            }
+            if current_write != Some(write) {
+                glDepthMask(write as u8);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn depth_modes() {
+        assert_eq!(DepthMode::default().test(0.2, 0.5), (true, true));
+        assert_eq!(DepthMode::default().test(0.5, 0.5), (false, false));
+        assert_eq!(DepthMode::TestWrite(DepthFunc::LessOrEqual).test(0.5, 0.5), (true, true));
+        assert_eq!(DepthMode::Test(DepthFunc::Less).test(0.2, 0.5), (true, false));
+        assert_eq!(DepthMode::Test(DepthFunc::Greater).test(0.2, 0.5), (false, false));
+        assert_eq!(DepthMode::Off.test(0.9, 0.1), (true, false));
+    }
+}
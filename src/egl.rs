@@ -0,0 +1,159 @@
+// -----------------------------------------------------------------------------
+//     - EGL -
+//     A pbuffer context on Mesa's surfaceless platform.
+//     This needs neither X11 nor Wayland, only libEGL and a Mesa driver
+//     (a render node or llvmpipe).
+//     glutin can only create surfaceless contexts through an event loop,
+//     which needs a display server, so this talks to EGL directly.
+// -----------------------------------------------------------------------------
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+use glutin::CreationError;
+use glutin_egl_sys::egl;
+use glutin_egl_sys::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLenum, EGLint};
+use libloading::Library;
+
+use crate::Size;
+
+// EGL_MESA_platform_surfaceless
+const PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+type GetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+
+pub(crate) struct EglContext {
+    // The function table is large, so keep it off the stack
+    egl: Box<egl::Egl>,
+    display: EGLDisplay,
+    surface: EGLSurface,
+    context: EGLContext,
+    // The function pointers in `egl` point into the library
+    _library: Library,
+}
+
+impl EglContext {
+    /// Create an OpenGL 3.3 core context with a pbuffer of `size`
+    /// and make it current on this thread.
+    pub(crate) fn new(size: Size<i32>, depth_bits: u8, stencil_bits: u8) -> Result<Self, CreationError> {
+        let library = Library::new("libEGL.so.1")
+            .or_else(|_| Library::new("libEGL.so"))
+            .map_err(|e| CreationError::NoBackendAvailable(Box::new(e)))?;
+
+        // Extension functions are not always exported by the library,
+        // and have to be looked up with `eglGetProcAddress`.
+        let get_proc_address: GetProcAddress = unsafe {
+            *library.get::<GetProcAddress>(b"eglGetProcAddress\0")
+                .map_err(|e| CreationError::NoBackendAvailable(Box::new(e)))?
+        };
+
+        let egl = Box::new(egl::Egl::load_with(|name| {
+            let name = CString::new(name).expect("EGL function names have no nul bytes");
+            match unsafe { library.get::<*const c_void>(name.as_bytes_with_nul()) } {
+                Ok(symbol) => *symbol,
+                Err(_) => unsafe { get_proc_address(name.as_ptr()) },
+            }
+        }));
+
+        if !egl.GetPlatformDisplayEXT.is_loaded() {
+            return Err(CreationError::NotSupported("EGL_EXT_platform_base is not supported".into()));
+        }
+
+        let display = unsafe {
+            egl.GetPlatformDisplayEXT(PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null())
+        };
+        if display == egl::NO_DISPLAY {
+            return Err(CreationError::NotSupported("EGL_MESA_platform_surfaceless is not supported".into()));
+        }
+
+        // The display is shared by every context in the process,
+        // so it's initialized once per context and never terminated.
+        let (mut major, mut minor) = (0, 0);
+        if unsafe { egl.Initialize(display, &mut major, &mut minor) } == egl::FALSE {
+            return Err(error(&egl, "eglInitialize"));
+        }
+
+        if unsafe { egl.BindAPI(egl::OPENGL_API) } == egl::FALSE {
+            return Err(error(&egl, "eglBindAPI"));
+        }
+
+        let config_attributes = [
+            egl::SURFACE_TYPE as EGLint, egl::PBUFFER_BIT as EGLint,
+            egl::RENDERABLE_TYPE as EGLint, egl::OPENGL_BIT as EGLint,
+            egl::RED_SIZE as EGLint, 8,
+            egl::GREEN_SIZE as EGLint, 8,
+            egl::BLUE_SIZE as EGLint, 8,
+            egl::ALPHA_SIZE as EGLint, 8,
+            egl::DEPTH_SIZE as EGLint, depth_bits as EGLint,
+            egl::STENCIL_SIZE as EGLint, stencil_bits as EGLint,
+            egl::NONE as EGLint,
+        ];
+
+        let mut config: EGLConfig = ptr::null();
+        let mut config_count = 0;
+        let chosen = unsafe {
+            egl.ChooseConfig(display, config_attributes.as_ptr(), &mut config, 1, &mut config_count)
+        };
+        if chosen == egl::FALSE {
+            return Err(error(&egl, "eglChooseConfig"));
+        }
+        if config_count == 0 {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
+        let surface_attributes = [
+            egl::WIDTH as EGLint, size.width,
+            egl::HEIGHT as EGLint, size.height,
+            egl::NONE as EGLint,
+        ];
+        let surface = unsafe { egl.CreatePbufferSurface(display, config, surface_attributes.as_ptr()) };
+        if surface == egl::NO_SURFACE {
+            return Err(error(&egl, "eglCreatePbufferSurface"));
+        }
+
+        let context_attributes = [
+            egl::CONTEXT_MAJOR_VERSION as EGLint, 3,
+            egl::CONTEXT_MINOR_VERSION as EGLint, 3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK as EGLint, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as EGLint,
+            egl::NONE as EGLint,
+        ];
+        let context = unsafe { egl.CreateContext(display, config, egl::NO_CONTEXT, context_attributes.as_ptr()) };
+        if context == egl::NO_CONTEXT {
+            let err = error(&egl, "eglCreateContext");
+            unsafe { egl.DestroySurface(display, surface) };
+            return Err(err);
+        }
+
+        // From here on `Drop` cleans up
+        let inst = Self { egl, display, surface, context, _library: library };
+
+        if unsafe { inst.egl.MakeCurrent(display, surface, surface, context) } == egl::FALSE {
+            return Err(error(&inst.egl, "eglMakeCurrent"));
+        }
+
+        Ok(inst)
+    }
+
+    pub(crate) fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).expect("GL function names have no nul bytes");
+        unsafe { self.egl.GetProcAddress(name.as_ptr()) as *const c_void }
+    }
+}
+
+impl Drop for EglContext {
+    fn drop(&mut self) {
+        unsafe {
+            // Release the context if it's current on this thread
+            if self.egl.GetCurrentContext() == self.context {
+                self.egl.MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            }
+            self.egl.DestroyContext(self.display, self.context);
+            self.egl.DestroySurface(self.display, self.surface);
+        }
+    }
+}
+
+fn error(egl: &egl::Egl, function: &str) -> CreationError {
+    let code = unsafe { egl.GetError() };
+    CreationError::OsError(format!("{} failed with EGL error {:#x}", function, code))
+}
@@ -1,10 +1,11 @@
 use std::io::Error as IoErr;
+use std::path::PathBuf;
 use std::string::FromUtf8Error;
 
 use thiserror::Error;
 use rusttype::gpu_cache::CacheWriteErr;
 use png::{EncodingError, DecodingError};
-use glutin::ContextError;
+use glutin::{ContextError, CreationError};
 
 pub type Result<T> = std::result::Result<T, NightmareError>;
 
@@ -34,9 +35,42 @@ pub enum NightmareError {
     #[error(transparent)]
     ContextError(#[from] ContextError),
 
+    #[error(transparent)]
+    CreationError(#[from] CreationError),
+
+    #[error("Invalid size: {0}x{1}")]
+    InvalidSize(i32, i32),
+
     #[error("Shader failure")]
     Shader(String),
 
     #[error("Shader program failure")]
     ShaderProgram(String),
+
+    #[error("Snapshot {reference:?} does not match: {differing} pixels differ")]
+    SnapshotMismatch { reference: PathBuf, differing: usize },
+
+    #[error("Missing snapshot {0:?}")]
+    MissingSnapshot(PathBuf),
+
+    #[error("Not enough room in the atlas for {0}")]
+    AtlasFull(String),
+
+    #[error("More than one image in the atlas is named {0}")]
+    AtlasDuplicate(String),
+
+    #[error("Invalid json: {0}")]
+    Json(String),
+
+    #[error("Invalid sprite sheet: {0}")]
+    SpriteSheet(String),
+
+    #[error("Invalid Tiled map: {0}")]
+    Tiled(String),
+
+    #[error("No animation state named {0}")]
+    UnknownState(String),
+
+    #[error("A node can not be the parent of itself or its ancestors")]
+    InvalidParent,
 }
@@ -79,6 +79,12 @@ impl<T> Entries<T> {
     }
 }
 
+impl<T> Default for Entries<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> std::ops::Index<usize> for Entries<T> {
     type Output = T;
 
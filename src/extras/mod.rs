@@ -1,7 +1,7 @@
 #![deny(missing_docs)]
 //! Extras
 //!
-//! Entries is a slab that doesn't shrink.
+//! Entries is a slab that doesn't shrink, used by the [`crate::scene`] graph.
 mod entries;
 
 pub use entries::{Entries, Entry};
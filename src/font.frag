@@ -4,13 +4,19 @@ out vec4 colour;
 in vec2 tex_coords;
 in vec2 tex_pos;
 in vec2 tex_size;
+in vec4 tint;
+in vec4 flash;
+in mat2 uv_transform;
 
 uniform sampler2D tex;
 
 void main() {
-    colour = vec4(1.0, 1.0, 1.0, texture(tex, tex_pos + tex_coords * tex_size).r);
+    vec2 coords = uv_transform * (tex_coords - 0.5) + 0.5;
+    colour = vec4(tint.rgb, tint.a * texture(tex, tex_pos + coords * tex_size).r);
 
     if (colour.a == 0.0) {
         discard;
     }
+
+    colour.rgb = min(colour.rgb + flash.rgb * flash.a, 1.0);
 }
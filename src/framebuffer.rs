@@ -17,13 +17,36 @@ use gl33::global_loader::*;
 use gl33::*;
 use num_traits::cast::NumCast;
 
-use crate::Texture;
+use crate::{Size, Texture};
+
+// -----------------------------------------------------------------------------
+//     - Depth buffer -
+// -----------------------------------------------------------------------------
+struct DepthBuffer(u32);
+
+impl DepthBuffer {
+    fn new(size: Size<i32>) -> Self {
+        let mut id = 0;
+        unsafe {
+            glGenRenderbuffers(1, &mut id);
+            glBindRenderbuffer(GL_RENDERBUFFER, id);
+            glRenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8, size.width, size.height);
+        }
+        Self(id)
+    }
+}
+
+impl Drop for DepthBuffer {
+    fn drop(&mut self) {
+        unsafe { glDeleteRenderbuffers(1, &self.0) };
+    }
+}
 
 /// Framebuffer target.
 /// For more information see:
 ///
 /// [https://www.khronos.org/opengl/wiki/Framebuffer_Object#Framebuffer_Object_Structure](https://www.khronos.org/opengl/wiki/Framebuffer_Object#Framebuffer_Object_Structure)
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum FramebufferTarget {
     /// GL_READ_FRAMEBUFFER
     Read,
@@ -32,6 +55,7 @@ pub enum FramebufferTarget {
     Draw,
 
     /// GL_FRAMEBUFFER
+    #[default]
     Both,
 }
 
@@ -45,12 +69,6 @@ impl FramebufferTarget {
     }
 }
 
-impl Default for FramebufferTarget {
-    fn default() -> Self {
-        FramebufferTarget::Both
-    }
-}
-
 /// Frame buffer
 ///
 /// When rendering to a framebuffer the Y axis will be inverted.
@@ -70,6 +88,7 @@ impl Default for FramebufferTarget {
 pub struct Framebuffer {
     id: u32,
     target: FramebufferTarget,
+    depth: Option<DepthBuffer>,
 }
 
 impl Framebuffer {
@@ -77,7 +96,7 @@ impl Framebuffer {
     pub fn new(target: FramebufferTarget) -> Self {
         let mut id = 0;
         unsafe { glGenFramebuffers(1, &mut id) };
-        Self { id, target }
+        Self { id, target, depth: None }
     }
 
     /// Bind this framebuffer, making all subsequent draw calls act
@@ -100,13 +119,20 @@ impl Framebuffer {
 
     /// Attach a texture to this frame buffer to render to.
     pub fn attach_texture<T: Copy + NumCast>(&mut self, texture: &Texture<T>) {
+        self.attach_texture_at(texture, 0);
+    }
+
+    /// Attach a texture as colour attachment `index`, for a fragment
+    /// shader with more than one output.
+    /// Only the first attachment is drawn to unless `glDrawBuffers` is called.
+    pub fn attach_texture_at<T: Copy + NumCast>(&mut self, texture: &Texture<T>, index: u32) {
         self.bind();
         texture.bind();
 
         unsafe {
             glFramebufferTexture2D(
                 GL_FRAMEBUFFER,
-                GL_COLOR_ATTACHMENT0,
+                GLenum(GL_COLOR_ATTACHMENT0.0 + index),
                 GL_TEXTURE_2D,
                 texture.id(),
                 0,
@@ -115,6 +141,30 @@ impl Framebuffer {
 
         self.unbind();
     }
+
+    /// Attach a depth (and stencil) buffer, so the z index of sprites
+    /// is respected when rendering to this framebuffer.
+    /// The size should be the same as the attached textures.
+    ///
+    /// The content of the new buffer is undefined until it's cleared
+    /// with [`crate::Context::clear`] or [`crate::Context::clear_depth`].
+    pub fn attach_depth_buffer(&mut self, size: Size<i32>) {
+        let depth = DepthBuffer::new(size);
+        self.bind();
+
+        unsafe {
+            glFramebufferRenderbuffer(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_STENCIL_ATTACHMENT,
+                GL_RENDERBUFFER,
+                depth.0,
+            )
+        };
+
+        self.unbind();
+        // Dropping the previous buffer detaches it
+        self.depth = Some(depth);
+    }
 }
 
 impl Default for Framebuffer {
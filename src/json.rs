@@ -0,0 +1,54 @@
+// -----------------------------------------------------------------------------
+//     - Json -
+//     Helpers on top of serde_json for the data formats
+//     the crate reads and writes (sprite sheets, maps).
+// -----------------------------------------------------------------------------
+use std::convert::TryFrom;
+
+pub(crate) use serde_json::{json, Value};
+
+use crate::errors::{NightmareError, Result};
+
+// serde_json limits the nesting depth, so a deeply nested (user supplied)
+// file is an error rather than a stack overflow.
+pub(crate) fn parse(src: &str) -> Result<Value> {
+    serde_json::from_str(src).map_err(|e| NightmareError::Json(e.to_string()))
+}
+
+// Only whole numbers that fit in a `u32`, so 1.5 or 1e20
+// are rejected rather than truncated or saturated.
+pub(crate) fn as_u32(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|n| u32::try_from(n).ok())
+}
+
+pub(crate) fn as_array(value: &Value) -> Option<&[Value]> {
+    value.as_array().map(Vec::as_slice)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn whole_numbers_only() {
+        let value = parse("[1, 1.5, -1, 4294967295, 4294967296, 1e20]").unwrap();
+        let numbers = value.as_array().unwrap().iter().map(as_u32).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![Some(1), None, None, Some(u32::MAX), None, None]);
+    }
+
+    #[test]
+    fn arrays_only() {
+        let value = parse(r#"{"a": [1, "b"], "c": {"d": 1}}"#).unwrap();
+        assert_eq!(as_array(&value["a"]), Some(&[json!(1), json!("b")][..]));
+        assert_eq!(as_array(&value["c"]), None);
+        assert_eq!(as_array(&value), None);
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(127)).is_ok());
+        assert!(matches!(parse(&nested(128)), Err(NightmareError::Json(_))));
+        assert!(parse(&"[".repeat(100_000)).is_err());
+    }
+}
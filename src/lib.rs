@@ -1,30 +1,60 @@
 mod animation;
+mod animator;
+mod batch;
+mod blend;
+mod camera;
 mod color;
 mod context;
+mod depth;
 mod sprite;
 mod viewport;
 mod transform;
 
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod egl;
+
+pub mod atlas;
 pub mod errors;
 pub mod framebuffer;
+pub mod particles;
 pub mod pixels;
 pub mod renderer;
+pub mod split_screen;
 pub mod texture;
+pub mod tilemap;
+pub mod tween;
+pub mod virtual_screen;
 
 #[cfg(feature = "eventloop")] pub mod events;
 #[cfg(feature = "text")] pub mod text;
 #[cfg(feature = "extras")] pub mod extras;
+#[cfg(feature = "extras")] pub mod scene;
+#[cfg(feature = "snapshot")] pub mod snapshot;
+#[cfg(feature = "json")] mod json;
+#[cfg(feature = "tiled")] pub mod tiled;
 
 pub use errors::Result;
 
-pub use animation::Animation;
+pub use animation::{Animation, AnimationEvent, Clip, Frame, PlayMode};
+pub use animator::{Animator, Condition, Transition};
+pub use batch::{BatchStats, SpriteBatch};
+pub use blend::{BlendEquation, BlendFactor, BlendFunc, BlendMode};
+pub use camera::Camera2D;
 pub use color::Color;
 pub use context::Context;
+pub use depth::{DepthFunc, DepthMode};
 pub use renderer::{default::Renderer, default::VertexData};
-pub use sprite::{FillMode, Sprite};
+pub use renderer::shapes::{LineJoin, ShapeRenderer, Shapes};
+pub use sprite::{FillMode, SliceFill, Sprite, UvRotation};
 pub use texture::Texture;
 pub use viewport::Viewport;
-pub use transform::Transform;
+pub use transform::{Affine, Transform};
 
 // -----------------------------------------------------------------------------
 //     - Vertex -
@@ -0,0 +1,44 @@
+# version 330 core
+
+// Weighted blended order independent transparency:
+// http://jcgt.org/published/0002/02/09/
+layout (location = 0) out vec4 accum;
+layout (location = 1) out float weight;
+
+in vec2 tex_coords;
+in vec2 tex_pos;
+in vec2 tex_size;
+in vec2 tile_count;
+in vec4 tint;
+in vec4 flash;
+in mat2 uv_transform;
+
+uniform sampler2D tex;
+
+// Viewport::FAR, with Viewport::NEAR at zero
+const float FAR = 10000.0;
+
+void main() {
+    vec2 coords = fract(tex_coords);
+    coords = uv_transform * (coords - 0.5) + 0.5;
+    vec2 the_final_coord = tex_pos + coords * tex_size;
+    vec4 colour = texture(tex, the_final_coord) * tint;
+
+    if (colour.a == 0.0) {
+        discard;
+    }
+
+    colour.rgb = min(colour.rgb + flash.rgb * flash.a, 1.0);
+
+    // Equation 8 from the paper, with the view depth in z indices,
+    // so the weight falls off over the z indices sprites use (0 to ~100).
+    // Closer (lower z index) fragments count for more, and the alpha is
+    // multiplied in below. The clamp keeps the 16 bit float targets from
+    // overflowing for hundreds of opaque layers.
+    float z = gl_FragCoord.z * FAR;
+    float w = clamp(10.0 / (1e-5 + pow(z / 10.0, 3.0) + pow(z / 200.0, 6.0)), 1e-2, 1e2);
+
+    // The alpha is multiplied into the revealage by the blend function
+    accum = vec4(colour.rgb * colour.a * w, colour.a);
+    weight = colour.a * w;
+}
@@ -0,0 +1,21 @@
+# version 330 core
+
+out vec4 colour;
+
+in vec2 uv;
+
+uniform sampler2D accum;
+uniform sampler2D weight;
+
+void main() {
+    vec4 sum = texture(accum, uv);
+    float revealage = sum.a;
+
+    // Nothing was drawn here
+    if (revealage == 1.0) {
+        discard;
+    }
+
+    vec3 average = sum.rgb / max(texture(weight, uv).r, 1e-5);
+    colour = vec4(average, 1.0 - revealage);
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+
+out vec2 uv;
+
+void main() {
+    // The quad covers the whole screen
+    gl_Position = vec4(position.xy * 2.0 - 1.0, 0.0, 1.0);
+    uv = position.xy;
+}
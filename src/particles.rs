@@ -0,0 +1,587 @@
+#![deny(missing_docs)]
+//! # Particles
+//!
+//! A [`ParticleEmitter`] spawns particles, moves them and ages them, and
+//! turns them into vertex data for a single call to [`Renderer::render`].
+//!
+//! Particles are kept in a pool of arrays (one per property) rather than
+//! as a list of sprites, and live in world space: moving the emitter
+//! does not move the particles it has already spawned.
+//!
+//! All randomness comes from a generator seeded when the emitter is
+//! created, so the same seed and the same updates always give the same
+//! particles.
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, viewport: Viewport) -> Result<()> {
+//! use nightmaregl::particles::{Curve, ParticleEmitter};
+//!
+//! let mut sparks = ParticleEmitter::new(Size::new(8.0, 8.0), 1234);
+//! sparks.rate = 200.0;
+//! sparks.lifetime = (0.5, 1.0);
+//! sparks.speed = (100.0, 200.0);
+//! sparks.direction = Rotation::degrees(90.0);
+//! sparks.spread = Rotation::degrees(45.0);
+//! sparks.gravity = Vector::new(0.0, -400.0);
+//! sparks.color = Curve::linear(Color::white(), Color::white().with_alpha(0.0));
+//! sparks.blend_mode = BlendMode::Additive;
+//!
+//! // Every frame
+//! sparks.update(0.016);
+//! sparks.render(&renderer, &texture, &viewport, &mut context)?;
+//! # Ok(())
+//! # }
+//! ```
+use num_traits::cast::NumCast;
+
+use crate::tween::{Easing, Lerp};
+use crate::{
+    BlendMode, Color, Context, DepthFunc, DepthMode, Point, Position, Rect, Renderer, Result, Rotation, Size, Sprite,
+    Texture, Transform, Vector, VertexData, Viewport,
+};
+
+// -----------------------------------------------------------------------------
+//     - Curve -
+// -----------------------------------------------------------------------------
+/// A value that changes over the life of a particle.
+///
+/// The curve is made of keys from 0.0 (when the particle is spawned)
+/// to 1.0 (when it dies), and the value is interpolated between them.
+///
+/// ```
+/// use nightmaregl::particles::Curve;
+///
+/// // Grow quickly, then shrink
+/// let scale = Curve::constant(0.0).key(0.25, 1.0).key(1.0, 0.0);
+///
+/// assert_eq!(scale.sample(0.125), 0.5);
+/// assert_eq!(scale.sample(0.625), 0.5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Curve<V> {
+    keys: Vec<(f32, V)>,
+    easing: Easing,
+}
+
+impl<V: Lerp> Curve<V> {
+    /// The same value for the whole life of the particle.
+    pub fn constant(value: V) -> Self {
+        Self {
+            keys: vec![(0.0, value)],
+            easing: Easing::Linear,
+        }
+    }
+
+    /// From one value at the start of the life of the particle to another at the end.
+    pub fn linear(from: V, to: V) -> Self {
+        Self::constant(from).key(1.0, to)
+    }
+
+    /// Add a key. `t` is clamped to 0.0 to 1.0.
+    /// A key at the same time as an existing key replaces it.
+    pub fn key(mut self, t: f32, value: V) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        match self.keys.iter().position(|(key, _)| *key >= t) {
+            Some(i) if self.keys[i].0 == t => self.keys[i].1 = value,
+            Some(i) => self.keys.insert(i, (t, value)),
+            None => self.keys.push((t, value)),
+        }
+        self
+    }
+
+    /// Easing applied between every pair of keys.
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// The value at `t`, from 0.0 to 1.0
+    pub fn sample(&self, t: f32) -> V {
+        let next = match self.keys.iter().position(|(key, _)| *key > t) {
+            Some(0) => return self.keys[0].1,
+            Some(next) => next,
+            None => return self.keys[self.keys.len() - 1].1,
+        };
+
+        let (from_t, from) = self.keys[next - 1];
+        let (to_t, to) = self.keys[next];
+        from.lerp(to, self.easing.apply((t - from_t) / (to_t - from_t)))
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Random -
+//     Xorshift, so a seed gives the same particles on every platform
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone)]
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero
+        match seed {
+            0 => Self(0x9e37_79b9_7f4a_7c15),
+            seed => Self(seed),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // 0.0 to 1.0, excluding 1.0
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Pool -
+// -----------------------------------------------------------------------------
+#[derive(Debug, Clone, Default)]
+struct Pool {
+    positions: Vec<Position<f32>>,
+    velocities: Vec<Vector<f32>>,
+    rotations: Vec<f32>,
+    ages: Vec<f32>,
+    lifetimes: Vec<f32>,
+}
+
+impl Pool {
+    fn len(&self) -> usize {
+        self.ages.len()
+    }
+
+    fn push(&mut self, position: Position<f32>, velocity: Vector<f32>, rotation: f32, lifetime: f32) {
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.rotations.push(rotation);
+        self.ages.push(0.0);
+        self.lifetimes.push(lifetime);
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        self.positions.swap_remove(index);
+        self.velocities.swap_remove(index);
+        self.rotations.swap_remove(index);
+        self.ages.swap_remove(index);
+        self.lifetimes.swap_remove(index);
+    }
+
+    fn clear(&mut self) {
+        self.positions.clear();
+        self.velocities.clear();
+        self.rotations.clear();
+        self.ages.clear();
+        self.lifetimes.clear();
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Particle emitter -
+// -----------------------------------------------------------------------------
+/// Spawns and simulates particles.
+///
+/// Ranges are `(min, max)` tuples, and every particle gets a random
+/// value in the range when it's spawned.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    /// Where particles are spawned, in world space.
+    pub position: Position<f32>,
+    /// Particles are spawned anywhere in an area this size,
+    /// centred on the position. Zero spawns them all at the position.
+    pub spawn_area: Size<f32>,
+    /// Particles spawned per second while emitting.
+    pub rate: f32,
+    /// Spawn particles at `rate`. Bursts are spawned either way.
+    pub emitting: bool,
+    /// Bursts of particles: the time since the emitter was created
+    /// (in seconds) and how many particles to spawn.
+    pub bursts: Vec<(f32, u32)>,
+    /// How many seconds a particle lives.
+    pub lifetime: (f32, f32),
+    /// Initial speed in units per second.
+    pub speed: (f32, f32),
+    /// Direction of the initial velocity. Zero is to the right.
+    pub direction: Rotation<f32>,
+    /// Width of the cone around the direction that particles are sent in.
+    pub spread: Rotation<f32>,
+    /// Acceleration in units per second squared.
+    pub gravity: Vector<f32>,
+    /// How quickly particles slow down. With a drag of 1.0 the velocity
+    /// of a particle drops to about a third every second.
+    pub drag: f32,
+    /// Initial rotation of a particle.
+    pub start_rotation: (Rotation<f32>, Rotation<f32>),
+    /// Rotation over life, added to the initial rotation.
+    pub rotation: Curve<Rotation<f32>>,
+    /// Size over life.
+    pub size: Curve<Size<f32>>,
+    /// Colour over life, used as the tint of the particle.
+    pub color: Curve<Color>,
+    /// Areas of the texture (in pixels) shown over the life of a particle,
+    /// each for the same amount of time.
+    /// Defaults to the whole texture.
+    pub frames: Vec<Rect<f32>>,
+    /// The z index of every particle.
+    pub z_index: i32,
+    /// How particles are blended, e.g. [`BlendMode::Additive`] for sparks.
+    pub blend_mode: BlendMode,
+    /// Depth testing of the particles. Every particle has the same z index,
+    /// so by default particles are tested against the depth buffer but
+    /// don't write to it: they are hidden behind sprites in front of
+    /// them, but never hide each other.
+    pub depth_mode: DepthMode,
+    /// The most particles alive at once. New particles are not
+    /// spawned while the pool is full.
+    pub max_particles: usize,
+    texture_size: Size<f32>,
+    random: Random,
+    pool: Pool,
+    time: f32,
+    // Fraction of a particle owed from the last update
+    owed: f32,
+    vertex_data: Vec<VertexData>,
+}
+
+impl ParticleEmitter {
+    /// Create an emitter for particles drawn with a texture of `texture_size`.
+    /// The seed is used for all the randomness of the emitter.
+    pub fn new(texture_size: Size<f32>, seed: u64) -> Self {
+        Self {
+            position: Position::zero(),
+            spawn_area: Size::zero(),
+            rate: 10.0,
+            emitting: true,
+            bursts: Vec::new(),
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 50.0),
+            direction: Rotation::degrees(90.0),
+            spread: Rotation::zero(),
+            gravity: Vector::zero(),
+            drag: 0.0,
+            start_rotation: (Rotation::zero(), Rotation::zero()),
+            rotation: Curve::constant(Rotation::zero()),
+            size: Curve::constant(texture_size),
+            color: Curve::constant(Color::white()),
+            frames: vec![Rect::new(Point::zero(), texture_size)],
+            z_index: 50,
+            blend_mode: BlendMode::Alpha,
+            depth_mode: DepthMode::Test(DepthFunc::LessOrEqual),
+            max_particles: 1000,
+            texture_size,
+            random: Random::new(seed),
+            pool: Pool::default(),
+            time: 0.0,
+            owed: 0.0,
+            vertex_data: Vec::new(),
+        }
+    }
+
+    /// Number of live particles.
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Are there no live particles.
+    pub fn is_empty(&self) -> bool {
+        self.pool.len() == 0
+    }
+
+    /// Remove all particles.
+    pub fn clear(&mut self) {
+        self.pool.clear();
+        self.owed = 0.0;
+    }
+
+    /// Spawn a number of particles right away.
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.pool.len() >= self.max_particles {
+            return;
+        }
+
+        let random = &mut self.random;
+        let offset = Vector::new(
+            (random.next_f32() - 0.5) * self.spawn_area.width,
+            (random.next_f32() - 0.5) * self.spawn_area.height,
+        );
+
+        let half_spread = self.spread.radians / 2.0;
+        let angle = self.direction.radians + random.range((-half_spread, half_spread));
+        let speed = random.range(self.speed);
+        let velocity = Vector::new(angle.cos(), angle.sin()) * speed;
+
+        let rotation = random.range((self.start_rotation.0.radians, self.start_rotation.1.radians));
+        let lifetime = random.range(self.lifetime);
+
+        self.pool.push(self.position + offset, velocity, rotation, lifetime);
+    }
+
+    /// Move and age the particles, remove the ones that died,
+    /// and spawn new ones.
+    ///
+    /// A `dt` that is NaN or infinite is ignored, as it would
+    /// poison the particles and stop the emitter for good.
+    pub fn update(&mut self, dt: f32) {
+        if !dt.is_finite() {
+            return;
+        }
+
+        let pool = &mut self.pool;
+
+        let mut i = 0;
+        while i < pool.len() {
+            pool.ages[i] += dt;
+            match pool.ages[i] >= pool.lifetimes[i] {
+                true => pool.swap_remove(i),
+                false => i += 1,
+            }
+        }
+
+        let damping = (-self.drag * dt).exp();
+        for (position, velocity) in pool.positions.iter_mut().zip(&mut pool.velocities) {
+            *velocity = (*velocity + self.gravity * dt) * damping;
+            *position += *velocity * dt;
+        }
+
+        let before = self.time;
+        self.time += dt;
+
+        let bursts = self
+            .bursts
+            .iter()
+            .filter(|(time, _)| *time >= before && *time < self.time)
+            .map(|(_, count)| count)
+            .sum();
+        self.burst(bursts);
+
+        if self.emitting {
+            self.owed += self.rate * dt;
+            let count = self.owed.floor();
+            self.owed -= count;
+            self.burst(count as u32);
+        }
+    }
+
+    /// The vertex data of every live particle.
+    pub fn vertex_data(&mut self) -> &[VertexData] {
+        let mut sprite = Sprite::from_size(self.texture_size);
+        sprite.z_index = self.z_index;
+
+        let pool = &self.pool;
+        self.vertex_data.clear();
+
+        for i in 0..pool.len() {
+            let life = pool.ages[i] / pool.lifetimes[i];
+
+            let frame = ((life * self.frames.len() as f32) as usize).min(self.frames.len().saturating_sub(1));
+            if let Some(rect) = self.frames.get(frame) {
+                sprite.texture_rect = *rect;
+            }
+
+            sprite.size = self.size.sample(life);
+            sprite.anchor = Position::new(sprite.size.width / 2.0, sprite.size.height / 2.0);
+            sprite.tint = self.color.sample(life);
+
+            let mut transform = Transform::new(pool.positions[i]);
+            transform.rotation = Rotation::radians(pool.rotations[i]) + self.rotation.sample(life);
+
+            self.vertex_data.push(VertexData::new(&sprite, &transform));
+        }
+
+        &self.vertex_data
+    }
+
+    /// Draw every live particle with a single draw call,
+    /// using the blend mode and the depth mode of the emitter.
+    pub fn render<U: Copy + NumCast>(
+        &mut self,
+        renderer: &Renderer<VertexData>,
+        texture: &Texture<U>,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        self.vertex_data();
+        renderer.render_with_modes(texture, &self.vertex_data, self.blend_mode, self.depth_mode, viewport, context)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn emitter(seed: u64) -> ParticleEmitter {
+        let mut emitter = ParticleEmitter::new(Size::new(4.0, 4.0), seed);
+        emitter.rate = 100.0;
+        emitter.lifetime = (0.5, 1.0);
+        emitter.speed = (10.0, 20.0);
+        emitter.spread = Rotation::degrees(90.0);
+        emitter.spawn_area = Size::new(10.0, 10.0);
+        emitter
+    }
+
+    fn positions(emitter: &mut ParticleEmitter) -> Vec<(f32, f32)> {
+        emitter.vertex_data().iter().map(|v| (v.model[(0, 3)], v.model[(1, 3)])).collect()
+    }
+
+    #[test]
+    fn same_seed_same_particles() {
+        let mut a = emitter(7);
+        let mut b = emitter(7);
+        let mut c = emitter(8);
+
+        for _ in 0..30 {
+            a.update(0.016);
+            b.update(0.016);
+            c.update(0.016);
+        }
+
+        assert!(!a.is_empty());
+        assert_eq!(positions(&mut a), positions(&mut b));
+        assert_ne!(positions(&mut a), positions(&mut c));
+    }
+
+    #[test]
+    fn non_finite_dt_is_ignored() {
+        let mut emitter = emitter(3);
+        emitter.update(0.1);
+        let before = positions(&mut emitter);
+
+        emitter.update(f32::NAN);
+        emitter.update(f32::INFINITY);
+        assert_eq!(positions(&mut emitter), before);
+
+        // Still spawning
+        emitter.update(0.1);
+        assert!(emitter.len() > before.len());
+        assert!(positions(&mut emitter).iter().all(|(x, y)| x.is_finite() && y.is_finite()));
+    }
+
+    #[test]
+    fn rate_bursts_and_lifetime() {
+        let mut emitter = emitter(1);
+        emitter.lifetime = (1.0, 1.0);
+        emitter.bursts = vec![(0.0, 20), (0.5, 5)];
+
+        // 100 per second over 0.25 seconds, and the first burst
+        emitter.update(0.125);
+        emitter.update(0.125);
+        assert_eq!(emitter.len(), 25 + 20);
+
+        emitter.emitting = false;
+        emitter.update(0.5);
+        assert_eq!(emitter.len(), 45 + 5);
+
+        // Everything spawned in the first update has died
+        emitter.update(0.375);
+        assert_eq!(emitter.len(), 13 + 5);
+
+        emitter.update(1.0);
+        assert!(emitter.is_empty());
+
+        emitter.max_particles = 10;
+        emitter.burst(20);
+        assert_eq!(emitter.len(), 10);
+    }
+
+    #[test]
+    fn gravity_and_drag() {
+        let mut emitter = ParticleEmitter::new(Size::new(2.0, 2.0), 3);
+        emitter.emitting = false;
+        emitter.speed = (0.0, 0.0);
+        emitter.gravity = Vector::new(0.0, -10.0);
+        emitter.burst(1);
+
+        emitter.update(0.5);
+        // Anchored in the centre: the corner is one unit down and to the left
+        assert_eq!(positions(&mut emitter), vec![(-1.0, -1.0 - 2.5)]);
+
+        emitter.clear();
+        emitter.gravity = Vector::zero();
+        emitter.speed = (10.0, 10.0);
+        emitter.direction = Rotation::zero();
+        emitter.drag = 2.0;
+        emitter.burst(1);
+        emitter.update(0.5);
+        let (x, _) = positions(&mut emitter)[0];
+        assert!((x - (-1.0 + 10.0 * (-1.0f32).exp() * 0.5)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn over_life() {
+        let mut emitter = ParticleEmitter::new(Size::new(32.0, 8.0), 5);
+        emitter.emitting = false;
+        emitter.lifetime = (2.0, 2.0);
+        emitter.size = Curve::linear(Size::new(8.0, 8.0), Size::new(16.0, 16.0));
+        emitter.color = Curve::linear(Color::white(), Color::black());
+        emitter.rotation = Curve::linear(Rotation::zero(), Rotation::degrees(180.0));
+        emitter.frames = (0..4).map(|i| Rect::new(Point::new(i as f32 * 8.0, 0.0), Size::new(8.0, 8.0))).collect();
+        emitter.burst(1);
+        emitter.update(1.0);
+
+        let vertex_data = emitter.vertex_data()[0];
+        assert_eq!(vertex_data.tint, Color { r: 0.5, g: 0.5, b: 0.5, a: 1.0 });
+        assert_eq!(vertex_data.texture_position.0, 0.5);
+        assert_eq!(vertex_data.texture_size, (0.25, 1.0));
+
+        // A quarter turn of a 12 unit quad
+        let model = vertex_data.model;
+        assert!(model[(0, 0)].abs() < 1e-4);
+        assert!((model[(1, 0)] - 12.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn overlapping_additive_particles_sum() {
+        use crate::pixels::{Pixel, Pixels};
+        use crate::renderer::software::{Renderer, Target};
+
+        let mut emitter = ParticleEmitter::new(Size::new(4.0, 4.0), 9);
+        emitter.emitting = false;
+        emitter.speed = (0.0, 0.0);
+        emitter.position = Position::new(4.0, 4.0);
+        emitter.color = Curve::constant(Color { r: 0.25, g: 0.0, b: 0.0, a: 1.0 });
+        emitter.blend_mode = BlendMode::Additive;
+        emitter.burst(2);
+
+        let renderer = Renderer {
+            blend_mode: emitter.blend_mode,
+            depth_mode: emitter.depth_mode,
+            ..Renderer::default()
+        };
+        let texture = Pixels::from_pixel(Pixel::white(), Size::new(4, 4));
+        let mut target = Target::new(Size::new(8, 8));
+        target.clear(Color::black());
+        let viewport = Viewport::new(Position::zero(), Size::new(8, 8));
+        renderer.render(&texture, emitter.vertex_data(), &viewport, &mut target);
+
+        // Both particles add a quarter red
+        assert_eq!(target.pixel(Position::new(4, 4)).r, 128);
+    }
+
+    #[test]
+    fn curve_keys() {
+        let curve = Curve::linear(0.0, 1.0).key(0.5, 4.0).easing(Easing::QuadIn);
+        assert_eq!(curve.sample(-1.0), 0.0);
+        assert_eq!(curve.sample(0.25), 1.0);
+        assert_eq!(curve.sample(0.5), 4.0);
+        assert_eq!(curve.sample(2.0), 1.0);
+    }
+}
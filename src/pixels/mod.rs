@@ -10,10 +10,15 @@
 //!
 //! let bytes = pixels.as_bytes();
 //! ```
+use std::fs::File;
+use std::io::BufWriter;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::path::Path;
 
 use bytemuck::Pod;
+use png::{ColorType, Decoder};
 
+use crate::errors::{NightmareError, Result};
 use crate::{Position, Size};
 
 mod region;
@@ -84,7 +89,7 @@ impl<T: Pod> Pixels<T> {
         &self,
         position: Position<usize>,
         size: Size<usize>,
-    ) -> Region<T> {
+    ) -> Region<'_, T> {
         debug_assert!(self.size.width >= size.width + position.x);
         debug_assert!(self.size.height >= size.height + position.y);
 
@@ -103,7 +108,7 @@ impl<T: Pod> Pixels<T> {
         &mut self,
         position: Position<usize>,
         size: Size<usize>,
-    ) -> RegionMut<T> {
+    ) -> RegionMut<'_, T> {
         debug_assert!(self.size.width >= size.width + position.x);
         debug_assert!(self.size.height >= size.height + position.y);
 
@@ -143,6 +148,53 @@ impl<T: Pod> Pixels<T> {
     }
 }
 
+// -----------------------------------------------------------------------------
+//     - Png -
+// -----------------------------------------------------------------------------
+impl Pixels<Pixel> {
+    /// Load a png from disk without involving the GPU.
+    /// Grayscale images are converted to RGBA.
+    pub fn from_disk(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())?;
+        let decoder = Decoder::new(file);
+        let (info, mut reader) = decoder.read_info()?;
+
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(NightmareError::InvalidColorType);
+        }
+
+        let mut bytes = vec![0; info.buffer_size()];
+        reader.next_frame(&mut bytes)?;
+
+        let pixels = match info.color_type {
+            ColorType::RGBA => bytemuck::cast_slice::<u8, Pixel>(&bytes).to_vec(),
+            ColorType::Grayscale => bytes
+                .iter()
+                .map(|&v| Pixel { r: v, g: v, b: v, a: 255 })
+                .collect(),
+            _ => return Err(NightmareError::InvalidColorType),
+        };
+
+        let size = Size::new(info.width as usize, info.height as usize);
+        Ok(Self::new(pixels, size))
+    }
+
+    /// Write the pixels to disk as an RGBA png.
+    pub fn write_to_disk(&self, dst: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(dst.as_ref())?;
+        let mut writer = BufWriter::new(file);
+        let size = self.size.cast::<u32>();
+        let mut encoder = png::Encoder::new(&mut writer, size.width, size.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.as_bytes())?;
+
+        Ok(())
+    }
+}
+
 // -----------------------------------------------------------------------------
 //     - Pixels trait impls -
 // -----------------------------------------------------------------------------
@@ -1,7 +1,6 @@
 #![deny(missing_docs)]
 //! Default renderer.
 //! Also contains [`VertexData`].
-use std::ffi::CStr;
 use std::ops::{Div, MulAssign};
 
 use gl33::global_loader::*;
@@ -11,10 +10,11 @@ use num_traits::cast::NumCast;
 use num_traits::{One, Zero};
 
 use super::shaders::ShaderProgram;
-use super::{GlType, Vbo, Vertex, VertexPointers, QUAD};
+use super::uniform::Uniforms;
+use super::{Attribute, GlType, InstanceBuffer, Vbo, Vertex, VertexPointers, QUAD};
 use crate::context::{Context, Vao};
-use crate::sprite::{FillMode, Sprite};
-use crate::{Result, Texture, Transform, Viewport};
+use crate::sprite::{FillMode, SliceFill, Sprite, UvRotation};
+use crate::{BlendMode, Color, DepthMode, Position, Rect, Result, Size, Texture, Transform, Viewport};
 
 /// Default vertex data
 #[derive(Debug, Clone, Copy)]
@@ -31,6 +31,16 @@ pub struct VertexData {
 
     /// Tile count
     pub tile_count: (f32, f32),
+
+    /// Multiplied with the colour of the texture
+    pub tint: Color,
+
+    /// Added to the colour of the texture, scaled by its alpha
+    pub flash: Color,
+
+    /// Flips and rotation of the texture, as a column major 2x2 matrix
+    /// applied to the texture coordinates around the centre of each tile
+    pub uv_transform: [f32; 4],
 }
 
 impl VertexData {
@@ -44,19 +54,171 @@ impl VertexData {
                 let size = sprite.size.to_f32();
                 let total_texture_size = sprite.texture_size.to_f32();
                 let (texture_width, texture_height) = sprite.get_texture_size();
-                let x = size.width / texture_width / total_texture_size.width;
-                let y = size.height / texture_height / total_texture_size.height;
-                (x, y)
+                let tile = Size::new(
+                    texture_width * total_texture_size.width,
+                    texture_height * total_texture_size.height,
+                );
+
+                // A sideways texture covers its height horizontally
+                let tile = match sprite.uv_rotation.is_sideways() {
+                    true => Size::new(tile.height, tile.width),
+                    false => tile,
+                };
+
+                (size.width / tile.width, size.height / tile.height)
             }
-            FillMode::Stretch => (1.0, 1.0),
+            FillMode::Stretch | FillMode::NineSlice { .. } => (1.0, 1.0),
         };
 
         VertexData {
-            model: Self::create_model(sprite, &transform),
+            model: Self::create_model(sprite, transform),
             texture_position: sprite.get_texture_position(),
             texture_size: sprite.get_texture_size(),
             tile_count,
+            tint: sprite.tint,
+            flash: sprite.flash,
+            uv_transform: sprite.get_uv_transform(),
+        }
+    }
+
+    /// All the instances needed to draw a sprite.
+    ///
+    /// This is a single instance, the same as [`VertexData::new`], unless
+    /// the sprite uses [`FillMode::NineSlice`], in which case there is one
+    /// instance for every part of the grid that isn't empty.
+    ///
+    /// Flips are applied to the whole sprite. The `uv_rotation` is
+    /// ignored for nine slice sprites.
+    ///
+    /// ```
+    /// use nightmaregl::{FillMode, Size, SliceFill, Sprite, Transform, VertexData};
+    ///
+    /// // A 48x48 panel texture with 16 pixel borders, drawn at 200x100
+    /// let mut sprite = Sprite::<f32>::from_size(Size::new(48.0, 48.0));
+    /// sprite.size = Size::new(200.0, 100.0);
+    /// sprite.fill = FillMode::NineSlice {
+    ///     left: 16.0,
+    ///     right: 16.0,
+    ///     top: 16.0,
+    ///     bottom: 16.0,
+    ///     edges: SliceFill::Repeat,
+    ///     centre: SliceFill::Stretch,
+    /// };
+    ///
+    /// let instances = VertexData::instances(&sprite, &Transform::default());
+    /// assert_eq!(instances.len(), 9);
+    /// ```
+    pub fn instances<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>>(
+        sprite: &Sprite<T>,
+        transform: &Transform<T>,
+    ) -> Vec<Self> {
+        let (left, right, top, bottom, edges, centre) = match sprite.fill {
+            FillMode::NineSlice { left, right, top, bottom, edges, centre } => (left, right, top, bottom, edges, centre),
+            _ => return vec![Self::new(sprite, transform)],
+        };
+
+        let transform = Transform {
+            translation: transform.translation.to_f32(),
+            scale: transform.scale.to_f32(),
+            rotation: transform.rotation.to_f32(),
+        };
+
+        let texture_rect = sprite.texture_rect.to_f32();
+        let size = sprite.size.to_f32();
+        let anchor = sprite.anchor.to_f32();
+
+        // Borders in the texture, and on the sprite. If the sprite is smaller
+        // than the borders, the borders shrink to fit.
+        let texture_columns = slices(texture_rect.size.width, left, right);
+        let texture_rows = slices(texture_rect.size.height, top, bottom);
+        let fit = |size: f32, a: f32, b: f32| match a + b > size && a + b > 0.0 {
+            true => (a * size / (a + b), b * size / (a + b)),
+            false => (a, b),
+        };
+        let (left, right) = fit(size.width, left, right);
+        let (top, bottom) = fit(size.height, top, bottom);
+        let columns = slices(size.width, left, right);
+        let rows = slices(size.height, top, bottom);
+
+        let mut instances = Vec::with_capacity(9);
+
+        for (row, (y, height)) in rows.iter().enumerate() {
+            for (column, (x, width)) in columns.iter().enumerate() {
+                if *width <= 0.0 || *height <= 0.0 {
+                    continue;
+                }
+
+                let fill = match (column, row) {
+                    (1, 1) => centre,
+                    (1, _) | (_, 1) => edges,
+                    _ => SliceFill::Stretch,
+                };
+
+                let (texture_x, texture_width) = texture_columns[column];
+                let (texture_y, texture_height) = texture_rows[row];
+
+                // Rows go from the top of the texture, but from the bottom of the sprite
+                let offset_x = match sprite.flip_x {
+                    true => size.width - x - width,
+                    false => *x,
+                };
+                let offset_y = match sprite.flip_y {
+                    true => *y,
+                    false => size.height - y - height,
+                };
+
+                let part = Sprite {
+                    texture_size: sprite.texture_size.to_f32(),
+                    size: Size::new(*width, *height),
+                    texture_rect: Rect::new(
+                        texture_rect.origin + Position::new(texture_x, texture_y),
+                        Size::new(texture_width, texture_height),
+                    ),
+                    anchor: Position::new(anchor.x - offset_x, anchor.y - offset_y),
+                    z_index: sprite.z_index,
+                    fill: match fill {
+                        SliceFill::Stretch => FillMode::Stretch,
+                        SliceFill::Repeat => FillMode::Repeat,
+                    },
+                    tint: sprite.tint,
+                    flash: sprite.flash,
+                    flip_x: sprite.flip_x,
+                    flip_y: sprite.flip_y,
+                    uv_rotation: UvRotation::None,
+                };
+
+                instances.push(Self::new(&part, &transform));
+            }
         }
+
+        instances
+    }
+
+    /// The coordinates in the texture for a point on the quad, where
+    /// 0, 0 is the top left of the sprite and 1, 1 the bottom right.
+    /// This is what the default shaders sample.
+    ///
+    /// ```
+    /// use nightmaregl::{Size, Sprite, Transform, VertexData};
+    ///
+    /// let mut sprite = Sprite::<f32>::from_size(Size::new(32.0, 32.0));
+    /// sprite.flip_x = true;
+    /// let vertex_data = VertexData::new(&sprite, &Transform::default());
+    ///
+    /// // The left edge of the sprite shows the right edge of the texture
+    /// assert_eq!(vertex_data.texture_coords((0.0, 0.0)), (1.0, 0.0));
+    /// ```
+    pub fn texture_coords(&self, uv: (f32, f32)) -> (f32, f32) {
+        let tiled = (uv.0 * self.tile_count.0, uv.1 * self.tile_count.1);
+        let fract = |f: f32| f - f.floor();
+        let (x, y) = (fract(tiled.0) - 0.5, fract(tiled.1) - 0.5);
+        let m = self.uv_transform;
+        let coords = (m[0] * x + m[2] * y + 0.5, m[1] * x + m[3] * y + 0.5);
+
+        (
+            self.texture_position.0 + coords.0 * self.texture_size.0,
+            self.texture_position.1 + coords.1 * self.texture_size.1,
+        )
     }
 
     /// Make the vertex data relative to another transformation.
@@ -116,6 +278,12 @@ impl VertexData {
     }
 }
 
+// Offset and length of the three parts of a nine slice
+fn slices(length: f32, start: f32, end: f32) -> [(f32, f32); 3] {
+    let middle = (length - start - end).max(0.0);
+    [(0.0, start), (start, middle), (start + middle, end)]
+}
+
 /// Default vertex pointers for [`crate::VertexData`].
 /// To use different vertex data with a different layout create new `VertexPointers` with
 /// a different layout.
@@ -129,6 +297,10 @@ pub fn default_vertex_pointers<T>(context: &mut Context) -> VertexPointers<T> {
         .add(10, 2, GlType::Float, false)
         .add(11, 2, GlType::Float, false)
         .add(12, 2, GlType::Float, false)
+        .add(7, 4, GlType::Float, false)
+        .add(8, 4, GlType::Float, false)
+        .add(13, 2, GlType::Float, false)
+        .add(14, 2, GlType::Float, false)
 }
 
 /// The default renderer.
@@ -148,10 +320,15 @@ pub fn default_vertex_pointers<T>(context: &mut Context) -> VertexPointers<T> {
 pub struct Renderer<T> {
     vao: Vao,
     vbo: Vbo<T>,
+    attributes: Vec<Attribute>,
     _quad_vbo: Vbo<Vertex>,
     shader_program: ShaderProgram,
     /// Multiplier for the size of a pixel.
     pub pixel_size: i32,
+    /// How the rendered sprites are blended with the frame buffer.
+    pub blend_mode: BlendMode,
+    /// Depth testing of the rendered sprites.
+    pub depth_mode: DepthMode,
 }
 
 impl<T: std::fmt::Debug> Renderer<T> {
@@ -172,6 +349,7 @@ impl<T: std::fmt::Debug> Renderer<T> {
     /// Create a new renderer.
     /// A renderer needs both a vertex shader and a fragment shader.
     pub fn new(vertex_pointers: VertexPointers<T>, shader_program: ShaderProgram) -> Result<Self> {
+        let attributes = vertex_pointers.attributes().to_vec();
         let (vao, vbo) = vertex_pointers.build();
 
         let (vao, quad_vbo) = VertexPointers::new(vao)
@@ -184,9 +362,12 @@ impl<T: std::fmt::Debug> Renderer<T> {
         let inst = Self {
             vao,
             vbo,
+            attributes,
             shader_program,
             _quad_vbo: quad_vbo,
             pixel_size: 1,
+            blend_mode: BlendMode::Alpha,
+            depth_mode: DepthMode::default(),
         };
 
         Ok(inst)
@@ -200,9 +381,112 @@ impl<T: std::fmt::Debug> Renderer<T> {
         vertex_data: &[T],
         viewport: &Viewport,
         context: &mut Context,
+    ) -> Result<()> {
+        self.render_blended(texture, vertex_data, self.blend_mode, viewport, context)
+    }
+
+    /// Render vertex data with a blend mode other than the one
+    /// set on the renderer.
+    pub fn render_blended<U: Copy + NumCast>(
+        &self,
+        texture: &Texture<U>,
+        vertex_data: &[T],
+        blend_mode: BlendMode,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        self.render_with_modes(texture, vertex_data, blend_mode, self.depth_mode, viewport, context)
+    }
+
+    /// Render vertex data with a blend mode and a depth mode
+    /// other than the ones set on the renderer.
+    pub fn render_with_modes<U: Copy + NumCast>(
+        &self,
+        texture: &Texture<U>,
+        vertex_data: &[T],
+        blend_mode: BlendMode,
+        depth_mode: DepthMode,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        self.prepare(texture, blend_mode, depth_mode, viewport, context)?;
+        self.vbo.load_data(vertex_data);
+        self.draw(vertex_data.len());
+
+        Ok(())
+    }
+
+    /// Render vertex data with a custom shader, setting its
+    /// uniforms after the renderer has set `vp` and `pixel_scale`.
+    /// See [`Uniforms`] for an example.
+    pub fn render_with_uniforms<U: Copy + NumCast>(
+        &self,
+        texture: &Texture<U>,
+        vertex_data: &[T],
+        uniforms: &impl Uniforms,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        self.prepare(texture, self.blend_mode, self.depth_mode, viewport, context)?;
+        uniforms.apply(&self.shader_program)?;
+        self.vbo.load_data(vertex_data);
+        self.draw(vertex_data.len());
+
+        Ok(())
+    }
+
+    /// The shader program of the renderer, for setting
+    /// uniforms that don't change between calls.
+    pub fn shader_program(&self) -> &ShaderProgram {
+        &self.shader_program
+    }
+
+    /// Render instance data that is already on the GPU.
+    /// Use this for data that rarely changes, like the chunks of a
+    /// [`crate::tilemap::TileMap`].
+    pub fn render_buffer<U: Copy + NumCast>(
+        &self,
+        texture: &Texture<U>,
+        buffer: &InstanceBuffer<T>,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.prepare(texture, self.blend_mode, self.depth_mode, viewport, context)?;
+
+        // Point the instance attributes at the buffer for this draw,
+        // then back at the buffer of the renderer
+        self.point_attributes(buffer.vbo());
+        self.draw(buffer.len());
+        self.point_attributes(&self.vbo);
+
+        Ok(())
+    }
+
+    // The vertex array has to be bound
+    fn point_attributes(&self, vbo: &Vbo<T>) {
+        vbo.enable();
+        for attribute in &self.attributes {
+            attribute.point::<T>();
+        }
+    }
+
+    // Bind everything and set the uniforms
+    fn prepare<U: Copy + NumCast>(
+        &self,
+        texture: &Texture<U>,
+        blend_mode: BlendMode,
+        depth_mode: DepthMode,
+        viewport: &Viewport,
+        context: &mut Context,
     ) -> Result<()> {
         self.shader_program.enable();
         context.bind_vao(&self.vao);
+        context.set_blend_mode(blend_mode);
+        context.set_depth_mode(depth_mode);
 
         unsafe {
             glViewport(
@@ -213,41 +497,216 @@ impl<T: std::fmt::Debug> Renderer<T> {
             );
         }
 
-        // -----------------------------------------------------------------------------
-        //     - Set transform / Texture offset -
-        //     Set the transform and the texture offset in one.
-        //     Load the uv coords from the texture.
-        // -----------------------------------------------------------------------------
         texture.bind();
 
-        let num_of_instances = vertex_data.len() as i32;
-
-        self.vbo.load_data(&vertex_data);
-
         // Clip
         let clip = viewport.projection * viewport.view;
+        self.shader_program.set_uniform("vp", &clip)?;
+        self.shader_program.set_uniform("pixel_scale", &(self.pixel_size as f32))?;
 
-        // TODO: cache this
-        let clip_uniform_name = CStr::from_bytes_with_nul(b"vp\0").expect("invalid c string");
-        self.shader_program
-            .set_uniform_matrix(clip, clip_uniform_name)?;
-
-        // TODO: cache this
-        let pixel_scale_uniform_name =
-            CStr::from_bytes_with_nul(b"pixel_scale\0").expect("invalid c string");
-
-        self.shader_program
-            .set_uniform_float(self.pixel_size as f32, pixel_scale_uniform_name)?;
+        Ok(())
+    }
 
+    fn draw(&self, instance_count: usize) {
         unsafe {
             glDrawArraysInstanced(
                 GL_TRIANGLE_STRIP,
                 0,
                 QUAD.len() as i32,
-                num_of_instances as i32,
+                instance_count as i32,
             )
         };
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sprite::UvRotation;
+    use crate::texture::Format;
+    use crate::{Point, Rect};
+    use super::super::shaders::Shader;
+
+    fn coords(sprite: &Sprite<f32>, uv: (f32, f32)) -> (f32, f32) {
+        VertexData::new(sprite, &Transform::default()).texture_coords(uv)
+    }
+
+    // A 4x2 texture, showing the right half
+    fn sprite() -> Sprite<f32> {
+        let mut sprite = Sprite::from_size(Size::new(4.0, 2.0));
+        sprite.texture_rect = Rect::new(Point::new(2.0, 0.0), Size::new(2.0, 2.0));
+        sprite.size = Size::new(2.0, 2.0);
+        sprite
+    }
+
+    #[test]
+    fn flips() {
+        let mut sprite = sprite();
+        assert_eq!(coords(&sprite, (0.25, 0.25)), (0.5 + 0.125, 0.25));
+
+        sprite.flip_x = true;
+        assert_eq!(coords(&sprite, (0.25, 0.25)), (1.0 - 0.125, 0.25));
+
+        sprite.flip_y = true;
+        assert_eq!(coords(&sprite, (0.25, 0.25)), (1.0 - 0.125, 0.75));
+    }
+
+    #[test]
+    fn rotations() {
+        let mut sprite = sprite();
+        // The top left of the sprite shows...
+        let top_left = |sprite: &Sprite<f32>| coords(sprite, (0.0, 0.0));
+
+        // ...the bottom left of the texture rect
+        sprite.uv_rotation = UvRotation::Clockwise90;
+        assert_eq!(top_left(&sprite), (0.5, 1.0));
+
+        // ...the bottom right
+        sprite.uv_rotation = UvRotation::Half;
+        assert_eq!(top_left(&sprite), (1.0, 1.0));
+
+        // ...the top right
+        sprite.uv_rotation = UvRotation::CounterClockwise90;
+        assert_eq!(top_left(&sprite), (1.0, 0.0));
+
+        // Rotated then flipped: the top right of the sprite shows the bottom left
+        sprite.uv_rotation = UvRotation::Clockwise90;
+        sprite.flip_x = true;
+        let top_right = coords(&sprite, (0.75, 0.25));
+        assert_eq!(top_right, (0.5 + 0.125, 0.75));
+    }
+
+    #[test]
+    fn repeat_with_rotation() {
+        let mut sprite = Sprite::<f32>::from_size(Size::new(4.0, 2.0));
+        sprite.size = Size::new(8.0, 8.0);
+        sprite.fill = FillMode::Repeat;
+        assert_eq!(VertexData::new(&sprite, &Transform::default()).tile_count, (2.0, 4.0));
+
+        sprite.uv_rotation = UvRotation::Clockwise90;
+        let vertex_data = VertexData::new(&sprite, &Transform::default());
+        assert_eq!(vertex_data.tile_count, (4.0, 2.0));
+
+        // Every tile is rotated on its own
+        assert_eq!(vertex_data.texture_coords((0.0, 0.0)), (0.0, 1.0));
+        assert_eq!(vertex_data.texture_coords((0.25, 0.0)), (0.0, 1.0));
+    }
+
+    fn nine_slice(edges: SliceFill) -> Sprite<f32> {
+        let mut sprite = Sprite::from_size(Size::new(12.0, 12.0));
+        sprite.size = Size::new(40.0, 20.0);
+        sprite.fill = FillMode::NineSlice {
+            left: 2.0,
+            right: 4.0,
+            top: 3.0,
+            bottom: 1.0,
+            edges,
+            centre: SliceFill::Stretch,
+        };
+        sprite
+    }
+
+    // Position and size of an instance
+    fn rect(vd: &VertexData) -> (f32, f32, f32, f32) {
+        (vd.model[(0, 3)], vd.model[(1, 3)], vd.model[(0, 0)], vd.model[(1, 1)])
+    }
+
+    #[test]
+    fn nine_slice_layout() {
+        let sprite = nine_slice(SliceFill::Stretch);
+        let instances = VertexData::instances(&sprite, &Transform::new(Position::new(100.0, 0.0)));
+        assert_eq!(instances.len(), 9);
+
+        // Top left corner at native size, at the top of the sprite
+        assert_eq!(rect(&instances[0]), (100.0, 17.0, 2.0, 3.0));
+        assert_eq!(instances[0].texture_position, (0.0, 0.0));
+        assert_eq!(instances[0].texture_size, (2.0 / 12.0, 3.0 / 12.0));
+
+        // Centre fills the rest
+        assert_eq!(rect(&instances[4]), (102.0, 1.0, 34.0, 16.0));
+        assert_eq!(instances[4].texture_position, (2.0 / 12.0, 3.0 / 12.0));
+
+        // Bottom right corner
+        assert_eq!(rect(&instances[8]), (136.0, 0.0, 4.0, 1.0));
+    }
+
+    #[test]
+    fn nine_slice_repeats_edges() {
+        let sprite = nine_slice(SliceFill::Repeat);
+        let instances = VertexData::instances(&sprite, &Transform::default());
+
+        // The top edge is 34 wide, repeating a 6 pixel wide part of the texture
+        assert_eq!(instances[1].tile_count, (34.0 / 6.0, 1.0));
+        // The left edge repeats vertically
+        assert_eq!(instances[3].tile_count, (1.0, 16.0 / 8.0));
+        // Corners and the centre stretch
+        assert_eq!(instances[0].tile_count, (1.0, 1.0));
+        assert_eq!(instances[4].tile_count, (1.0, 1.0));
+    }
+
+    #[test]
+    fn nine_slice_smaller_than_borders() {
+        let mut sprite = nine_slice(SliceFill::Stretch);
+        sprite.size = Size::new(3.0, 20.0);
+
+        // No middle column, and the borders shrink to fit
+        let instances = VertexData::instances(&sprite, &Transform::default());
+        assert_eq!(instances.len(), 6);
+        assert_eq!(rect(&instances[0]).2, 1.0);
+        assert_eq!(rect(&instances[1]).2, 2.0);
+    }
+
+    #[test]
+    fn nine_slice_flipped() {
+        let mut sprite = nine_slice(SliceFill::Stretch);
+        sprite.flip_x = true;
+
+        // The left column is drawn on the right
+        let instances = VertexData::instances(&sprite, &Transform::default());
+        assert_eq!(rect(&instances[0]), (38.0, 17.0, 2.0, 3.0));
+        assert_eq!(instances[0].texture_position, (0.0, 0.0));
+    }
+
+    const UNIFORMS_FRAGMENT: &str = "#version 330 core
+uniform sampler2D overlay;
+uniform vec4 tint;
+uniform float weights[2];
+out vec4 colour;
+
+void main() {
+    colour = texture(overlay, vec2(0.5)) * tint * (weights[0] + weights[1]);
+}
+";
+
+    #[test]
+    fn render_with_uniforms() {
+        let mut context = Context::builder("uniforms").build_headless(Size::new(4, 4)).unwrap();
+        let vertex = Shader::default_vertex().unwrap();
+        let fragment = Shader::new_fragment(UNIFORMS_FRAGMENT).unwrap();
+        let shader_program = ShaderProgram::new(vertex, fragment).unwrap();
+        let renderer = Renderer::new(default_vertex_pointers(&mut context), shader_program).unwrap();
+
+        let texture = Texture::<f32>::new().with_format(Format::Rgba).with_data(&[255; 4], Size::new(1.0, 1.0));
+        let overlay = Texture::<f32>::new().with_format(Format::Rgba).with_data(&[255, 128, 0, 255], Size::new(1.0, 1.0));
+
+        let mut sprite = Sprite::new(&texture);
+        sprite.size = Size::new(4.0, 4.0);
+        let vertex_data = [VertexData::new(&sprite, &Transform::default())];
+
+        let uniforms = |shader_program: &ShaderProgram| {
+            shader_program.set_texture("overlay", &overlay, 1)?;
+            shader_program.set_uniform("tint", &Color { r: 1.0, g: 0.5, b: 1.0, a: 1.0 })?;
+            shader_program.set_uniform("weights", &[0.25f32, 0.75])
+        };
+
+        let viewport = Viewport::new(Position::zero(), Size::new(4, 4));
+        context.clear(Color::black());
+        renderer.render_with_uniforms(&texture, &vertex_data, &uniforms, &viewport, &mut context).unwrap();
+
+        let mut pixels = [0u8; 4 * 4 * 4];
+        unsafe { glReadPixels(0, 0, 4, 4, GL_RGBA, GL_UNSIGNED_BYTE, pixels.as_mut_ptr().cast()) };
+        for pixel in pixels.chunks(4) {
+            assert_eq!(pixel, [255, 64, 0, 255]);
+        }
     }
 }
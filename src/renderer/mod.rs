@@ -7,9 +7,14 @@ use gl33::global_loader::*;
 use gl33::*;
 
 pub mod default;
+pub mod oit;
+pub mod shapes;
+pub mod software;
 mod shaders;
+pub mod uniform;
 
 pub use shaders::{FragmentShader, Shader, ShaderProgram, VertexShader};
+pub use uniform::{Sampler, Uniform, UniformArray, Uniforms};
 
 /// Vertex buffer object
 #[derive(Debug, PartialEq)]
@@ -34,7 +39,7 @@ impl<T> Vbo<T> {
         unsafe {
             glBufferData(
                 GL_ARRAY_BUFFER,
-                (size_of::<T>() * data.len()) as isize,
+                std::mem::size_of_val(data) as isize,
                 p.cast(),
                 GL_STATIC_DRAW,
             )
@@ -42,6 +47,58 @@ impl<T> Vbo<T> {
     }
 }
 
+// -----------------------------------------------------------------------------
+//     - Instance buffer -
+// -----------------------------------------------------------------------------
+/// Instance data kept on the GPU between frames.
+/// Only upload the data again when it changes, and draw it with
+/// [`default::Renderer::render_buffer`].
+#[derive(Debug)]
+pub struct InstanceBuffer<T> {
+    vbo: Vbo<T>,
+    len: usize,
+}
+
+impl<T> InstanceBuffer<T> {
+    /// Create an empty instance buffer
+    pub fn new() -> Self {
+        let mut vbo = 0;
+        unsafe { glGenBuffers(1, &mut vbo) };
+        assert_ne!(vbo, 0);
+
+        Self {
+            vbo: Vbo::new(vbo),
+            len: 0,
+        }
+    }
+
+    /// Replace the data in the buffer
+    pub fn load(&mut self, data: &[T]) {
+        self.vbo.load_data(data);
+        self.len = data.len();
+    }
+
+    /// Number of instances in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer has no instances
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn vbo(&self) -> &Vbo<T> {
+        &self.vbo
+    }
+}
+
+impl<T> Default for InstanceBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Drop for Vbo<T> {
     fn drop(&mut self) {
         unsafe { glDeleteBuffers(1, &self.0) };
@@ -86,6 +143,7 @@ pub fn new_vertex_pointers<T>(context: &mut Context) -> VertexPointers<T> {
 }
 
 /// OpenGL data type
+#[derive(Debug, Copy, Clone)]
 pub enum GlType {
     /// GL_FLOAT
     Float,
@@ -93,12 +151,55 @@ pub enum GlType {
     Int,
 }
 
+// -----------------------------------------------------------------------------
+//     - Attribute -
+//     Kept so the attributes can be pointed at another buffer
+//     without copying its data.
+// -----------------------------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Attribute {
+    position: u32,
+    param_count: i32,
+    gl_type: GlType,
+    normalized: bool,
+    offset: u32,
+}
+
+impl Attribute {
+    // Point the attribute at the buffer bound to GL_ARRAY_BUFFER,
+    // in the bound vertex array
+    pub(crate) fn point<T>(&self) {
+        match self.gl_type {
+            GlType::Float => unsafe {
+                glVertexAttribPointer(
+                    self.position,
+                    self.param_count,
+                    GL_FLOAT,
+                    self.normalized as u8,
+                    size_of::<T>() as i32,
+                    self.offset as *const _,
+                );
+            },
+            GlType::Int => unsafe {
+                glVertexAttribIPointer(
+                    self.position,
+                    self.param_count,
+                    GL_INT,
+                    size_of::<T>() as i32,
+                    self.offset as *const _,
+                );
+            },
+        };
+    }
+}
+
 /// Vertex pointers.
 pub struct VertexPointers<T> {
     next_offset: u32,
     vao: Vao,
     vbo: Vbo<T>,
     divisor: Option<u32>,
+    attributes: Vec<Attribute>,
 }
 
 impl<T> VertexPointers<T> {
@@ -114,6 +215,7 @@ impl<T> VertexPointers<T> {
             vao,
             vbo,
             divisor: None,
+            attributes: Vec::new(),
         }
     }
 
@@ -129,27 +231,15 @@ impl<T> VertexPointers<T> {
         gl_type: GlType,
         normalized: bool,
     ) -> Self {
-        match gl_type {
-            GlType::Float => unsafe {
-                glVertexAttribPointer(
-                    position,
-                    param_count,
-                    GL_FLOAT,
-                    normalized as u8,
-                    size_of::<T>() as i32,
-                    self.next_offset as *const _,
-                );
-            },
-            GlType::Int => unsafe {
-                glVertexAttribIPointer(
-                    position,
-                    param_count,
-                    GL_INT,
-                    size_of::<T>() as i32,
-                    self.next_offset as *const _,
-                );
-            },
+        let attribute = Attribute {
+            position,
+            param_count,
+            gl_type,
+            normalized,
+            offset: self.next_offset,
         };
+        attribute.point::<T>();
+        self.attributes.push(attribute);
 
         unsafe { glEnableVertexAttribArray(position) };
 
@@ -167,6 +257,10 @@ impl<T> VertexPointers<T> {
         self
     }
 
+    pub(crate) fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
     pub(crate) fn build(self) -> (Vao, Vbo<T>) {
         let VertexPointers { vbo, vao, .. } = self;
         (vao, vbo)
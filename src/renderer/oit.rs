@@ -0,0 +1,270 @@
+#![deny(missing_docs)]
+//! Order independent transparency.
+//!
+//! Translucent sprites normally have to be drawn back to front to blend
+//! correctly, which means sorting them every frame. The [`OitRenderer`]
+//! uses weighted blended order independent transparency instead:
+//!
+//! [http://jcgt.org/published/0002/02/09/](http://jcgt.org/published/0002/02/09/)
+//!
+//! Every translucent fragment is added to an accumulation target, weighted
+//! by its alpha and depth, and the average is drawn on top of the frame
+//! buffer at the end. The result is an approximation: overlapping sprites
+//! with very different colours and similar depths blend into one another.
+//!
+//! The depth weight falls off over z indices 0 to about 100, so that is
+//! the range where nearer sprites win. Beyond it every sprite gets the
+//! same weight. The targets are 16 bit floats, which overflow after
+//! about 650 fully opaque layers at the nearest depth.
+use gl33::global_loader::*;
+use gl33::*;
+use num_traits::cast::NumCast;
+
+use super::default::{default_vertex_pointers, Renderer, VertexData};
+use super::shaders::ShaderProgram;
+use super::{GlType, Vbo, QUAD};
+use crate::context::{Context, Vao};
+use crate::framebuffer::{Framebuffer, FramebufferTarget};
+use crate::texture::Format;
+use crate::{BlendEquation, BlendFactor, BlendFunc, BlendMode, DepthFunc, DepthMode};
+use crate::{Result, Size, Texture, Vertex, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Targets -
+//     The accumulated colour (with the revealage in alpha),
+//     the total weight and a copy of the depth buffer.
+// -----------------------------------------------------------------------------
+struct Targets {
+    framebuffer: Framebuffer,
+    accum: Texture<i32>,
+    weight: Texture<i32>,
+}
+
+impl Targets {
+    fn new(size: Size<i32>) -> Self {
+        let accum = Texture::<i32>::new().with_format(Format::Rgba16F).with_no_data(size);
+        let weight = Texture::<i32>::new().with_format(Format::Red16F).with_no_data(size);
+
+        let mut framebuffer = Framebuffer::default();
+        framebuffer.attach_texture_at(&accum, 0);
+        framebuffer.attach_texture_at(&weight, 1);
+        framebuffer.attach_depth_buffer(size);
+
+        framebuffer.bind();
+        let draw_buffers = [GL_COLOR_ATTACHMENT0, GL_COLOR_ATTACHMENT1];
+        unsafe { glDrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr()) };
+        framebuffer.unbind();
+
+        Self {
+            framebuffer,
+            accum,
+            weight,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - OIT renderer -
+// -----------------------------------------------------------------------------
+/// Draws translucent sprites in any order.
+///
+/// Draw everything opaque to the default frame buffer first, with depth
+/// testing and writing. Then draw the translucent sprites between
+/// [`OitRenderer::begin`] and [`OitRenderer::finish`]. These are hidden
+/// behind opaque sprites with a lower z index, but never hide each other.
+///
+/// The renderer has to be the same size as the window.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # use nightmaregl::renderer::oit::OitRenderer;
+/// # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, walls: Vec<VertexData>, glass: Vec<VertexData>, viewport: Viewport) -> Result<()> {
+/// let mut oit = OitRenderer::new(context.window_size(), &mut context)?;
+///
+/// context.clear(Color::black());
+/// renderer.render(&texture, &walls, &viewport, &mut context)?;
+///
+/// oit.begin();
+/// oit.render(&texture, &glass, &viewport, &mut context)?;
+/// oit.finish(&mut context)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct OitRenderer {
+    renderer: Renderer<VertexData>,
+    composite: ShaderProgram,
+    vao: Vao,
+    _quad_vbo: Vbo<Vertex>,
+    targets: Targets,
+    size: Size<i32>,
+    /// Multiplier for the size of a pixel.
+    pub pixel_size: i32,
+}
+
+impl OitRenderer {
+    /// Create a new renderer, the size of the window.
+    pub fn new(size: Size<i32>, context: &mut Context) -> Result<Self> {
+        let vertex_pointers = default_vertex_pointers(context);
+        let mut renderer = Renderer::new(vertex_pointers, ShaderProgram::default_oit()?)?;
+
+        // Add up the colour and weight, and multiply the revealage
+        // (how much of what is behind shows through) by 1 - alpha.
+        renderer.blend_mode = BlendMode::Custom {
+            color: BlendFunc::add(BlendFactor::One, BlendFactor::One),
+            alpha: BlendFunc {
+                src: BlendFactor::Zero,
+                dst: BlendFactor::OneMinusSrcAlpha,
+                equation: BlendEquation::Add,
+            },
+        };
+        renderer.depth_mode = DepthMode::Test(DepthFunc::Less);
+
+        let (vao, quad_vbo) = super::new_vertex_pointers(context)
+            .add(0, 3, GlType::Float, false)
+            .add(1, 2, GlType::Float, false)
+            .build();
+
+        quad_vbo.load_data(&QUAD);
+
+        let inst = Self {
+            renderer,
+            composite: ShaderProgram::oit_composite()?,
+            vao,
+            _quad_vbo: quad_vbo,
+            targets: Targets::new(size),
+            size,
+            pixel_size: 1,
+        };
+
+        Ok(inst)
+    }
+
+    /// Resize the renderer. Call this when the window is resized.
+    pub fn resize(&mut self, size: Size<i32>) {
+        if size != self.size {
+            self.targets = Targets::new(size);
+            self.size = size;
+        }
+    }
+
+    /// Start drawing translucent sprites.
+    /// This copies the depth of the opaque sprites drawn so far, and
+    /// binds the accumulation targets until [`OitRenderer::finish`] is called.
+    pub fn begin(&mut self) {
+        let Size { width, height, .. } = self.size;
+
+        self.targets.framebuffer.bind_target(FramebufferTarget::Draw);
+
+        unsafe {
+            glBindFramebuffer(GL_READ_FRAMEBUFFER, 0);
+            glBlitFramebuffer(
+                0, 0, width, height,
+                0, 0, width, height,
+                GL_DEPTH_BUFFER_BIT,
+                GL_NEAREST,
+            );
+        }
+
+        self.targets.framebuffer.bind_target(FramebufferTarget::Both);
+
+        // Clearing respects the colour mask, but not blending or depth testing
+        let accum = [0.0, 0.0, 0.0, 1.0];
+        let weight = [0.0; 4];
+        unsafe {
+            glClearBufferfv(GL_COLOR, 0, accum.as_ptr());
+            glClearBufferfv(GL_COLOR, 1, weight.as_ptr());
+        }
+    }
+
+    /// Render translucent vertex data.
+    /// This has to be called between [`OitRenderer::begin`] and [`OitRenderer::finish`].
+    pub fn render<U: Copy + NumCast>(
+        &mut self,
+        texture: &Texture<U>,
+        vertex_data: &[VertexData],
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        self.renderer.pixel_size = self.pixel_size;
+        self.renderer.render(texture, vertex_data, viewport, context)
+    }
+
+    /// Stop drawing translucent sprites, and draw them
+    /// on top of the default frame buffer.
+    pub fn finish(&mut self, context: &mut Context) -> Result<()> {
+        self.targets.framebuffer.unbind();
+
+        self.composite.enable();
+        context.bind_vao(&self.vao);
+        context.set_blend_mode(BlendMode::Alpha);
+        context.set_depth_mode(DepthMode::Off);
+
+        unsafe { glViewport(0, 0, self.size.width, self.size.height) };
+
+        self.composite.set_texture("accum", &self.targets.accum, 0)?;
+        self.composite.set_texture("weight", &self.targets.weight, 1)?;
+
+        unsafe { glDrawArrays(GL_TRIANGLE_STRIP, 0, QUAD.len() as i32) };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Color, Viewport};
+
+    // Same as the weight in oit.frag
+    fn weight(z_index: i32) -> f32 {
+        let z = Viewport::depth(z_index) * Viewport::FAR as f32;
+        (10.0 / (1e-5 + (z / 10.0).powi(3) + (z / 200.0).powi(6))).clamp(1e-2, 1e2)
+    }
+
+    // Same as oit.frag, the blend mode of the renderer and oit_composite.frag
+    fn resolve(fragments: &[(Color, i32)]) -> Color {
+        let (mut accum, mut total, mut revealage) = ([0.0; 3], 0.0, 1.0);
+        for (colour, z_index) in fragments {
+            let w = weight(*z_index);
+            accum[0] += colour.r * colour.a * w;
+            accum[1] += colour.g * colour.a * w;
+            accum[2] += colour.b * colour.a * w;
+            total += colour.a * w;
+            revealage *= 1.0 - colour.a;
+        }
+
+        Color {
+            r: accum[0] / total,
+            g: accum[1] / total,
+            b: accum[2] / total,
+            a: 1.0 - revealage,
+        }
+    }
+
+    #[test]
+    fn nearer_sprites_count_for_more() {
+        let red = Color { r: 1.0, g: 0.0, b: 0.0, a: 0.5 };
+        let blue = Color { r: 0.0, g: 0.0, b: 1.0, a: 0.5 };
+
+        let red_in_front = resolve(&[(blue, 60), (red, 20)]);
+        assert!(red_in_front.r > 0.9, "{:?}", red_in_front);
+        assert_eq!(resolve(&[(red, 20), (blue, 60)]), red_in_front);
+        assert_eq!(red_in_front.a, 0.75);
+
+        let blue_in_front = resolve(&[(blue, 20), (red, 60)]);
+        assert!(blue_in_front.b > 0.9, "{:?}", blue_in_front);
+    }
+
+    #[test]
+    fn weight_does_not_overflow() {
+        let max_half_float = 65504.0;
+        assert!(weight(Viewport::NEAR) * 650.0 < max_half_float);
+        assert!(weight(50) > weight(51));
+    }
+
+    #[test]
+    fn shader_uses_the_viewport_depth_range() {
+        let src = include_str!("../oit.frag");
+        assert_eq!(Viewport::NEAR, 0);
+        assert!(src.contains(&format!("const float FAR = {}.0;", Viewport::FAR)));
+    }
+}
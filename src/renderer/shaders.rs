@@ -1,11 +1,14 @@
-use std::ffi::CStr;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
 
 use log::info;
 use gl33::global_loader::*;
 use gl33::*;
-use nalgebra::Matrix4;
+use num_traits::cast::NumCast;
 
-use crate::Result;
+use super::uniform::{Sampler, Uniform};
+use crate::{Result, Texture};
 use crate::errors::NightmareError;
 
 // -----------------------------------------------------------------------------
@@ -14,6 +17,11 @@ use crate::errors::NightmareError;
 const DEFAULT_VERTEX: &[u8] = include_bytes!("../default.vert");
 const DEFAULT_FRAGMENT: &[u8] = include_bytes!("../default.frag");
 const DEFAULT_FONT: &[u8] = include_bytes!("../font.frag");
+const SHAPE_VERTEX: &[u8] = include_bytes!("../shape.vert");
+const SHAPE_FRAGMENT: &[u8] = include_bytes!("../shape.frag");
+const OIT_FRAGMENT: &[u8] = include_bytes!("../oit.frag");
+const OIT_COMPOSITE_VERTEX: &[u8] = include_bytes!("../oit_composite.vert");
+const OIT_COMPOSITE_FRAGMENT: &[u8] = include_bytes!("../oit_composite.frag");
 
 // -----------------------------------------------------------------------------
 //     - Shader types -
@@ -44,7 +52,7 @@ impl Shader<VertexShader> {
     }
 
     pub fn default_vertex() -> Result<Shader<VertexShader>> {
-        Self::new_vertex(&DEFAULT_VERTEX)
+        Self::new_vertex(DEFAULT_VERTEX)
     }
 }
 
@@ -62,40 +70,47 @@ impl Shader<FragmentShader> {
     }
 
     pub fn default_fragment() -> Result<Shader<FragmentShader>> {
-        Self::new_fragment(&DEFAULT_FRAGMENT)
+        Self::new_fragment(DEFAULT_FRAGMENT)
     }
 
     pub fn default_font() -> Result<Shader<FragmentShader>> {
-        Self::new_fragment(&DEFAULT_FONT)
+        Self::new_fragment(DEFAULT_FONT)
     }
 }
 
 // -----------------------------------------------------------------------------
 //     - Shader program -
 // -----------------------------------------------------------------------------
+/// A linked vertex and fragment shader.
+///
+/// Uniform locations are looked up once and cached.
+/// See [`super::uniform`] for the types that can be set as uniforms.
 #[derive(Debug)]
-pub struct ShaderProgram(pub(crate) u32);
+pub struct ShaderProgram {
+    pub(crate) id: u32,
+    locations: RefCell<HashMap<String, i32>>,
+}
 
 impl ShaderProgram {
     pub(crate) fn attach_shader(&self, shader_id: u32) {
-        glAttachShader(self.0, shader_id);
+        glAttachShader(self.id, shader_id);
     }
 
     pub(crate) fn link(&self) -> Result<()> {
-        glLinkProgram(self.0);
+        glLinkProgram(self.id);
 
         let mut shader_compiled = 0;
-        unsafe { glGetProgramiv(self.0, GL_LINK_STATUS, &mut shader_compiled) };
+        unsafe { glGetProgramiv(self.id, GL_LINK_STATUS, &mut shader_compiled) };
 
         // Failed to compile the shaders
         if shader_compiled == GL_FALSE.0 as i32 {
             let mut error_len = 1024;
 
             unsafe {
-                glGetProgramiv(self.0, GL_INFO_LOG_LENGTH, &mut error_len);
+                glGetProgramiv(self.id, GL_INFO_LOG_LENGTH, &mut error_len);
 
                 let mut log: Vec<u8> = Vec::with_capacity(error_len as usize);
-                glGetProgramInfoLog(self.0, error_len, &mut error_len, log.as_mut_ptr().cast());
+                glGetProgramInfoLog(self.id, error_len, &mut error_len, log.as_mut_ptr().cast());
 
                 log.set_len(error_len as usize);
 
@@ -112,37 +127,70 @@ impl ShaderProgram {
         glDeleteShader(shader_id);
     }
 
-    pub(crate) fn enable(&self) {
-        glUseProgram(self.0);
+    /// Make this the shader program in use.
+    pub fn enable(&self) {
+        glUseProgram(self.id);
     }
 
-    fn get_uniform_location(&self, name: &CStr) -> Result<i32> {
-        let uniform_loc = unsafe { glGetUniformLocation(self.0, name.as_ptr().cast()) };
+    /// The location of a uniform.
+    /// Returns an error if the program has no active uniform with that name,
+    /// which includes uniforms the shader compiler optimised away.
+    pub fn uniform_location(&self, name: &str) -> Result<i32> {
+        if let Some(location) = self.locations.borrow().get(name) {
+            return Ok(*location);
+        }
+
+        let invalid = || NightmareError::ShaderProgram(format!("Invalid uniform name or location: {:?}", name));
+
+        let c_name = CString::new(name).map_err(|_| invalid())?;
+        let uniform_loc = unsafe { glGetUniformLocation(self.id, c_name.as_ptr().cast()) };
         if uniform_loc == -1 {
-            return Err(NightmareError::ShaderProgram(format!(
-                "Invalid uniform name or location: {:?}",
-                name
-            )));
+            return Err(invalid());
         }
 
+        self.locations.borrow_mut().insert(name.to_string(), uniform_loc);
         Ok(uniform_loc)
     }
 
-    pub(crate) fn set_uniform_matrix(&self, matrix: Matrix4<f32>, name: &CStr) -> Result<()> {
-        let uniform_loc = self.get_uniform_location(name)?;
-        let transpose = false as u8;
-        unsafe { glUniformMatrix4fv(uniform_loc, 1, transpose, matrix.as_ptr()) };
+    /// Set a uniform.
+    ///
+    /// This makes the shader program the one in use, so uniforms can be set
+    /// at any time, e.g. on [`crate::Renderer::shader_program`] between renders,
+    /// without first calling [`ShaderProgram::enable`].
+    ///
+    /// ```
+    /// # use nightmaregl::renderer::ShaderProgram;
+    /// # use nalgebra::Matrix3;
+    /// # fn run(shader_program: &ShaderProgram) -> nightmaregl::Result<()> {
+    /// shader_program.set_uniform("frame", &3)?;
+    /// shader_program.set_uniform("inverted", &true)?;
+    /// shader_program.set_uniform("weights", &[0.25f32, 0.5, 0.25])?;
+    /// shader_program.set_uniform("colour_matrix", &Matrix3::<f32>::identity())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_uniform<U: Uniform + ?Sized>(&self, name: &str, value: &U) -> Result<()> {
+        let uniform_loc = self.uniform_location(name)?;
+        self.enable();
+        value.set_uniform(uniform_loc);
 
         Ok(())
     }
 
-    pub(crate) fn set_uniform_float(&self, f: f32, name: &CStr) -> Result<()> {
-        let uniform_loc = self.get_uniform_location(name)?;
-        unsafe { glUniform1f(uniform_loc, f) };
+    /// Bind a texture to a texture unit, and set the sampler uniform to that unit.
+    /// Unit 0 is used by the renderers for the texture being rendered.
+    /// As with [`ShaderProgram::set_uniform`] this makes the program the one in use.
+    pub fn set_texture<T: Copy + NumCast>(&self, name: &str, texture: &Texture<T>, unit: u32) -> Result<()> {
+        unsafe {
+            glActiveTexture(GLenum(GL_TEXTURE0.0 + unit));
+            texture.bind();
+            glActiveTexture(GL_TEXTURE0);
+        }
 
-        Ok(())
+        self.set_uniform(name, &Sampler(unit))
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self> {
         let vertex_shader = Shader::default_vertex()?;
         let fragment_shader = Shader::default_fragment()?;
@@ -155,9 +203,35 @@ impl ShaderProgram {
         Self::new(vertex_shader, fragment_shader)
     }
 
+    /// Shader program for [`crate::ShapeRenderer`]
+    pub fn default_shapes() -> Result<Self> {
+        let vertex_shader = Shader::new_vertex(SHAPE_VERTEX)?;
+        let fragment_shader = Shader::new_fragment(SHAPE_FRAGMENT)?;
+        Self::new(vertex_shader, fragment_shader)
+    }
+
+    /// Shader program for the accumulation pass of
+    /// [`crate::renderer::oit::OitRenderer`]
+    pub fn default_oit() -> Result<Self> {
+        let vertex_shader = Shader::default_vertex()?;
+        let fragment_shader = Shader::new_fragment(OIT_FRAGMENT)?;
+        Self::new(vertex_shader, fragment_shader)
+    }
+
+    /// Shader program for the composite pass of
+    /// [`crate::renderer::oit::OitRenderer`]
+    pub fn oit_composite() -> Result<Self> {
+        let vertex_shader = Shader::new_vertex(OIT_COMPOSITE_VERTEX)?;
+        let fragment_shader = Shader::new_fragment(OIT_COMPOSITE_FRAGMENT)?;
+        Self::new(vertex_shader, fragment_shader)
+    }
+
     pub fn new(vertex: Shader<VertexShader>, fragment: Shader<FragmentShader>) -> Result<Self> {
-        let shader_program = ShaderProgram(glCreateProgram());
-        info!("shader program {} created", shader_program.0);
+        let shader_program = ShaderProgram {
+            id: glCreateProgram(),
+            locations: RefCell::new(HashMap::new()),
+        };
+        info!("shader program {} created", shader_program.id);
 
         shader_program.attach_shader(vertex.id);
         shader_program.attach_shader(fragment.id);
@@ -197,3 +271,106 @@ unsafe fn load_shader(shader: u32, src: &[u8]) -> Result<()> {
     Ok(())
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Color, Context, Size};
+    use nalgebra::{Matrix3, Vector2};
+
+    const VERTEX: &str = "#version 330 core
+void main() {
+    gl_Position = vec4(0.0);
+}
+";
+
+    const FRAGMENT: &str = "#version 330 core
+uniform float weights[3];
+uniform vec2 offsets[2];
+uniform vec4 palette[2];
+uniform int frame;
+uniform mat3 colour_matrix;
+out vec4 colour;
+
+void main() {
+    float weight = weights[0] + weights[1] + weights[2];
+    colour = (palette[0] + palette[1]) * weight + vec4(offsets[0] + offsets[1], float(frame), 0.0);
+    colour.rgb = colour_matrix * colour.rgb;
+}
+";
+
+    fn context() -> Context {
+        Context::builder("uniforms").build_headless(Size::new(1, 1)).unwrap()
+    }
+
+    fn new_program() -> ShaderProgram {
+        let vertex = Shader::new_vertex(VERTEX).unwrap();
+        let fragment = Shader::new_fragment(FRAGMENT).unwrap();
+        ShaderProgram::new(vertex, fragment).unwrap()
+    }
+
+    fn floats<const N: usize>(program: &ShaderProgram, name: &str) -> [f32; N] {
+        let mut values = [0.0; N];
+        let location = program.uniform_location(name).unwrap();
+        unsafe { glGetUniformfv(program.id, location, values.as_mut_ptr()) };
+        values
+    }
+
+    #[test]
+    fn locations_are_cached() {
+        let _context = context();
+        let program = new_program();
+
+        let location = program.uniform_location("frame").unwrap();
+        assert_eq!(program.locations.borrow().get("frame"), Some(&location));
+        assert_eq!(program.uniform_location("frame").unwrap(), location);
+
+        // Unknown names are an error, and are not cached
+        assert!(program.set_uniform("missing", &1.0f32).is_err());
+        assert!(!program.locations.borrow().contains_key("missing"));
+    }
+
+    #[test]
+    fn arrays_and_vecs() {
+        let _context = context();
+        let program = new_program();
+
+        program.set_uniform("weights", &[0.25f32, 0.5, 0.75]).unwrap();
+        program.set_uniform("offsets", &vec![Vector2::new(1.0f32, 2.0), Vector2::new(3.0, 4.0)]).unwrap();
+        program.set_uniform("palette", &[Color::white(), Color::transparent()][..]).unwrap();
+        program.set_uniform("frame", &3).unwrap();
+
+        assert_eq!(floats(&program, "weights[1]"), [0.5]);
+        assert_eq!(floats(&program, "weights[2]"), [0.75]);
+        assert_eq!(floats(&program, "offsets[0]"), [1.0, 2.0]);
+        assert_eq!(floats(&program, "offsets[1]"), [3.0, 4.0]);
+        assert_eq!(floats(&program, "palette[0]"), [1.0; 4]);
+        assert_eq!(floats(&program, "palette[1]"), [0.0; 4]);
+
+        let mut frame = 0;
+        unsafe { glGetUniformiv(program.id, program.uniform_location("frame").unwrap(), &mut frame) };
+        assert_eq!(frame, 3);
+    }
+
+    #[test]
+    fn matrices_are_column_major() {
+        let _context = context();
+        let program = new_program();
+
+        let matrix = Matrix3::new(1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+        program.set_uniform("colour_matrix", &matrix).unwrap();
+        assert_eq!(floats(&program, "colour_matrix"), [1.0, 4.0, 7.0, 2.0, 5.0, 8.0, 3.0, 6.0, 9.0]);
+    }
+
+    #[test]
+    fn set_uniform_binds_the_program() {
+        let _context = context();
+        let program = new_program();
+        let other = new_program();
+
+        other.enable();
+        program.set_uniform("weights", &[1.0f32, 2.0, 3.0]).unwrap();
+        assert_eq!(floats(&program, "weights[2]"), [3.0]);
+        assert_eq!(floats(&other, "weights[2]"), [0.0]);
+    }
+}
@@ -0,0 +1,613 @@
+#![deny(missing_docs)]
+//! Shape renderer.
+//! Draw untextured rectangles, circles, lines and polygons.
+//!
+//! Shapes are collected in [`Shapes`] and drawn by the [`ShapeRenderer`]
+//! in a single draw call.
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # fn run(mut context: Context, viewport: Viewport) -> Result<()> {
+//! let renderer = ShapeRenderer::new(&mut context)?;
+//! let mut shapes = Shapes::new();
+//!
+//! let transform = Transform::new(Position::new(100.0, 100.0));
+//! let bar = Rect::new(Point::zero(), Size::new(64.0, 8.0));
+//! shapes.rect(bar, Color::grey(), &transform);
+//! shapes.rect_outline(bar, 1.0, Color::white(), &transform);
+//! shapes.circle(Point::new(32.0, 32.0), 10.0, Color::white(), &transform);
+//!
+//! renderer.render(&shapes, &viewport, &mut context)?;
+//! shapes.clear();
+//! # Ok(())
+//! # }
+//! ```
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::ops::Range;
+
+use gl33::global_loader::*;
+use gl33::*;
+
+use super::shaders::ShaderProgram;
+use super::{GlType, Vbo};
+use crate::context::{Context, Vao};
+use crate::{Affine, BlendMode, Color, DepthMode, Point, Rect, Result, Rotation, Size, Vector, Viewport};
+
+// Miters longer than this (as a multiple of half the thickness) are bevelled
+const MITER_LIMIT: f32 = 4.0;
+
+// -----------------------------------------------------------------------------
+//     - Shape vertex -
+// -----------------------------------------------------------------------------
+/// A vertex of a shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct ShapeVertex {
+    /// Position, with the `z_index` as z.
+    pub position: [f32; 3],
+    /// Colour
+    pub color: [f32; 4],
+}
+
+// -----------------------------------------------------------------------------
+//     - Line join -
+// -----------------------------------------------------------------------------
+/// How two segments of a polyline are joined.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineJoin {
+    /// Extend the edges until they meet.
+    /// Very sharp corners are bevelled instead.
+    Miter,
+    /// Cut the corner off.
+    Bevel,
+    /// Round the corner.
+    Round,
+}
+
+// -----------------------------------------------------------------------------
+//     - Shapes -
+// -----------------------------------------------------------------------------
+/// A collection of shapes, turned into triangles as they are added.
+///
+/// Every shape takes a colour and a transform (or anything that converts into
+/// an [`Affine`]). Positions and sizes are in the local space of the transform,
+/// the same as the size of a sprite.
+#[derive(Debug, Clone)]
+pub struct Shapes {
+    vertices: Vec<ShapeVertex>,
+    /// Used for every shape added after setting it.
+    /// Same as [`crate::Sprite::z_index`]: lower is drawn in front.
+    /// Defaults to 50, the same as a sprite.
+    pub z_index: i32,
+    /// Number of segments used for a full circle.
+    /// Arcs and rounded corners use a part of this.
+    pub segments: u32,
+}
+
+impl Shapes {
+    /// Create an empty collection of shapes.
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            z_index: 50,
+            segments: 32,
+        }
+    }
+
+    /// Remove all shapes.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// The triangles of all the shapes, three vertices per triangle.
+    pub fn vertices(&self) -> &[ShapeVertex] {
+        &self.vertices
+    }
+
+    /// A filled triangle.
+    pub fn triangle(&mut self, points: [Point<f32>; 3], color: Color, transform: impl Into<Affine>) {
+        let affine = transform.into();
+        self.push(&points, &[[0, 1, 2]], color, &affine);
+    }
+
+    /// A filled rectangle.
+    pub fn rect(&mut self, rect: Rect<f32>, color: Color, transform: impl Into<Affine>) {
+        let affine = transform.into();
+        self.push_rect(rect, color, &affine);
+    }
+
+    /// The outline of a rectangle.
+    /// The outline is drawn inside the rectangle.
+    pub fn rect_outline(&mut self, rect: Rect<f32>, thickness: f32, color: Color, transform: impl Into<Affine>) {
+        let affine = transform.into();
+        let t = thickness.min(rect.size.width / 2.0).min(rect.size.height / 2.0);
+        let (x, y) = (rect.min_x(), rect.min_y());
+        let Size { width, height, .. } = rect.size;
+
+        let sides = [
+            Rect::new(Point::new(x, y), Size::new(width, t)),
+            Rect::new(Point::new(x, y + height - t), Size::new(width, t)),
+            Rect::new(Point::new(x, y + t), Size::new(t, height - t * 2.0)),
+            Rect::new(Point::new(x + width - t, y + t), Size::new(t, height - t * 2.0)),
+        ];
+
+        for side in &sides {
+            self.push_rect(*side, color, &affine);
+        }
+    }
+
+    /// A filled rectangle with rounded corners.
+    pub fn rounded_rect(&mut self, rect: Rect<f32>, radius: f32, color: Color, transform: impl Into<Affine>) {
+        let radius = radius.min(rect.size.width / 2.0).min(rect.size.height / 2.0);
+        if radius <= 0.0 {
+            return self.rect(rect, color, transform);
+        }
+
+        let steps = (self.segments / 4).max(1);
+        let corners = [
+            (Point::new(rect.max_x() - radius, rect.min_y() + radius), -FRAC_PI_2),
+            (Point::new(rect.max_x() - radius, rect.max_y() - radius), 0.0),
+            (Point::new(rect.min_x() + radius, rect.max_y() - radius), FRAC_PI_2),
+            (Point::new(rect.min_x() + radius, rect.min_y() + radius), PI),
+        ];
+
+        let outline = corners
+            .iter()
+            .flat_map(|(centre, start)| {
+                (0..=steps).map(move |i| {
+                    let angle = start + FRAC_PI_2 * i as f32 / steps as f32;
+                    *centre + Vector::new(angle.cos(), angle.sin()) * radius
+                })
+            })
+            .collect::<Vec<_>>();
+
+        self.fan(rect.center(), &outline, color, &transform.into());
+    }
+
+    /// A filled circle.
+    pub fn circle(&mut self, centre: Point<f32>, radius: f32, color: Color, transform: impl Into<Affine>) {
+        self.ellipse(centre, Size::new(radius, radius), color, transform);
+    }
+
+    /// A filled ellipse.
+    pub fn ellipse(&mut self, centre: Point<f32>, radii: Size<f32>, color: Color, transform: impl Into<Affine>) {
+        let segments = self.segments.max(3);
+        let outline = (0..segments)
+            .map(|i| {
+                let angle = TAU * i as f32 / segments as f32;
+                centre + Vector::new(angle.cos() * radii.width, angle.sin() * radii.height)
+            })
+            .collect::<Vec<_>>();
+
+        self.fan(centre, &outline, color, &transform.into());
+    }
+
+    /// The outline of a circle between two angles, counter clockwise
+    /// from the positive x axis. The outline is centred on the radius.
+    pub fn arc(
+        &mut self,
+        centre: Point<f32>,
+        radius: f32,
+        angles: Range<Rotation<f32>>,
+        thickness: f32,
+        color: Color,
+        transform: impl Into<Affine>,
+    ) {
+        let sweep = angles.end.radians - angles.start.radians;
+        let full = sweep.abs() >= TAU;
+        let steps = ((sweep.abs() / TAU * self.segments as f32).ceil() as u32).max(1);
+
+        let points = (0..=steps)
+            .map(|i| {
+                let angle = angles.start.radians + sweep * i as f32 / steps as f32;
+                centre + Vector::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect::<Vec<_>>();
+
+        // A full circle is a closed polyline, without the duplicated end point
+        let (points, closed) = match full {
+            true => (&points[..points.len() - 1], true),
+            false => (&points[..], false),
+        };
+
+        self.stroke(points, thickness, LineJoin::Miter, closed, color, &transform.into());
+    }
+
+    /// A line between two points.
+    pub fn line(&mut self, from: Point<f32>, to: Point<f32>, thickness: f32, color: Color, transform: impl Into<Affine>) {
+        self.stroke(&[from, to], thickness, LineJoin::Bevel, false, color, &transform.into());
+    }
+
+    /// Connected lines through the points.
+    /// If `closed` is true the last point is connected to the first.
+    pub fn polyline(
+        &mut self,
+        points: &[Point<f32>],
+        thickness: f32,
+        join: LineJoin,
+        closed: bool,
+        color: Color,
+        transform: impl Into<Affine>,
+    ) {
+        self.stroke(points, thickness, join, closed, color, &transform.into());
+    }
+
+    /// A filled polygon. The polygon can be concave, but the
+    /// edges should not cross each other.
+    pub fn polygon(&mut self, points: &[Point<f32>], color: Color, transform: impl Into<Affine>) {
+        let triangles = triangulate(points);
+        self.push(points, &triangles, color, &transform.into());
+    }
+
+    fn push_rect(&mut self, rect: Rect<f32>, color: Color, affine: &Affine) {
+        let points = [
+            rect.origin,
+            Point::new(rect.max_x(), rect.min_y()),
+            Point::new(rect.max_x(), rect.max_y()),
+            Point::new(rect.min_x(), rect.max_y()),
+        ];
+        self.push(&points, &[[0, 1, 2], [0, 2, 3]], color, affine);
+    }
+
+    // Triangles between the centre and every pair of neighbouring points
+    fn fan(&mut self, centre: Point<f32>, outline: &[Point<f32>], color: Color, affine: &Affine) {
+        let mut points = Vec::with_capacity(outline.len() + 1);
+        points.push(centre);
+        points.extend_from_slice(outline);
+
+        let n = outline.len();
+        let triangles = (0..n).map(|i| [0, i + 1, (i + 1) % n + 1]).collect::<Vec<_>>();
+        self.push(&points, &triangles, color, affine);
+    }
+
+    fn stroke(&mut self, points: &[Point<f32>], thickness: f32, join: LineJoin, closed: bool, color: Color, affine: &Affine) {
+        let mut points = points.to_vec();
+        points.dedup();
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+
+        if points.len() < 2 {
+            return;
+        }
+
+        let half = thickness / 2.0;
+        let n = points.len();
+        let segment_count = if closed && n > 2 { n } else { n - 1 };
+        let normal = |i: usize| {
+            let d = (points[(i + 1) % n] - points[i]).normalize();
+            Vector::new(-d.y, d.x)
+        };
+
+        let mut triangles = Vec::new();
+        let mut out = points.clone();
+
+        let add = |out: &mut Vec<Point<f32>>, p: Point<f32>| {
+            out.push(p);
+            out.len() - 1
+        };
+
+        // Segments
+        for i in 0..segment_count {
+            let offset = normal(i) * half;
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            let q = [a + offset, a - offset, b - offset, b + offset].map(|p| add(&mut out, p));
+            triangles.push([q[0], q[1], q[2]]);
+            triangles.push([q[0], q[2], q[3]]);
+        }
+
+        // Joins
+        let joints = match segment_count == n {
+            true => 0..n,
+            false => 1..n - 1,
+        };
+
+        for i in joints {
+            let (n0, n1) = (normal((i + n - 1) % n), normal(i));
+            let p = points[i];
+            let cross = n0.x * n1.y - n0.y * n1.x;
+            if cross.abs() < 1e-6 && n0.dot(n1) > 0.0 {
+                continue;
+            }
+
+            // The gap is on the outside of the turn
+            let side = if cross > 0.0 { -1.0 } else { 1.0 };
+            let (n0, n1) = (n0 * side, n1 * side);
+            let centre = add(&mut out, p);
+            let o0 = add(&mut out, p + n0 * half);
+            let o1 = add(&mut out, p + n1 * half);
+
+            let bisector = n0 + n1;
+            let cos = match bisector.length() > 1e-6 {
+                true => bisector.normalize().dot(n0),
+                false => 0.0,
+            };
+
+            match join {
+                LineJoin::Miter if cos > 1.0 / MITER_LIMIT => {
+                    let tip = add(&mut out, p + bisector.normalize() * (half / cos));
+                    triangles.push([centre, o0, tip]);
+                    triangles.push([centre, tip, o1]);
+                }
+                LineJoin::Round => {
+                    let start = n0.y.atan2(n0.x);
+                    let sweep = (n0.x * n1.y - n0.y * n1.x).atan2(n0.dot(n1));
+                    let steps = ((sweep.abs() / TAU * self.segments as f32).ceil() as usize).max(1);
+
+                    let mut previous = o0;
+                    for step in 1..steps {
+                        let angle = start + sweep * step as f32 / steps as f32;
+                        let next = add(&mut out, p + Vector::new(angle.cos(), angle.sin()) * half);
+                        triangles.push([centre, previous, next]);
+                        previous = next;
+                    }
+                    triangles.push([centre, previous, o1]);
+                }
+                _ => triangles.push([centre, o0, o1]),
+            }
+        }
+
+        self.push(&out, &triangles, color, affine);
+    }
+
+    fn push(&mut self, points: &[Point<f32>], triangles: &[[usize; 3]], color: Color, affine: &Affine) {
+        let z = self.z_index as f32;
+        let color = [color.r, color.g, color.b, color.a];
+
+        let vertices = triangles.iter().flatten().map(|i| {
+            let p = affine.transform_point(points[*i]);
+            ShapeVertex {
+                position: [p.x, p.y, z],
+                color,
+            }
+        });
+
+        self.vertices.extend(vertices);
+    }
+}
+
+impl Default for Shapes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Triangulation -
+//     Ear clipping: repeatedly cut off a convex corner
+//     that has no other point inside it.
+// -----------------------------------------------------------------------------
+fn triangulate(points: &[Point<f32>]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    let cross = |a: Point<f32>, b: Point<f32>, c: Point<f32>| (b - a).cross(c - a);
+
+    // Work counter clockwise
+    let area = (0..points.len())
+        .map(|i| points[i].to_vector().cross(points[(i + 1) % points.len()].to_vector()))
+        .sum::<f32>();
+
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    if area < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            if cross(pa, pb, pc) <= 0.0 {
+                return false;
+            }
+
+            remaining.iter().all(|&j| {
+                j == a || j == b || j == c || {
+                    let p = points[j];
+                    cross(pa, pb, p) < 0.0 || cross(pb, pc, p) < 0.0 || cross(pc, pa, p) < 0.0
+                }
+            })
+        });
+
+        // Crossing edges or collinear points: clip anyway rather than give up
+        let i = ear.unwrap_or(0);
+        triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+// -----------------------------------------------------------------------------
+//     - Shape renderer -
+// -----------------------------------------------------------------------------
+/// Render [`Shapes`].
+/// Uses the same [`Viewport`] as the sprite renderer.
+pub struct ShapeRenderer {
+    vao: Vao,
+    vbo: Vbo<ShapeVertex>,
+    shader_program: ShaderProgram,
+    /// Multiplier for the size of a pixel.
+    pub pixel_size: i32,
+    /// How the shapes are blended with the frame buffer.
+    pub blend_mode: BlendMode,
+    /// Depth testing of the shapes.
+    pub depth_mode: DepthMode,
+}
+
+impl ShapeRenderer {
+    /// Create a shape renderer.
+    pub fn new(context: &mut Context) -> Result<Self> {
+        let shader_program = ShaderProgram::default_shapes()?;
+        let (vao, vbo) = super::new_vertex_pointers(context)
+            .add(0, 3, GlType::Float, false)
+            .add(1, 4, GlType::Float, false)
+            .build();
+
+        let inst = Self {
+            vao,
+            vbo,
+            shader_program,
+            pixel_size: 1,
+            blend_mode: BlendMode::Alpha,
+            depth_mode: DepthMode::default(),
+        };
+
+        Ok(inst)
+    }
+
+    /// Render all the shapes in a single draw call.
+    pub fn render(&self, shapes: &Shapes, viewport: &Viewport, context: &mut Context) -> Result<()> {
+        if shapes.vertices.is_empty() {
+            return Ok(());
+        }
+
+        self.shader_program.enable();
+        context.bind_vao(&self.vao);
+        context.set_blend_mode(self.blend_mode);
+        context.set_depth_mode(self.depth_mode);
+
+        unsafe {
+            glViewport(
+                viewport.position.x,
+                viewport.position.y,
+                viewport.size.width,
+                viewport.size.height,
+            );
+        }
+
+        self.vbo.load_data(&shapes.vertices);
+
+        let clip = viewport.projection * viewport.view;
+        self.shader_program.set_uniform("vp", &clip)?;
+        self.shader_program.set_uniform("pixel_scale", &(self.pixel_size as f32))?;
+
+        unsafe { glDrawArrays(GL_TRIANGLES, 0, shapes.vertices.len() as i32) };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Position, Transform};
+
+    fn area(shapes: &Shapes) -> f32 {
+        shapes
+            .vertices()
+            .chunks(3)
+            .map(|t| {
+                let p = t.iter().map(|v| Point::new(v.position[0], v.position[1])).collect::<Vec<_>>();
+                (p[1] - p[0]).cross(p[2] - p[0]).abs() / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn same_default_z_index_as_sprites() {
+        let mut shapes = Shapes::new();
+        shapes.rect(Rect::new(Point::zero(), Size::new(1.0, 1.0)), Color::white(), Affine::identity());
+        let sprite = crate::Sprite::<f32>::from_size(Size::new(1.0, 1.0));
+        assert_eq!(shapes.z_index, sprite.z_index);
+        assert_eq!(shapes.vertices()[0].position[2], sprite.z_index as f32);
+    }
+
+    #[test]
+    fn rect_is_transformed() {
+        let mut shapes = Shapes::new();
+        shapes.z_index = 3;
+        let rect = Rect::new(Point::zero(), Size::new(2.0, 1.0));
+        shapes.rect(rect, Color::white(), &Transform::new(Position::new(10.0, 20.0)));
+
+        assert_eq!(shapes.vertices().len(), 6);
+        assert_eq!(shapes.vertices()[2].position, [12.0, 21.0, 3.0]);
+        assert_eq!(area(&shapes), 2.0);
+    }
+
+    #[test]
+    fn outline_covers_the_border() {
+        let mut shapes = Shapes::new();
+        let rect = Rect::new(Point::zero(), Size::new(10.0, 6.0));
+        shapes.rect_outline(rect, 1.0, Color::white(), Affine::identity());
+        assert_eq!(area(&shapes), 60.0 - 8.0 * 4.0);
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // An L shape
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(2.0, 0.0),
+            Point::new(2.0, 1.0),
+            Point::new(1.0, 1.0),
+            Point::new(1.0, 2.0),
+            Point::new(0.0, 2.0),
+        ];
+
+        let mut shapes = Shapes::new();
+        shapes.polygon(&points, Color::white(), Affine::identity());
+        assert_eq!(shapes.vertices().len(), 4 * 3);
+        assert!((area(&shapes) - 3.0).abs() < 1e-5);
+
+        // Clockwise works too
+        let reversed = points.iter().rev().cloned().collect::<Vec<_>>();
+        shapes.clear();
+        shapes.polygon(&reversed, Color::white(), Affine::identity());
+        assert!((area(&shapes) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn circle_area() {
+        let mut shapes = Shapes::new();
+        shapes.segments = 256;
+        shapes.circle(Point::new(5.0, 5.0), 2.0, Color::white(), Affine::identity());
+        assert_eq!(shapes.vertices().len(), 256 * 3);
+        assert!((area(&shapes) - PI * 4.0).abs() < 0.01);
+
+        shapes.clear();
+        shapes.rounded_rect(Rect::new(Point::zero(), Size::new(4.0, 4.0)), 1.0, Color::white(), Affine::identity());
+        assert!((area(&shapes) - (16.0 - 4.0 + PI)).abs() < 0.05);
+    }
+
+    #[test]
+    fn polyline_joins() {
+        let points = [Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0)];
+        let mut shapes = Shapes::new();
+
+        // Two 10x2 segments, plus the corner
+        shapes.polyline(&points, 2.0, LineJoin::Bevel, false, Color::white(), Affine::identity());
+        assert!((area(&shapes) - (40.0 + 0.5)).abs() < 1e-4);
+
+        shapes.clear();
+        shapes.polyline(&points, 2.0, LineJoin::Miter, false, Color::white(), Affine::identity());
+        assert!((area(&shapes) - 41.0).abs() < 1e-4);
+
+        shapes.clear();
+        shapes.polyline(&points, 2.0, LineJoin::Round, false, Color::white(), Affine::identity());
+        let quarter = PI / 4.0;
+        assert!((area(&shapes) - (40.0 + quarter)).abs() < 0.01);
+    }
+
+    #[test]
+    fn full_arc_is_a_ring() {
+        let mut shapes = Shapes::new();
+        shapes.segments = 256;
+        let angles = Rotation::zero()..Rotation::radians(TAU);
+        shapes.arc(Point::zero(), 10.0, angles, 2.0, Color::white(), Affine::identity());
+
+        // Between radius 9 and 11, with the miters only just outside
+        let distances = shapes
+            .vertices()
+            .iter()
+            .map(|v| Vector::new(v.position[0], v.position[1]).length())
+            .collect::<Vec<_>>();
+        assert!(distances.iter().all(|d| *d > 9.0 - 1e-3 && *d < 11.01));
+        assert!(distances.iter().any(|d| *d > 11.0 - 1e-3));
+    }
+}
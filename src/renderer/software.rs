@@ -0,0 +1,521 @@
+#![deny(missing_docs)]
+//! Software renderer.
+//!
+//! Renders [`VertexData`] the same way the [default renderer](super::default::Renderer)
+//! and the default shaders do, but on the CPU, writing into a [`Target`].
+//!
+//! This makes it possible to test rendering on machines without a GPU.
+//!
+//! ```
+//! use nightmaregl::renderer::software::{Renderer, Target};
+//! use nightmaregl::pixels::{Pixel, Pixels};
+//! use nightmaregl::{Color, Position, Size, Sprite, Transform, VertexData, Viewport};
+//!
+//! let texture = Pixels::from_pixel(Pixel::white(), Size::new(4, 4));
+//! let sprite = Sprite::<f32>::from_size(Size::new(4.0, 4.0));
+//! let transform = Transform::new(Position::new(2.0, 2.0));
+//!
+//! let viewport = Viewport::new(Position::zero(), Size::new(8, 8));
+//! let mut target = Target::new(Size::new(8, 8));
+//! target.clear(Color::black());
+//!
+//! let renderer = Renderer::default();
+//! renderer.render(&texture, &[VertexData::new(&sprite, &transform)], &viewport, &mut target);
+//!
+//! assert_eq!(target.pixel(Position::new(2, 2)), Pixel::white());
+//! assert_eq!(target.pixel(Position::new(1, 1)), Pixel::black());
+//! ```
+use nalgebra::{Matrix4, Vector4};
+
+use crate::pixels::{Pixel, Pixels};
+use crate::{BlendMode, Color, DepthMode, Position, Size, VertexData, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Target -
+// -----------------------------------------------------------------------------
+/// A render target for the software renderer.
+///
+/// Just like an OpenGL framebuffer, the first row of pixels
+/// is the bottom row.
+#[derive(Debug)]
+pub struct Target {
+    pixels: Pixels<Pixel>,
+    depth: Vec<f32>,
+}
+
+impl Target {
+    /// Create a new target with transparent pixels.
+    pub fn new(size: Size<usize>) -> Self {
+        Self {
+            pixels: Pixels::from_pixel(Pixel::transparent(), size),
+            depth: vec![1.0; size.width * size.height],
+        }
+    }
+
+    /// Clear the colour and the depth of the target.
+    pub fn clear(&mut self, color: Color) {
+        let pixel = color.into();
+        self.pixels.iter_mut().for_each(|p| *p = pixel);
+        self.depth.iter_mut().for_each(|d| *d = 1.0);
+    }
+
+    /// The size of the target.
+    pub fn size(&self) -> Size<usize> {
+        self.pixels.size()
+    }
+
+    /// Get a pixel, where 0, 0 is the bottom left pixel.
+    pub fn pixel(&self, pos: Position<usize>) -> Pixel {
+        self.pixels[pos.y * self.size().width + pos.x]
+    }
+
+    /// The rendered pixels.
+    pub fn pixels(&self) -> &Pixels<Pixel> {
+        &self.pixels
+    }
+
+    /// Consume the target, returning the rendered pixels.
+    pub fn into_pixels(self) -> Pixels<Pixel> {
+        self.pixels
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Renderer -
+// -----------------------------------------------------------------------------
+/// Software renderer, mirroring the default renderer and the
+/// default vertex and fragment shaders:
+///
+/// * Textures are sampled using nearest filtering and clamped to the edge.
+/// * Fully transparent pixels are discarded.
+/// * Depth testing uses the depth mode, "less than" by default.
+/// * Pixels are blended using the blend mode, alpha blending by default.
+#[derive(Debug, Copy, Clone)]
+pub struct Renderer {
+    /// Multiplier for the size of a pixel.
+    pub pixel_size: i32,
+    /// How pixels are blended with the target.
+    pub blend_mode: BlendMode,
+    /// Depth testing against the depth of the target.
+    pub depth_mode: DepthMode,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            pixel_size: 1,
+            blend_mode: BlendMode::Alpha,
+            depth_mode: DepthMode::default(),
+        }
+    }
+}
+
+impl Renderer {
+    /// Render vertex data into the target.
+    pub fn render(
+        &self,
+        texture: &Pixels<Pixel>,
+        vertex_data: &[VertexData],
+        viewport: &Viewport,
+        target: &mut Target,
+    ) {
+        let mut scaling_matrix = Matrix4::identity();
+        scaling_matrix[(0, 0)] = self.pixel_size as f32;
+        scaling_matrix[(1, 1)] = self.pixel_size as f32;
+
+        let clip = viewport.projection * viewport.view * scaling_matrix;
+
+        for vd in vertex_data {
+            self.draw_instance(texture, vd, clip * vd.model, viewport, target);
+        }
+    }
+
+    fn draw_instance(
+        &self,
+        texture: &Pixels<Pixel>,
+        vd: &VertexData,
+        mvp: Matrix4<f32>,
+        viewport: &Viewport,
+        target: &mut Target,
+    ) {
+        // Map the unit quad into window space.
+        // Since the projection is orthographic the quad is always
+        // a parallelogram: an origin and two edges.
+        let to_window = |x: f32, y: f32| {
+            let ndc = mvp * Vector4::new(x, y, 0.0, 1.0);
+            let vp_pos = viewport.position.to_f32();
+            let vp_size = viewport.size.to_f32();
+            (
+                (ndc.x + 1.0) / 2.0 * vp_size.width + vp_pos.x,
+                (ndc.y + 1.0) / 2.0 * vp_size.height + vp_pos.y,
+                (ndc.z + 1.0) / 2.0,
+            )
+        };
+
+        let origin = to_window(0.0, 0.0);
+        let right = to_window(1.0, 0.0);
+        let up = to_window(0.0, 1.0);
+
+        let edge_x = (right.0 - origin.0, right.1 - origin.1, right.2 - origin.2);
+        let edge_y = (up.0 - origin.0, up.1 - origin.1, up.2 - origin.2);
+
+        let det = edge_x.0 * edge_y.1 - edge_x.1 * edge_y.0;
+        if det.abs() < f32::EPSILON {
+            return;
+        }
+
+        // Bounding box, clipped to both the viewport and the target
+        let xs = [origin.0, right.0, up.0, origin.0 + edge_x.0 + edge_y.0];
+        let ys = [origin.1, right.1, up.1, origin.1 + edge_x.1 + edge_y.1];
+        let min_x = xs.iter().cloned().fold(f32::INFINITY, f32::min).floor();
+        let max_x = xs.iter().cloned().fold(f32::NEG_INFINITY, f32::max).ceil();
+        let min_y = ys.iter().cloned().fold(f32::INFINITY, f32::min).floor();
+        let max_y = ys.iter().cloned().fold(f32::NEG_INFINITY, f32::max).ceil();
+
+        let size = target.size();
+        let vp_min = viewport.position;
+        let vp_max = viewport.position + viewport.size.to_vector();
+        let min_x = (min_x as i32).max(vp_min.x).max(0);
+        let min_y = (min_y as i32).max(vp_min.y).max(0);
+        let max_x = (max_x as i32).min(vp_max.x).min(size.width as i32);
+        let max_y = (max_y as i32).min(vp_max.y).min(size.height as i32);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // Pixel centre in the local space of the quad
+                let px = x as f32 + 0.5 - origin.0;
+                let py = y as f32 + 0.5 - origin.1;
+                let local_x = (px * edge_y.1 - py * edge_y.0) / det;
+                let local_y = (edge_x.0 * py - edge_x.1 * px) / det;
+
+                if !(0.0..1.0).contains(&local_x) || !(0.0..1.0).contains(&local_y) {
+                    continue;
+                }
+
+                let depth = origin.2 + local_x * edge_x.2 + local_y * edge_y.2;
+                if !(0.0..=1.0).contains(&depth) {
+                    continue;
+                }
+
+                let index = y as usize * size.width + x as usize;
+                let (pass, write) = self.depth_mode.test(depth, target.depth[index]);
+                if !pass {
+                    continue;
+                }
+
+                // The top of the quad has a uv of zero on the y axis
+                let uv = (local_x, 1.0 - local_y);
+                let color = match fragment(texture, vd, uv) {
+                    Some(c) => c,
+                    None => continue,
+                };
+
+                let dst = &mut target.pixels[index];
+                *dst = blend(self.blend_mode, color, *dst);
+                if write {
+                    target.depth[index] = depth;
+                }
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Fragment -
+//     Same as default.frag
+// -----------------------------------------------------------------------------
+fn fragment(texture: &Pixels<Pixel>, vd: &VertexData, uv: (f32, f32)) -> Option<Pixel> {
+    let colour = sample(texture, vd.texture_coords(uv));
+    let (tint, flash) = (vd.tint, vd.flash);
+    let alpha = colour.a as f32 / 255.0 * tint.a;
+    if alpha == 0.0 {
+        return None;
+    }
+
+    let channel = |c: u8, tint: f32, flash: f32| {
+        let c = c as f32 / 255.0 * tint + flash * vd.flash.a;
+        (c.min(1.0) * 255.0).round() as u8
+    };
+
+    let colour = Pixel {
+        r: channel(colour.r, tint.r, flash.r),
+        g: channel(colour.g, tint.g, flash.g),
+        b: channel(colour.b, tint.b, flash.b),
+        a: (alpha * 255.0).round() as u8,
+    };
+
+    Some(colour)
+}
+
+// Nearest filtering, clamped to the edge
+fn sample(texture: &Pixels<Pixel>, (u, v): (f32, f32)) -> Pixel {
+    let size = texture.size();
+    if size.width == 0 || size.height == 0 {
+        return Pixel::transparent();
+    }
+
+    let x = ((u * size.width as f32).floor() as i64).clamp(0, size.width as i64 - 1) as usize;
+    let y = ((v * size.height as f32).floor() as i64).clamp(0, size.height as i64 - 1) as usize;
+    texture[y * size.width + x]
+}
+
+fn blend(blend_mode: BlendMode, src: Pixel, dst: Pixel) -> Pixel {
+    let colour = blend_mode.blend(src.into(), dst.into());
+    let channel = |c: f32| (c * 255.0).round() as u8;
+
+    Pixel {
+        r: channel(colour.r),
+        g: channel(colour.g),
+        b: channel(colour.b),
+        a: channel(colour.a),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DepthFunc, FillMode, Rect, Point, Sprite, Transform};
+
+    fn render(texture: &Pixels<Pixel>, vertex_data: &[VertexData], size: Size<usize>) -> Target {
+        let viewport = Viewport::new(Position::zero(), size.cast::<i32>());
+        let mut target = Target::new(size);
+        target.clear(Color::black());
+        Renderer::default().render(texture, vertex_data, &viewport, &mut target);
+        target
+    }
+
+    fn red() -> Pixel {
+        Pixel { r: 255, ..Default::default() }
+    }
+
+    fn green() -> Pixel {
+        Pixel { g: 255, ..Default::default() }
+    }
+
+    #[test]
+    fn render_sprite_at_position() {
+        let texture = Pixels::from_pixel(red(), Size::new(2, 2));
+        let sprite = Sprite::<f32>::from_size(Size::new(2.0, 2.0));
+        let transform = Transform::new(Position::new(1.0, 1.0));
+        let target = render(&texture, &[VertexData::new(&sprite, &transform)], Size::new(4, 4));
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = match (1..3).contains(&x) && (1..3).contains(&y) {
+                    true => red(),
+                    false => Pixel::black(),
+                };
+                assert_eq!(target.pixel(Position::new(x, y)), expected, "{}x{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn top_row_of_texture_is_drawn_at_the_top() {
+        // Texture rows start at the top, target rows start at the bottom
+        let texture = Pixels::new(vec![red(), green()], Size::new(1, 2));
+        let sprite = Sprite::<f32>::from_size(Size::new(1.0, 2.0));
+        let transform = Transform::default();
+        let target = render(&texture, &[VertexData::new(&sprite, &transform)], Size::new(1, 2));
+
+        assert_eq!(target.pixel(Position::new(0, 1)), red());
+        assert_eq!(target.pixel(Position::new(0, 0)), green());
+    }
+
+    #[test]
+    fn lower_z_index_is_drawn_above() {
+        let texture = Pixels::new(vec![red(), green()], Size::new(2, 1));
+
+        let mut front = Sprite::<f32>::from_size(Size::new(2.0, 1.0));
+        front.texture_rect = Rect::new(Point::zero(), Size::new(1.0, 1.0));
+        front.size = Size::new(2.0, 2.0);
+        front.z_index = 1;
+
+        let mut back = front;
+        back.texture_rect.origin = Point::new(1.0, 0.0);
+        back.z_index = 2;
+
+        let transform = Transform::default();
+        let front = VertexData::new(&front, &transform);
+        let back = VertexData::new(&back, &transform);
+
+        let target = render(&texture, &[front, back], Size::new(2, 2));
+        assert!(target.pixels().iter().all(|p| *p == red()));
+
+        let target = render(&texture, &[back, front], Size::new(2, 2));
+        assert!(target.pixels().iter().all(|p| *p == red()));
+    }
+
+    #[test]
+    fn blend_modes() {
+        let texture = Pixels::new(vec![red(), green()], Size::new(2, 1));
+
+        let mut back = Sprite::<f32>::from_size(Size::new(2.0, 1.0));
+        back.texture_rect = Rect::new(Point::new(1.0, 0.0), Size::new(1.0, 1.0));
+        back.size = Size::new(1.0, 1.0);
+        back.z_index = 2;
+
+        let mut front = back;
+        front.texture_rect.origin = Point::zero();
+        front.tint = Color::white().with_alpha(0.5);
+        front.z_index = 1;
+
+        let transform = Transform::default();
+        let vertex_data = [VertexData::new(&back, &transform), VertexData::new(&front, &transform)];
+        let viewport = Viewport::new(Position::zero(), Size::new(1, 1));
+
+        let draw = |blend_mode: BlendMode| {
+            let mut target = Target::new(Size::new(1, 1));
+            target.clear(Color::black());
+            let renderer = Renderer {
+                blend_mode,
+                ..Default::default()
+            };
+            renderer.render(&texture, &vertex_data, &viewport, &mut target);
+            target.pixel(Position::zero())
+        };
+
+        assert_eq!(draw(BlendMode::Alpha), Pixel { r: 128, g: 127, b: 0, a: 191 });
+        assert_eq!(draw(BlendMode::Additive), Pixel { r: 128, g: 255, b: 0, a: 255 });
+        assert_eq!(draw(BlendMode::Multiply), Pixel { r: 0, g: 0, b: 0, a: 255 });
+        assert_eq!(draw(BlendMode::Replace), Pixel { r: 255, g: 0, b: 0, a: 128 });
+    }
+
+    #[test]
+    fn depth_modes() {
+        let texture = Pixels::new(vec![red(), green()], Size::new(2, 1));
+
+        let mut back = Sprite::<f32>::from_size(Size::new(2.0, 1.0));
+        back.texture_rect = Rect::new(Point::new(1.0, 0.0), Size::new(1.0, 1.0));
+        back.size = Size::new(1.0, 1.0);
+        back.z_index = 2;
+
+        let mut front = back;
+        front.texture_rect.origin = Point::zero();
+        front.z_index = 1;
+
+        // The front sprite is drawn first
+        let transform = Transform::default();
+        let vertex_data = [VertexData::new(&front, &transform), VertexData::new(&back, &transform)];
+        let viewport = Viewport::new(Position::zero(), Size::new(1, 1));
+
+        let draw = |depth_mode: DepthMode| {
+            let mut target = Target::new(Size::new(1, 1));
+            target.clear(Color::black());
+            let renderer = Renderer {
+                depth_mode,
+                ..Default::default()
+            };
+            renderer.render(&texture, &vertex_data, &viewport, &mut target);
+            (target.pixel(Position::zero()), target.depth[0] < 1.0)
+        };
+
+        assert_eq!(draw(DepthMode::default()), (red(), true));
+        assert_eq!(draw(DepthMode::Test(DepthFunc::Less)), (green(), false));
+        assert_eq!(draw(DepthMode::TestWrite(DepthFunc::Greater)), (Pixel::black(), false));
+        assert_eq!(draw(DepthMode::Off), (green(), false));
+    }
+
+    #[test]
+    fn transparent_pixels_are_discarded() {
+        let texture = Pixels::from_pixel(Pixel::transparent(), Size::new(2, 2));
+        let sprite = Sprite::<f32>::from_size(Size::new(2.0, 2.0));
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(2, 2));
+
+        assert!(target.pixels().iter().all(|p| *p == Pixel::black()));
+        assert!(target.depth.iter().all(|d| *d == 1.0));
+    }
+
+    #[test]
+    fn repeat_tiles_the_texture() {
+        let texture = Pixels::new(vec![red(), green()], Size::new(2, 1));
+        let mut sprite = Sprite::<f32>::from_size(Size::new(2.0, 1.0));
+        sprite.size = Size::new(6.0, 1.0);
+        sprite.fill = FillMode::Repeat;
+
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(6, 1));
+        let expected = [red(), green(), red(), green(), red(), green()];
+        assert_eq!(target.pixels().as_slice(), &expected);
+    }
+
+    #[test]
+    fn pixel_size_scales_the_sprite() {
+        let texture = Pixels::from_pixel(red(), Size::new(1, 1));
+        let sprite = Sprite::<f32>::from_size(Size::new(1.0, 1.0));
+        let viewport = Viewport::new(Position::zero(), Size::new(4, 4));
+        let mut target = Target::new(Size::new(4, 4));
+        target.clear(Color::black());
+
+        let renderer = Renderer {
+            pixel_size: 3,
+            ..Default::default()
+        };
+        renderer.render(&texture, &[VertexData::new(&sprite, &Transform::default())], &viewport, &mut target);
+
+        let red_count = target.pixels().iter().filter(|p| **p == red()).count();
+        assert_eq!(red_count, 9);
+    }
+
+    #[test]
+    fn tint_and_flash() {
+        let texture = Pixels::from_pixel(Pixel::white(), Size::new(1, 1));
+        let mut sprite = Sprite::<f32>::from_size(Size::new(1.0, 1.0));
+
+        // Half transparent red over black
+        sprite.tint = Color { r: 1.0, g: 0.0, b: 0.0, a: 0.5 };
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(1, 1));
+        assert_eq!(target.pixel(Position::zero()), Pixel { r: 128, g: 0, b: 0, a: 191 });
+
+        // A full flash turns it white
+        sprite.tint = Color::white();
+        sprite.flash = Color::white();
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(1, 1));
+        assert_eq!(target.pixel(Position::zero()), Pixel::white());
+
+        // Fully faded out sprites are discarded
+        sprite.tint = Color::white().with_alpha(0.0);
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(1, 1));
+        assert_eq!(target.pixel(Position::zero()), Pixel::black());
+    }
+
+    #[test]
+    fn flipped_sprite() {
+        let texture = Pixels::new(vec![red(), green()], Size::new(2, 1));
+        let mut sprite = Sprite::<f32>::from_size(Size::new(2.0, 1.0));
+        sprite.flip_x = true;
+
+        let target = render(&texture, &[VertexData::new(&sprite, &Transform::default())], Size::new(2, 1));
+        assert_eq!(target.pixels().as_slice(), &[green(), red()]);
+    }
+
+    #[test]
+    fn nine_slice_keeps_the_corners() {
+        let texture = Pixels::new(vec![
+            red(), green(), red(),
+            green(), Pixel::white(), green(),
+            red(), green(), red(),
+        ], Size::new(3, 3));
+
+        let mut sprite = Sprite::<f32>::from_size(Size::new(3.0, 3.0));
+        sprite.size = Size::new(5.0, 4.0);
+        sprite.fill = FillMode::NineSlice {
+            left: 1.0,
+            right: 1.0,
+            top: 1.0,
+            bottom: 1.0,
+            edges: crate::SliceFill::Stretch,
+            centre: crate::SliceFill::Stretch,
+        };
+
+        let vertex_data = VertexData::instances(&sprite, &Transform::default());
+        let target = render(&texture, &vertex_data, Size::new(5, 4));
+
+        let (r, g, w) = (red(), green(), Pixel::white());
+        assert_eq!(target.pixels().as_slice(), &[
+            r, g, g, g, r,
+            g, w, w, w, g,
+            g, w, w, w, g,
+            r, g, g, g, r,
+        ]);
+    }
+}
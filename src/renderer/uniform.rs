@@ -0,0 +1,192 @@
+#![deny(missing_docs)]
+//! Typed uniforms.
+//!
+//! Anything that implements [`Uniform`] can be set on a [`ShaderProgram`]:
+//!
+//! | Rust                                 | GLSL                    |
+//! |--------------------------------------|-------------------------|
+//! | `i32`, `u32`, `bool`, `f32`          | `int`, `uint`, `bool`, `float` |
+//! | `Vector2<f32>` to `Vector4<f32>`     | `vec2` to `vec4`        |
+//! | `Matrix2<f32>` to `Matrix4<f32>`     | `mat2` to `mat4`        |
+//! | [`Color`]                            | `vec4`                  |
+//! | [`Sampler`]                          | `sampler2D`             |
+//! | a slice or an array of any of these  | an array                |
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # use nightmaregl::renderer::ShaderProgram;
+//! # use nalgebra::Vector2;
+//! # fn run(shader_program: &ShaderProgram) -> Result<()> {
+//! shader_program.set_uniform("time", &1.5f32)?;
+//! shader_program.set_uniform("wind", &Vector2::new(0.5f32, 0.0))?;
+//! shader_program.set_uniform("palette", &[Color::white(), Color::black()])?;
+//! # Ok(())
+//! # }
+//! ```
+use gl33::global_loader::*;
+use nalgebra::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+use super::shaders::ShaderProgram;
+use crate::{Color, Result};
+
+// -----------------------------------------------------------------------------
+//     - Uniform -
+// -----------------------------------------------------------------------------
+/// A value that can be set as a uniform.
+/// Implement [`UniformArray`] rather than this.
+pub trait Uniform {
+    /// Set the uniform at `location` of the shader program in use.
+    fn set_uniform(&self, location: i32);
+}
+
+/// A type that can be a uniform, or an element in an array of uniforms.
+pub trait UniformArray: Sized {
+    /// Set the uniform array starting at `location`
+    /// of the shader program in use.
+    fn set_uniform_array(values: &[Self], location: i32);
+}
+
+impl<T: UniformArray> Uniform for T {
+    fn set_uniform(&self, location: i32) {
+        T::set_uniform_array(std::slice::from_ref(self), location);
+    }
+}
+
+impl<T: UniformArray> Uniform for [T] {
+    fn set_uniform(&self, location: i32) {
+        T::set_uniform_array(self, location);
+    }
+}
+
+impl<T: UniformArray, const N: usize> Uniform for [T; N] {
+    fn set_uniform(&self, location: i32) {
+        T::set_uniform_array(self, location);
+    }
+}
+
+impl<T: UniformArray> Uniform for Vec<T> {
+    fn set_uniform(&self, location: i32) {
+        T::set_uniform_array(self, location);
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Sampler -
+// -----------------------------------------------------------------------------
+/// A texture unit, for a `sampler2D` uniform.
+/// Use [`ShaderProgram::set_texture`] to bind a texture to
+/// the unit and set the sampler at the same time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sampler(pub u32);
+
+// -----------------------------------------------------------------------------
+//     - Uniforms -
+// -----------------------------------------------------------------------------
+/// A set of uniforms for a custom shader, passed to
+/// [`crate::Renderer::render_with_uniforms`].
+///
+/// Any closure taking a [`ShaderProgram`] is a set of uniforms.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # use nightmaregl::renderer::{ShaderProgram, Uniforms};
+/// # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, noise: Texture<f32>, vertex_data: Vec<VertexData>, viewport: Viewport) -> Result<()> {
+/// struct Dissolve<'a> {
+///     noise: &'a Texture<f32>,
+///     amount: f32,
+/// }
+///
+/// impl Uniforms for Dissolve<'_> {
+///     fn apply(&self, shader_program: &ShaderProgram) -> Result<()> {
+///         shader_program.set_texture("noise", self.noise, 1)?;
+///         shader_program.set_uniform("amount", &self.amount)
+///     }
+/// }
+///
+/// let dissolve = Dissolve { noise: &noise, amount: 0.5 };
+/// renderer.render_with_uniforms(&texture, &vertex_data, &dissolve, &viewport, &mut context)?;
+///
+/// // Or with a closure
+/// let time = |shader_program: &ShaderProgram| shader_program.set_uniform("time", &2.0f32);
+/// renderer.render_with_uniforms(&texture, &vertex_data, &time, &viewport, &mut context)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Uniforms {
+    /// Set the uniforms on the shader program.
+    fn apply(&self, shader_program: &ShaderProgram) -> Result<()>;
+}
+
+impl<F: Fn(&ShaderProgram) -> Result<()>> Uniforms for F {
+    fn apply(&self, shader_program: &ShaderProgram) -> Result<()> {
+        self(shader_program)
+    }
+}
+
+impl Uniforms for () {
+    fn apply(&self, _: &ShaderProgram) -> Result<()> {
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Uniform types -
+// -----------------------------------------------------------------------------
+impl UniformArray for i32 {
+    fn set_uniform_array(values: &[Self], location: i32) {
+        unsafe { glUniform1iv(location, values.len() as i32, values.as_ptr()) };
+    }
+}
+
+impl UniformArray for u32 {
+    fn set_uniform_array(values: &[Self], location: i32) {
+        unsafe { glUniform1uiv(location, values.len() as i32, values.as_ptr()) };
+    }
+}
+
+impl UniformArray for bool {
+    fn set_uniform_array(values: &[Self], location: i32) {
+        let values = values.iter().map(|b| *b as i32).collect::<Vec<_>>();
+        i32::set_uniform_array(&values, location);
+    }
+}
+
+impl UniformArray for Sampler {
+    fn set_uniform_array(values: &[Self], location: i32) {
+        let units = values.iter().map(|s| s.0 as i32).collect::<Vec<_>>();
+        i32::set_uniform_array(&units, location);
+    }
+}
+
+impl UniformArray for f32 {
+    fn set_uniform_array(values: &[Self], location: i32) {
+        unsafe { glUniform1fv(location, values.len() as i32, values.as_ptr()) };
+    }
+}
+
+// Vectors, matrices and colours are stored as consecutive floats
+macro_rules! float_uniform {
+    ($type:ty, $func:ident) => {
+        impl UniformArray for $type {
+            fn set_uniform_array(values: &[Self], location: i32) {
+                unsafe { $func(location, values.len() as i32, values.as_ptr().cast()) };
+            }
+        }
+    };
+    ($type:ty, $func:ident, matrix) => {
+        impl UniformArray for $type {
+            fn set_uniform_array(values: &[Self], location: i32) {
+                let transpose = false as u8;
+                unsafe { $func(location, values.len() as i32, transpose, values.as_ptr().cast()) };
+            }
+        }
+    };
+}
+
+float_uniform!(Vector2<f32>, glUniform2fv);
+float_uniform!(Vector3<f32>, glUniform3fv);
+float_uniform!(Vector4<f32>, glUniform4fv);
+float_uniform!(Color, glUniform4fv);
+float_uniform!(Matrix2<f32>, glUniformMatrix2fv, matrix);
+float_uniform!(Matrix3<f32>, glUniformMatrix3fv, matrix);
+float_uniform!(Matrix4<f32>, glUniformMatrix4fv, matrix);
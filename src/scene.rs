@@ -0,0 +1,374 @@
+#![deny(missing_docs)]
+//! # Scene graph
+//!
+//! A tree of nodes, where every node has a transform relative to its parent
+//! and optionally a sprite.
+//!
+//! The world matrix of a node is cached, and only recalculated when the
+//! transform of the node, or any of its ancestors, changed.
+//!
+//! ```
+//! use nightmaregl::scene::Scene;
+//! use nightmaregl::{Position, Size, Sprite, Transform};
+//!
+//! let mut scene = Scene::new();
+//! let ship = scene.add(Transform::new(Position::new(100.0, 100.0)), None);
+//!
+//! let sprite = Sprite::from_size(Size::new(8.0, 8.0));
+//! let turret = scene.add_child(ship, Transform::new(Position::new(10.0, 0.0)), Some(sprite));
+//! assert_eq!(scene.world_position(turret), Position::new(110.0, 100.0));
+//!
+//! // Moving the ship moves the turret
+//! scene.transform_mut(ship).translation.x = 200.0;
+//! assert_eq!(scene.world_position(turret), Position::new(210.0, 100.0));
+//!
+//! // One sprite to render
+//! assert_eq!(scene.vertex_data().len(), 1);
+//! ```
+use std::ops::{Div, MulAssign};
+
+use nalgebra::{Matrix4, Scalar};
+use num_traits::cast::NumCast;
+use num_traits::{One, Zero};
+
+use crate::errors::{NightmareError, Result};
+use crate::extras::Entries;
+use crate::{Affine, Position, Sprite, Transform, VertexData};
+
+// -----------------------------------------------------------------------------
+//     - Node id -
+// -----------------------------------------------------------------------------
+/// Refers to a node in a [`Scene`].
+///
+/// Ids of removed nodes are reused, so don't hold on
+/// to the id of a node after removing it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+// -----------------------------------------------------------------------------
+//     - Node -
+// -----------------------------------------------------------------------------
+struct Node<T> {
+    transform: Transform<T>,
+    sprite: Option<Sprite<T>>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Matrix4<f32>,
+    dirty: bool,
+}
+
+// -----------------------------------------------------------------------------
+//     - Scene -
+// -----------------------------------------------------------------------------
+/// A tree of transforms and sprites.
+///
+/// Accessing a node that was removed panics.
+pub struct Scene<T> {
+    nodes: Entries<Node<T>>,
+    roots: Vec<NodeId>,
+}
+
+impl<T> Scene<T>
+where
+    T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output = T>,
+{
+    /// Create an empty scene.
+    pub fn new() -> Self {
+        Self {
+            nodes: Entries::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Add a node at the top of the scene.
+    pub fn add(&mut self, transform: Transform<T>, sprite: Option<Sprite<T>>) -> NodeId {
+        let id = self.insert(transform, sprite, None);
+        self.roots.push(id);
+        id
+    }
+
+    /// Add a node as the last child of `parent`.
+    /// The transform is relative to the parent.
+    pub fn add_child(&mut self, parent: NodeId, transform: Transform<T>, sprite: Option<Sprite<T>>) -> NodeId {
+        let id = self.insert(transform, sprite, Some(parent));
+        self.node_mut(parent).children.push(id);
+        id
+    }
+
+    /// Remove a node and all its descendants.
+    pub fn remove(&mut self, id: NodeId) {
+        self.detach(id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.nodes.remove(id.0);
+            stack.extend(node.children);
+        }
+    }
+
+    /// Move a node to a new parent (or to the top of the scene with `None`),
+    /// keeping its position, rotation and scale in the world.
+    ///
+    /// Fails if the new parent is the node itself or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<()> {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(a) = ancestor {
+                if a == id {
+                    return Err(NightmareError::InvalidParent);
+                }
+                ancestor = self.node(a).parent;
+            }
+        }
+
+        let world = self.world_matrix(id);
+        let parent_world = match parent {
+            Some(parent) => self.world_matrix(parent),
+            None => Matrix4::identity(),
+        };
+        let local = parent_world.try_inverse().unwrap_or_else(Matrix4::identity) * world;
+
+        self.detach(id);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.transform = Transform::from_affine(&Affine::from(local));
+        self.mark_dirty(id);
+
+        Ok(())
+    }
+
+    /// The parent of a node.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// The children of a node, in drawing order.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Nodes at the top of the scene, in drawing order.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// The transform of a node, relative to its parent.
+    pub fn transform(&self, id: NodeId) -> &Transform<T> {
+        &self.node(id).transform
+    }
+
+    /// The transform of a node, relative to its parent.
+    /// The world matrix of the node and its descendants is
+    /// recalculated when next needed.
+    pub fn transform_mut(&mut self, id: NodeId) -> &mut Transform<T> {
+        self.mark_dirty(id);
+        &mut self.node_mut(id).transform
+    }
+
+    /// The sprite of a node.
+    pub fn sprite(&self, id: NodeId) -> Option<&Sprite<T>> {
+        self.node(id).sprite.as_ref()
+    }
+
+    /// The sprite of a node.
+    pub fn sprite_mut(&mut self, id: NodeId) -> &mut Option<Sprite<T>> {
+        &mut self.node_mut(id).sprite
+    }
+
+    /// The matrix transforming from the local space of the node to the world.
+    pub fn world_matrix(&mut self, id: NodeId) -> Matrix4<f32> {
+        let node = self.node(id);
+        if !node.dirty {
+            return node.world;
+        }
+
+        let parent_world = match node.parent {
+            Some(parent) => self.world_matrix(parent),
+            None => Matrix4::identity(),
+        };
+
+        let node = self.node_mut(id);
+        node.world = parent_world * node.transform.matrix();
+        node.dirty = false;
+        node.world
+    }
+
+    /// The position of the node in the world.
+    pub fn world_position(&mut self, id: NodeId) -> Position<f32> {
+        let world = self.world_matrix(id);
+        Position::new(world[(0, 3)], world[(1, 3)])
+    }
+
+    /// Vertex data for every node with a sprite, parents before
+    /// children, ready to pass to [`crate::Renderer::render`].
+    pub fn vertex_data(&mut self) -> Vec<VertexData> {
+        let mut vertex_data = Vec::new();
+        let mut stack = self.roots.iter().rev().cloned().collect::<Vec<_>>();
+
+        while let Some(id) = stack.pop() {
+            let parent_world = match self.node(id).parent {
+                Some(parent) => self.world_matrix(parent),
+                None => Matrix4::identity(),
+            };
+
+            // Make sure the cache is up to date for the children
+            self.world_matrix(id);
+
+            let node = self.node(id);
+            if let Some(sprite) = &node.sprite {
+                vertex_data.extend(VertexData::instances(sprite, &node.transform).into_iter().map(|mut vd| {
+                    vd.model = parent_world * vd.model;
+                    vd
+                }));
+            }
+
+            stack.extend(node.children.iter().rev());
+        }
+
+        vertex_data
+    }
+
+    fn insert(&mut self, transform: Transform<T>, sprite: Option<Sprite<T>>, parent: Option<NodeId>) -> NodeId {
+        let node = Node {
+            transform,
+            sprite,
+            parent,
+            children: Vec::new(),
+            world: Matrix4::identity(),
+            dirty: true,
+        };
+        NodeId(self.nodes.push(node))
+    }
+
+    fn node(&self, id: NodeId) -> &Node<T> {
+        &self.nodes[id.0]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node<T> {
+        &mut self.nodes[id.0]
+    }
+
+    // Remove the node from the children of its parent (or the roots)
+    fn detach(&mut self, id: NodeId) {
+        let siblings = match self.node(id).parent {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|child| *child != id);
+    }
+
+    fn mark_dirty(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.node_mut(id);
+            // The descendants of a dirty node are already dirty
+            if node.dirty {
+                continue;
+            }
+            node.dirty = true;
+            stack.extend(node.children.iter().cloned());
+        }
+    }
+}
+
+impl<T> Default for Scene<T>
+where
+    T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output = T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rotation, Size, Vector};
+
+    fn close(a: Position<f32>, b: Position<f32>) -> bool {
+        (a - b).length() < 1e-4
+    }
+
+    #[test]
+    fn world_transform_propagates() {
+        let mut scene = Scene::<f32>::new();
+        let mut parent = Transform::new(Position::new(10.0, 0.0));
+        parent.rotation = Rotation::radians(std::f32::consts::FRAC_PI_2);
+        parent.scale = Vector::new(2.0, 2.0);
+
+        let parent = scene.add(parent, None);
+        let child = scene.add_child(parent, Transform::new(Position::new(5.0, 0.0)), None);
+        let grandchild = scene.add_child(child, Transform::new(Position::new(1.0, 0.0)), None);
+
+        // Rotated a quarter turn and doubled: (5, 0) -> (0, 10)
+        assert!(close(scene.world_position(child), Position::new(10.0, 10.0)));
+        assert!(close(scene.world_position(grandchild), Position::new(10.0, 12.0)));
+    }
+
+    #[test]
+    fn cached_until_changed() {
+        let mut scene = Scene::<f32>::new();
+        let parent = scene.add(Transform::default(), None);
+        let child = scene.add_child(parent, Transform::new(Position::new(1.0, 0.0)), None);
+
+        scene.world_matrix(child);
+        assert!(!scene.node(child).dirty);
+
+        scene.transform_mut(parent).translation = Position::new(5.0, 5.0);
+        assert!(scene.node(child).dirty);
+        assert!(close(scene.world_position(child), Position::new(6.0, 5.0)));
+    }
+
+    #[test]
+    fn reparent_keeps_world_position() {
+        let mut scene = Scene::<f32>::new();
+        let mut a = Transform::new(Position::new(10.0, 20.0));
+        a.rotation = Rotation::radians(0.5);
+        let a = scene.add(a, None);
+        let b = scene.add(Transform::new(Position::new(-5.0, 3.0)), None);
+        let child = scene.add_child(a, Transform::new(Position::new(4.0, 0.0)), None);
+
+        let before = scene.world_position(child);
+        scene.set_parent(child, Some(b)).unwrap();
+
+        assert_eq!(scene.parent(child), Some(b));
+        assert!(scene.children(a).is_empty());
+        assert!(close(scene.world_position(child), before));
+        assert!((scene.transform(child).rotation.radians - 0.5).abs() < 1e-4);
+
+        assert!(matches!(scene.set_parent(b, Some(b)), Err(NightmareError::InvalidParent)));
+    }
+
+    #[test]
+    fn vertex_data_parents_first() {
+        let mut scene = Scene::<f32>::new();
+        let sprite = |z| {
+            let mut sprite = Sprite::from_size(Size::new(1.0, 1.0));
+            sprite.z_index = z;
+            sprite
+        };
+
+        let root = scene.add(Transform::default(), Some(sprite(1)));
+        let child = scene.add_child(root, Transform::default(), Some(sprite(2)));
+        scene.add_child(child, Transform::default(), Some(sprite(3)));
+        scene.add_child(root, Transform::default(), None);
+        scene.add(Transform::default(), Some(sprite(4)));
+
+        let z = scene
+            .vertex_data()
+            .iter()
+            .map(|vd| vd.model[(2, 3)].round() as i32)
+            .collect::<Vec<_>>();
+
+        assert_eq!(z.len(), 4);
+        assert!(z[0] < z[1] && z[1] < z[2]);
+
+        scene.remove(child);
+        assert_eq!(scene.vertex_data().len(), 2);
+    }
+}
@@ -0,0 +1,9 @@
+# version 330 core
+
+out vec4 colour;
+
+in vec4 vertex_colour;
+
+void main() {
+    colour = vertex_colour;
+}
@@ -0,0 +1,18 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec4 _colour;
+
+uniform mat4 vp;
+uniform float pixel_scale;
+
+out vec4 vertex_colour;
+
+void main() {
+    mat4 scaling_matrix = mat4(1.0);
+    scaling_matrix[0][0] = pixel_scale;
+    scaling_matrix[1][1] = pixel_scale;
+
+    gl_Position = vp * scaling_matrix * vec4(position, 1.0);
+    vertex_colour = _colour;
+}
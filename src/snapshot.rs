@@ -0,0 +1,318 @@
+#![deny(missing_docs)]
+//! # Snapshot testing
+//!
+//! Compare rendered frames against reference images stored as png files.
+//!
+//! When a frame doesn't match its reference, three images are written
+//! next to the reference: `<name>.actual.png`, `<name>.expected.png` and
+//! `<name>.diff.png`, where the differing pixels are red.
+//!
+//! Set the `NIGHTMAREGL_BLESS` environment variable to overwrite
+//! the references with the rendered frames.
+//!
+//! ```
+//! # use nightmaregl::{Context, Renderer, VertexData, Texture, Color};
+//! # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, vertex_data: Vec<VertexData>) -> nightmaregl::Result<()> {
+//! use nightmaregl::Size;
+//! use nightmaregl::snapshot::{assert_matches, render_to_pixels, Tolerance};
+//!
+//! let frame = render_to_pixels(Size::new(64, 64), &mut context, |viewport, context| {
+//!     context.clear(Color::black());
+//!     renderer.render(&texture, &vertex_data, viewport, context)
+//! })?;
+//!
+//! let tolerance = Tolerance { channel: 2, max_pixels: 0 };
+//! assert_matches(&frame, "tests/references/scene.png", tolerance)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::path::{Path, PathBuf};
+
+use crate::errors::{NightmareError, Result};
+use crate::framebuffer::Framebuffer;
+use crate::pixels::{Pixel, Pixels};
+use crate::texture::{Format, Texture};
+use crate::{Context, Position, Size, Viewport};
+
+/// Set this environment variable to overwrite the reference images.
+pub const BLESS_VAR: &str = "NIGHTMAREGL_BLESS";
+
+// -----------------------------------------------------------------------------
+//     - Tolerance -
+// -----------------------------------------------------------------------------
+/// How much a frame can differ from its reference and still match.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Tolerance {
+    /// The maximum difference of any channel (r, g, b or a)
+    /// before a pixel is considered different.
+    pub channel: u8,
+
+    /// The maximum number of different pixels.
+    pub max_pixels: usize,
+}
+
+// -----------------------------------------------------------------------------
+//     - Diff -
+// -----------------------------------------------------------------------------
+/// The difference between two images of the same size.
+#[derive(Debug)]
+pub struct Diff {
+    /// The number of pixels that differ more than the
+    /// channel tolerance.
+    pub differing: usize,
+
+    /// The largest difference between any two channels.
+    pub max_channel_difference: u8,
+
+    /// The differing pixels are red, the rest are a faded
+    /// version of the expected image.
+    pub image: Pixels<Pixel>,
+}
+
+impl Diff {
+    /// Compare two images of the same size.
+    /// Returns `None` if the sizes differ.
+    pub fn new(actual: &Pixels<Pixel>, expected: &Pixels<Pixel>, channel_tolerance: u8) -> Option<Self> {
+        if actual.size() != expected.size() {
+            return None;
+        }
+
+        let mut differing = 0;
+        let mut max_channel_difference = 0;
+
+        let pixels = actual
+            .iter()
+            .zip(expected.iter())
+            .map(|(a, e)| {
+                let diff = channel_difference(*a, *e);
+                max_channel_difference = max_channel_difference.max(diff);
+
+                match diff > channel_tolerance {
+                    true => {
+                        differing += 1;
+                        Pixel { r: 255, g: 0, b: 0, a: 255 }
+                    }
+                    false => {
+                        let grey = ((e.r as u16 + e.g as u16 + e.b as u16) / 3 / 4) as u8;
+                        Pixel { r: grey, g: grey, b: grey, a: 255 }
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let inst = Self {
+            differing,
+            max_channel_difference,
+            image: Pixels::new(pixels, actual.size()),
+        };
+
+        Some(inst)
+    }
+}
+
+fn channel_difference(a: Pixel, b: Pixel) -> u8 {
+    let diff = |a: u8, b: u8| (a as i16 - b as i16).unsigned_abs() as u8;
+    diff(a.r, b.r)
+        .max(diff(a.g, b.g))
+        .max(diff(a.b, b.b))
+        .max(diff(a.a, b.a))
+}
+
+// -----------------------------------------------------------------------------
+//     - Assert -
+// -----------------------------------------------------------------------------
+/// Compare the frame with the reference image at the given path.
+///
+/// If `NIGHTMAREGL_BLESS` is set, the reference is overwritten instead.
+pub fn assert_matches(actual: &Pixels<Pixel>, reference: impl AsRef<Path>, tolerance: Tolerance) -> Result<()> {
+    let bless = std::env::var_os(BLESS_VAR).map(|v| v != "0").unwrap_or(false);
+    check(actual, reference.as_ref(), tolerance, bless)
+}
+
+fn check(actual: &Pixels<Pixel>, reference: &Path, tolerance: Tolerance, bless: bool) -> Result<()> {
+    if bless {
+        if let Some(dir) = reference.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        return actual.write_to_disk(reference);
+    }
+
+    if !reference.exists() {
+        actual.write_to_disk(sibling(reference, "actual"))?;
+        return Err(NightmareError::MissingSnapshot(reference.to_path_buf()));
+    }
+
+    let expected = Pixels::from_disk(reference)?;
+
+    let differing = match Diff::new(actual, &expected, tolerance.channel) {
+        Some(diff) if diff.differing <= tolerance.max_pixels => return Ok(()),
+        Some(diff) => {
+            diff.image.write_to_disk(sibling(reference, "diff"))?;
+            diff.differing
+        }
+        None => actual.len().max(expected.len()),
+    };
+
+    actual.write_to_disk(sibling(reference, "actual"))?;
+    expected.write_to_disk(sibling(reference, "expected"))?;
+
+    Err(NightmareError::SnapshotMismatch {
+        reference: reference.to_path_buf(),
+        differing,
+    })
+}
+
+// path/to/name.png -> path/to/name.<suffix>.png
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    reference.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+// -----------------------------------------------------------------------------
+//     - Render to pixels -
+// -----------------------------------------------------------------------------
+/// Render into a texture attached to a framebuffer and read the pixels back.
+///
+/// The viewport passed to the closure has its y axis swapped,
+/// so the first row of pixels is the top of the frame, same as a png.
+/// The framebuffer has a cleared depth buffer, so the z index is respected.
+pub fn render_to_pixels<F>(size: Size<i32>, context: &mut Context, f: F) -> Result<Pixels<Pixel>>
+where
+    F: FnOnce(&Viewport, &mut Context) -> Result<()>,
+{
+    let texture = Texture::<i32>::new()
+        .with_format(Format::Rgba)
+        .with_no_data(size);
+
+    let mut framebuffer = Framebuffer::default();
+    framebuffer.attach_texture(&texture);
+    framebuffer.attach_depth_buffer(size);
+    framebuffer.bind();
+    context.clear_depth();
+
+    let mut viewport = Viewport::new(Position::zero(), size);
+    viewport.swap_y();
+
+    let res = f(&viewport, context);
+    framebuffer.unbind();
+    res?;
+
+    Ok(texture.get_pixels())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Color, Renderer, Sprite, Transform, VertexData};
+
+    fn red() -> Pixel {
+        Pixel { r: 255, ..Default::default() }
+    }
+
+    fn reference(name: &str) -> PathBuf {
+        // One directory per test and process, so runs never share files
+        let dir = std::env::temp_dir()
+            .join(format!("nightmaregl-snapshot-tests-{}", std::process::id()))
+            .join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(format!("{}.png", name))
+    }
+
+    #[test]
+    fn diff_within_tolerance() {
+        let expected = Pixels::from_pixel(red(), Size::new(2, 2));
+        let mut actual = Pixels::from_pixel(red(), Size::new(2, 2));
+        actual[0].r = 250;
+        actual[1].g = 10;
+
+        let diff = Diff::new(&actual, &expected, 5).unwrap();
+        assert_eq!(diff.differing, 1);
+        assert_eq!(diff.max_channel_difference, 10);
+        assert_eq!(diff.image[1], red());
+    }
+
+    #[test]
+    fn diff_of_different_sizes() {
+        let expected = Pixels::from_pixel(red(), Size::new(2, 2));
+        let actual = Pixels::from_pixel(red(), Size::new(1, 2));
+        assert!(Diff::new(&actual, &expected, 0).is_none());
+    }
+
+    #[test]
+    fn bless_then_match() {
+        let path = reference("bless");
+        let pixels = Pixels::from_pixel(red(), Size::new(3, 2));
+
+        check(&pixels, &path, Tolerance::default(), true).unwrap();
+        check(&pixels, &path, Tolerance::default(), false).unwrap();
+
+        assert_eq!(Pixels::from_disk(&path).unwrap().as_bytes(), pixels.as_bytes());
+    }
+
+    #[test]
+    fn mismatch_writes_images() {
+        let path = reference("mismatch");
+        let expected = Pixels::from_pixel(red(), Size::new(2, 2));
+        check(&expected, &path, Tolerance::default(), true).unwrap();
+
+        let mut actual = Pixels::from_pixel(red(), Size::new(2, 2));
+        actual[3] = Pixel::white();
+
+        let tolerance = Tolerance { channel: 0, max_pixels: 1 };
+        check(&actual, &path, tolerance, false).unwrap();
+
+        let err = check(&actual, &path, Tolerance::default(), false).unwrap_err();
+        assert!(matches!(err, NightmareError::SnapshotMismatch { differing: 1, .. }));
+
+        let diff = Pixels::from_disk(sibling(&path, "diff")).unwrap();
+        assert_eq!(diff[3], red());
+        assert!(sibling(&path, "actual").exists());
+        assert!(sibling(&path, "expected").exists());
+    }
+
+    #[test]
+    fn missing_reference() {
+        let path = reference("missing");
+        let pixels = Pixels::from_pixel(red(), Size::new(1, 1));
+        let err = check(&pixels, &path, Tolerance::default(), false).unwrap_err();
+        assert!(matches!(err, NightmareError::MissingSnapshot(_)));
+    }
+
+    #[test]
+    fn render_quad_to_pixels() {
+        let mut context = Context::builder("snapshot").build_headless(Size::new(4, 4)).unwrap();
+        let renderer = Renderer::default(&mut context).unwrap();
+
+        // A red top row and a green bottom row
+        let green = Pixel { g: 255, ..Default::default() };
+        let data = [red(), green];
+        let texture = Texture::<f32>::new()
+            .with_format(Format::Rgba)
+            .with_data(bytemuck::cast_slice(&data), Size::new(1.0f32, 2.0));
+
+        let mut sprite = Sprite::new(&texture);
+        sprite.size = Size::new(2.0, 2.0);
+        let vertex_data = [VertexData::new(&sprite, &Transform::new(Position::new(1.0, 0.0)))];
+
+        let frame = render_to_pixels(Size::new(4, 4), &mut context, |viewport, context| {
+            context.clear(Color::black());
+            renderer.render(&texture, &vertex_data, viewport, context)
+        })
+        .unwrap();
+
+        // The sprite is in the bottom of the frame, which is the end of the png
+        let mut expected = Pixels::from_pixel(Pixel::black(), Size::new(4, 4));
+        for x in 1..3 {
+            expected.insert_pixel(Position::new(x, 2), red());
+            expected.insert_pixel(Position::new(x, 3), green);
+        }
+
+        let path = reference("render");
+        check(&expected, &path, Tolerance::default(), true).unwrap();
+        assert_matches(&frame, &path, Tolerance::default()).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+#![deny(missing_docs)]
+//! # Split screen
+//!
+//! Divide the window into several viewports, each with its own
+//! camera and clear colour.
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, vertex_data: Vec<VertexData>) -> Result<()> {
+//! use nightmaregl::split_screen::{Corner, SplitScreen};
+//!
+//! // Two players side by side, and a minimap in the top right corner
+//! let mut split = SplitScreen::columns(2, context.window_size());
+//! let minimap = split.add_inset(Corner::TopRight, Size::new(0.2, 0.2), 10);
+//! split.pane_mut(minimap).camera.zoom = 0.25;
+//!
+//! // On `Event::Resize`, or call `split.handle_event(&event)` for every event
+//! split.resize(context.window_size());
+//!
+//! // On `Event::Draw`
+//! split.clear(&context);
+//! for pane in split.panes() {
+//!     renderer.render(&texture, &vertex_data, &pane.viewport, &mut context)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+#[cfg(feature = "eventloop")]
+use crate::events::Event;
+use crate::{Camera2D, Color, Context, Point, Position, Rect, Size, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Area -
+// -----------------------------------------------------------------------------
+/// A corner of the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Corner {
+    /// Top left
+    TopLeft,
+    /// Top right
+    TopRight,
+    /// Bottom left
+    BottomLeft,
+    /// Bottom right
+    BottomRight,
+}
+
+/// The part of the window covered by a pane.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Area {
+    /// A fraction of the window, where 0, 0 is the bottom left
+    /// corner and 1, 1 is the top right corner.
+    Fraction(Rect<f32>),
+    /// A rectangle in a corner of the window, e.g. a minimap.
+    Inset {
+        /// The corner
+        corner: Corner,
+        /// Size as a fraction of the window
+        size: Size<f32>,
+        /// Distance from the edges of the window in pixels
+        margin: i32,
+    },
+}
+
+impl Area {
+    /// The position and size of the area in the window.
+    pub fn layout(&self, window_size: Size<i32>) -> (Position<i32>, Size<i32>) {
+        let window = window_size.to_f32();
+
+        match *self {
+            Area::Fraction(rect) => {
+                // Round the edges, not the size, so neighbouring panes
+                // don't leave gaps or overlap.
+                let x0 = (rect.min_x() * window.width).round() as i32;
+                let x1 = (rect.max_x() * window.width).round() as i32;
+                let y0 = (rect.min_y() * window.height).round() as i32;
+                let y1 = (rect.max_y() * window.height).round() as i32;
+                (Position::new(x0, y0), Size::new(x1 - x0, y1 - y0))
+            }
+            Area::Inset { corner, size, margin } => {
+                let size = Size::new(size.width * window.width, size.height * window.height)
+                    .round()
+                    .to_i32();
+                let left = margin;
+                let right = window_size.width - margin - size.width;
+                let bottom = margin;
+                let top = window_size.height - margin - size.height;
+
+                let position = match corner {
+                    Corner::TopLeft => Position::new(left, top),
+                    Corner::TopRight => Position::new(right, top),
+                    Corner::BottomLeft => Position::new(left, bottom),
+                    Corner::BottomRight => Position::new(right, bottom),
+                };
+
+                (position, size)
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Pane -
+// -----------------------------------------------------------------------------
+/// A part of the window with its own viewport and camera.
+#[derive(Debug, Clone)]
+pub struct Pane {
+    /// The viewport to render the pane with.
+    pub viewport: Viewport,
+    /// The camera of the pane.
+    /// Call [`SplitScreen::apply_cameras`] after changing it.
+    pub camera: Camera2D,
+    /// The colour the pane is cleared with.
+    pub clear_color: Color,
+    /// The part of the window covered by the pane.
+    pub area: Area,
+}
+
+// -----------------------------------------------------------------------------
+//     - Split screen -
+// -----------------------------------------------------------------------------
+/// Manage several viewports in one window.
+///
+/// Panes are drawn in the order they were added, so insets
+/// should be added last.
+#[derive(Debug, Clone)]
+pub struct SplitScreen {
+    panes: Vec<Pane>,
+    window_size: Size<i32>,
+}
+
+impl SplitScreen {
+    /// An empty layout.
+    pub fn new(window_size: Size<i32>) -> Self {
+        Self {
+            panes: Vec::new(),
+            window_size,
+        }
+    }
+
+    /// Panes side by side, from left to right.
+    pub fn columns(count: usize, window_size: Size<i32>) -> Self {
+        Self::grid(count, 1, window_size)
+    }
+
+    /// Panes on top of each other, from top to bottom.
+    pub fn rows(count: usize, window_size: Size<i32>) -> Self {
+        Self::grid(1, count, window_size)
+    }
+
+    /// A grid of panes, from left to right, then top to bottom.
+    pub fn grid(columns: usize, rows: usize, window_size: Size<i32>) -> Self {
+        let mut inst = Self::new(window_size);
+        let size = Size::new(1.0 / columns.max(1) as f32, 1.0 / rows.max(1) as f32);
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let origin = Point::new(
+                    column as f32 * size.width,
+                    1.0 - (row + 1) as f32 * size.height,
+                );
+                inst.add(Area::Fraction(Rect::new(origin, size)));
+            }
+        }
+
+        inst
+    }
+
+    /// Add a pane, returning its index.
+    /// The camera looks at the middle of the pane.
+    pub fn add(&mut self, area: Area) -> usize {
+        let (position, size) = area.layout(self.window_size);
+        let viewport = Viewport::new(position, size);
+        let camera = Camera2D::new(viewport.centre().to_f32());
+
+        let mut pane = Pane {
+            viewport,
+            camera,
+            clear_color: Color::black(),
+            area,
+        };
+        pane.camera.apply(&mut pane.viewport);
+
+        self.panes.push(pane);
+        self.panes.len() - 1
+    }
+
+    /// Add a pane in a corner, e.g. a minimap.
+    /// `size` is a fraction of the window.
+    pub fn add_inset(&mut self, corner: Corner, size: Size<f32>, margin: i32) -> usize {
+        self.add(Area::Inset { corner, size, margin })
+    }
+
+    /// Remove a pane.
+    /// The index of every pane after it goes down by one.
+    pub fn remove(&mut self, index: usize) -> Pane {
+        self.panes.remove(index)
+    }
+
+    /// All the panes, in drawing order.
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    /// A pane.
+    pub fn pane(&self, index: usize) -> &Pane {
+        &self.panes[index]
+    }
+
+    /// A pane.
+    pub fn pane_mut(&mut self, index: usize) -> &mut Pane {
+        &mut self.panes[index]
+    }
+
+    /// Lay out the panes again for a new window size.
+    pub fn resize(&mut self, window_size: Size<i32>) {
+        self.window_size = window_size;
+
+        for pane in &mut self.panes {
+            let (position, size) = pane.area.layout(window_size);
+            pane.viewport.position = position;
+            pane.viewport.resize(size);
+        }
+
+        self.apply_cameras();
+    }
+
+    /// Lay out the panes again if the event is `Event::Resize`.
+    #[cfg(feature = "eventloop")]
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        if let Event::Resize(size) = event {
+            self.resize(size.cast());
+        }
+    }
+
+    /// Update the view of every viewport from its camera.
+    pub fn apply_cameras(&mut self) {
+        for pane in &mut self.panes {
+            pane.camera.apply(&mut pane.viewport);
+        }
+    }
+
+    /// Clear every pane with its own colour.
+    /// Nothing outside the panes is cleared.
+    pub fn clear(&self, context: &Context) {
+        for pane in &self.panes {
+            context.clear_viewport(&pane.viewport, pane.clear_color);
+        }
+    }
+
+    /// The index of the top most pane at a position in the window,
+    /// where 0, 0 is the top left corner (same as `Event::MouseMoved`).
+    pub fn pane_at(&self, point: Point<f32>) -> Option<usize> {
+        let point = Point::new(point.x, self.window_size.height as f32 - point.y);
+
+        self.panes.iter().rposition(|pane| {
+            let rect = Rect::new(pane.viewport.position.to_point(), *pane.viewport.size()).to_f32();
+            rect.contains(point)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn areas(split: &SplitScreen) -> Vec<(Position<i32>, Size<i32>)> {
+        split
+            .panes()
+            .iter()
+            .map(|p| (p.viewport.position, *p.viewport.size()))
+            .collect()
+    }
+
+    #[test]
+    fn columns_cover_the_window() {
+        let split = SplitScreen::columns(3, Size::new(100, 50));
+        assert_eq!(areas(&split), vec![
+            (Position::new(0, 0), Size::new(33, 50)),
+            (Position::new(33, 0), Size::new(34, 50)),
+            (Position::new(67, 0), Size::new(33, 50)),
+        ]);
+    }
+
+    #[test]
+    fn grid_starts_top_left() {
+        let split = SplitScreen::grid(2, 2, Size::new(100, 100));
+        assert_eq!(split.pane(0).viewport.position, Position::new(0, 50));
+        assert_eq!(split.pane(1).viewport.position, Position::new(50, 50));
+        assert_eq!(split.pane(2).viewport.position, Position::new(0, 0));
+    }
+
+    #[test]
+    fn inset_and_resize() {
+        let mut split = SplitScreen::rows(2, Size::new(200, 100));
+        let minimap = split.add_inset(Corner::TopRight, Size::new(0.25, 0.5), 5);
+        assert_eq!(areas(&split)[minimap], (Position::new(145, 45), Size::new(50, 50)));
+
+        #[cfg(feature = "eventloop")]
+        split.handle_event::<()>(&Event::Resize(Size::new(400, 200)));
+        #[cfg(not(feature = "eventloop"))]
+        split.resize(Size::new(400, 200));
+        assert_eq!(areas(&split)[minimap], (Position::new(295, 95), Size::new(100, 100)));
+        assert_eq!(areas(&split)[1], (Position::new(0, 0), Size::new(400, 100)));
+
+        // The minimap is on top of the first row
+        assert_eq!(split.pane_at(Point::new(300.0, 10.0)), Some(minimap));
+        assert_eq!(split.pane_at(Point::new(10.0, 10.0)), Some(0));
+        assert_eq!(split.pane_at(Point::new(10.0, 190.0)), Some(1));
+    }
+}
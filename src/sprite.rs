@@ -6,10 +6,32 @@ use num_traits::cast::NumCast;
 use num_traits::Zero;
 
 use crate::texture::Texture;
-use crate::{Point, Position, Rect, Size};
+use crate::{Color, Point, Position, Rect, Size};
+
+/// Rotate the texture of a sprite in steps of 90 degrees,
+/// without rotating the sprite itself.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum UvRotation {
+    /// No rotation
+    #[default]
+    None,
+    /// A quarter turn clockwise
+    Clockwise90,
+    /// Half a turn
+    Half,
+    /// A quarter turn counter clockwise
+    CounterClockwise90,
+}
+
+impl UvRotation {
+    /// True if the width and height of the texture are swapped
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, UvRotation::Clockwise90 | UvRotation::CounterClockwise90)
+    }
+}
 
 /// Tiling mode. Either stretch or tiling
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FillMode {
     /// Stretch the texture to cover the entire
     /// sprite size.
@@ -18,6 +40,35 @@ pub enum FillMode {
     /// Repeat a portion of the texture over
     /// the entire sprite.
     Repeat,
+
+    /// Split the texture rect into a 3x3 grid. The corners keep their
+    /// size, the edges and the centre stretch or repeat to fill the sprite.
+    ///
+    /// The borders are in pixels of the texture. A nine slice sprite is made
+    /// of up to nine instances, see [`crate::VertexData::instances`].
+    NineSlice {
+        /// Width of the left column
+        left: f32,
+        /// Width of the right column
+        right: f32,
+        /// Height of the top row
+        top: f32,
+        /// Height of the bottom row
+        bottom: f32,
+        /// How the edges fill the space between the corners
+        edges: SliceFill,
+        /// How the centre is filled
+        centre: SliceFill,
+    },
+}
+
+/// How a part of a [`FillMode::NineSlice`] sprite fills its space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SliceFill {
+    /// Stretch the texture
+    Stretch,
+    /// Repeat the texture
+    Repeat,
 }
 
 /// A sprite, positioned somehwere in world space.
@@ -51,9 +102,25 @@ pub struct Sprite<T> {
     /// If a sprite has a lower `z_index` than another sprite it will
     /// be drawn above it. Note however that for alpha values to work
     /// the draw order is also important.
+    ///
+    /// Only z indices from [`Viewport::NEAR`](crate::Viewport::NEAR) to
+    /// [`Viewport::FAR`](crate::Viewport::FAR) are drawn.
     pub z_index: i32,
     /// Decide whether to tile or stretch.
     pub fill: FillMode,
+    /// Multiplied with the colour of the texture.
+    /// Lower the alpha to fade the sprite out.
+    pub tint: Color,
+    /// Added to the colour of the texture, scaled by the alpha
+    /// of the flash, e.g. flash white when hit.
+    pub flash: Color,
+    /// Mirror the texture horizontally.
+    /// Unlike a negative scale this keeps the anchor in place.
+    pub flip_x: bool,
+    /// Mirror the texture vertically.
+    pub flip_y: bool,
+    /// Rotate the texture. The texture is rotated first, then flipped.
+    pub uv_rotation: UvRotation,
 }
 
 impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>> Sprite<T> {
@@ -78,6 +145,11 @@ impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>>
             anchor: Position::zero(),
             z_index: 50,
             fill: FillMode::Stretch,
+            tint: Color::white(),
+            flash: Color::transparent(),
+            flip_x: false,
+            flip_y: false,
+            uv_rotation: UvRotation::None,
         }
     }
 
@@ -128,6 +200,25 @@ impl<T: Copy + NumCast + Zero + MulAssign + Default + Scalar + Div<Output = T>>
         )
     }
 
+    // Maps texture coordinates of the sprite, relative to the centre of the
+    // sprite, to coordinates in the texture rect. Column major.
+    pub(crate) fn get_uv_transform(&self) -> [f32; 4] {
+        let (fx, fy) = (
+            if self.flip_x { -1.0 } else { 1.0 },
+            if self.flip_y { -1.0 } else { 1.0 },
+        );
+
+        // Undo the flip, then undo the rotation.
+        // The v axis points down, so clockwise on screen
+        // maps (u, v) to (v, -u).
+        match self.uv_rotation {
+            UvRotation::None => [fx, 0.0, 0.0, fy],
+            UvRotation::Clockwise90 => [0.0, -fx, fy, 0.0],
+            UvRotation::Half => [-fx, 0.0, 0.0, -fy],
+            UvRotation::CounterClockwise90 => [0.0, fx, -fy, 0.0],
+        }
+    }
+
     pub(crate) fn get_texture_size(&self) -> (f32, f32) {
         let tex_rect_size = self.texture_rect.size.to_f32();
         let total_tex_size = self.texture_size.to_f32();
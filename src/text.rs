@@ -12,7 +12,7 @@ use unicode_segmentation::UnicodeSegmentation;
 use crate::errors::{NightmareError, Result};
 use crate::renderer::default::VertexData;
 use crate::texture::Texture;
-use crate::{Context, Position, Size, Vector, Sprite, Transform};
+use crate::{Color, Context, Position, Size, Vector, Sprite, Transform};
 
 // -----------------------------------------------------------------------------
 //     - Word wrapping -
@@ -58,6 +58,7 @@ pub struct Text {
     cache: FontCache,
     sprites: Vec<(Sprite<f32>, Transform<f32>)>,
     position: Position<f32>,
+    color: Color,
     caret: Point<f32>,
     previous_glyph_id: Option<GlyphId>
 }
@@ -65,7 +66,7 @@ pub struct Text {
 impl Text {
     /// Create a text object from a path
     pub fn from_path(path: impl AsRef<Path>, font_size: f32, wrap: WordWrap, context: &Context) -> Result<Self> {
-        let scale_factor = context.window().scale_factor() as f32;
+        let scale_factor = context.scale_factor() as f32;
         let font = Font::from_path(path, scale_factor * font_size)?;
         let inst = Self::from_font(Arc::new(font), wrap);
         Ok(inst)
@@ -80,6 +81,7 @@ impl Text {
             cache: FontCache::new(Size::new(512.0, 512.0)),
             sprites: Vec::new(),
             position: Position::zero(),
+            color: Color::white(),
             caret: Point {x: 0.0, y: 0.0, },
             previous_glyph_id: None,
         }
@@ -114,6 +116,14 @@ impl Text {
         });
     }
 
+    /// Set the colour of the text
+    pub fn color(&mut self, color: Color) {
+        self.color = color;
+        self.sprites.iter_mut().for_each(|(sprite, _)| {
+            sprite.tint = color;
+        });
+    }
+
     /// The texture for the font
     pub fn texture(&self) -> &Texture<f32> { 
         &self.cache.texture
@@ -166,13 +176,14 @@ impl Text {
                 let mut sprite = Sprite::new(&self.cache.texture);
                 let mut transform = Transform::default();
                 let scale = self.cache.size.width;
-                let tex_offset = crate::Point::new(uv.min.x as f32, uv.min.y as f32).cast() * scale;
+                let tex_offset = crate::Point::new(uv.min.x, uv.min.y).cast() * scale;
                 let size = Size::new(uv.width(), uv.height());
                 let pos = Position::new(vert.min.x, -vert.max.y) + self.position.cast();
 
                 sprite.texture_rect.origin = tex_offset;
                 sprite.texture_rect.size = size.cast() * scale;
                 sprite.size = size;
+                sprite.tint = self.color;
 
                 transform.translate_mut(pos.cast());
                 transform.scale = Vector::new(scale, scale);
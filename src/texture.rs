@@ -30,34 +30,53 @@ impl OutputInfoExt for OutputInfo {
 
 #[derive(Debug, Copy, Clone)]
 /// Texture format.
-/// Currently only supports RGBA and Red
+/// Currently supports RGBA and Red, as bytes or as half floats.
 pub enum Format {
     /// RGBA values. This is most likely the format to use,
     /// unless dealing with fonts.
     Rgba,
     /// This is most likely used with text
     Red,
+    /// RGBA values stored as 16 bit floats, for values outside 0.0 to 1.0.
+    /// Data is written and read as `f32`s.
+    Rgba16F,
+    /// A red channel stored as a 16 bit float.
+    /// Data is written and read as `f32`s.
+    Red16F,
 }
 
 impl Format {
-    fn to_format(&self) -> PixelFormat {
+    fn to_format(self) -> PixelFormat {
         match self {
-            Format::Rgba => GL_RGBA,
-            Format::Red => GL_RED,
+            Format::Rgba | Format::Rgba16F => GL_RGBA,
+            Format::Red | Format::Red16F => GL_RED,
         }
     }
 
-    fn to_internal_format(&self) -> i32 {
+    fn to_internal_format(self) -> i32 {
         match self {
             Format::Rgba => GL_RGBA8.0 as i32,
             Format::Red => GL_RED.0 as i32,
+            Format::Rgba16F => GL_RGBA16F.0 as i32,
+            Format::Red16F => GL_R16F.0 as i32,
         }
     }
 
+    // The type of the data written to and read from the texture
+    fn data_type(self) -> PixelType {
+        match self {
+            Format::Rgba | Format::Red => GL_UNSIGNED_BYTE,
+            Format::Rgba16F | Format::Red16F => GL_FLOAT,
+        }
+    }
+
+    // Bytes per pixel of the data written to and read from the texture
     fn size(&self) -> usize {
         match self {
             Format::Rgba => 4,
             Format::Red => 1,
+            Format::Rgba16F => 16,
+            Format::Red16F => 4,
         }
     }
 }
@@ -89,8 +108,6 @@ pub enum Filter {
 // -----------------------------------------------------------------------------
 /// Texture builder that is missing a format.
 pub struct NoFormat;
-/// A texture builder that has a format set.
-pub struct WithFormat(Format);
 
 /// A texture builder.
 /// To create a texture builder use [`Texture::new`].
@@ -187,7 +204,7 @@ impl TextureBuilder<Format> {
                 size.height,
                 0, // Border
                 self.1.to_format(),
-                GL_UNSIGNED_BYTE,
+                self.1.data_type(),
                 data.as_ptr().cast(),
             )
         };
@@ -220,7 +237,7 @@ impl TextureBuilder<Format> {
                 size.height,
                 0, // Border
                 self.1.to_format(),
-                GL_UNSIGNED_BYTE,
+                self.1.data_type(),
                 std::ptr::null(),
             )
         };
@@ -262,21 +279,22 @@ pub struct Texture<T: Copy + NumCast> {
 
 impl<T: Copy + NumCast> Texture<T> {
     /// Create a [TextureBuilder](crate::texture::TextureBuilder).
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> TextureBuilder<NoFormat> {
         TextureBuilder::new()
     }
 
     unsafe fn align_for_read(&self) {
         match self.format {
-            Format::Rgba => glPixelStorei(GL_PACK_ALIGNMENT, 4),
             Format::Red => glPixelStorei(GL_PACK_ALIGNMENT, 1),
+            _ => glPixelStorei(GL_PACK_ALIGNMENT, 4),
         }
     }
 
     unsafe fn align_for_write(&self) {
         match self.format {
-            Format::Rgba => glPixelStorei(GL_UNPACK_ALIGNMENT, 4),
             Format::Red => glPixelStorei(GL_UNPACK_ALIGNMENT, 1),
+            _ => glPixelStorei(GL_UNPACK_ALIGNMENT, 4),
         }
     }
 
@@ -374,7 +392,7 @@ impl<T: Copy + NumCast> Texture<T> {
                 size.width,
                 size.height,
                 self.format.to_format(),
-                GL_UNSIGNED_BYTE,
+                self.format.data_type(),
                 data.as_ptr().cast(),
             );
         }
@@ -402,7 +420,7 @@ impl<T: Copy + NumCast> Texture<T> {
                 GL_TEXTURE_2D,
                 0, // mipmap level
                 self.format.to_format(),
-                GL_UNSIGNED_BYTE,
+                self.format.data_type(),
                 output_buf.as_mut_ptr().cast(),
             );
 
@@ -440,8 +458,7 @@ impl<T: Copy + NumCast> Texture<T> {
         };
 
         let capacity = info.pixel_count(pixel_size);
-        let mut bytes = Vec::with_capacity(capacity);
-        unsafe { bytes.set_len(capacity) };
+        let mut bytes = vec![0; capacity];
 
         reader.next_frame(&mut bytes)?;
 
@@ -456,7 +473,14 @@ impl<T: Copy + NumCast> Texture<T> {
     }
 
     /// Write a texture to disk.
+    /// Float textures can't be written as png, and return an error.
     pub fn write_to_disk<U: Pod, V: AsRef<Path>>(&self, dst: V) -> Result<()> {
+        let color_type = match self.format {
+            Format::Rgba => png::ColorType::RGBA,
+            Format::Red => png::ColorType::Grayscale,
+            Format::Rgba16F | Format::Red16F => return Err(NightmareError::InvalidColorType),
+        };
+
         let size = self.size.to_i32();
         let output_buf = self.get_pixels::<U>();
 
@@ -464,13 +488,9 @@ impl<T: Copy + NumCast> Texture<T> {
         let file = File::create(dst.as_ref())?;
         let mut writer = BufWriter::new(file);
         let size = size.to_u32();
-        let mut encoder = png::Encoder::new(&mut writer, size.width, size.height as u32);
-
-        match self.format {
-            Format::Rgba => encoder.set_color(png::ColorType::RGBA),
-            Format::Red => encoder.set_color(png::ColorType::Grayscale),
-        }
+        let mut encoder = png::Encoder::new(&mut writer, size.width, size.height);
 
+        encoder.set_color(color_type);
         encoder.set_depth(png::BitDepth::Eight);
         let mut writer = encoder.write_header().unwrap();
         writer.write_image_data(output_buf.as_bytes())?;
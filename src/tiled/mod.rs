@@ -0,0 +1,992 @@
+#![deny(missing_docs)]
+//! # Tiled maps
+//!
+//! Load maps made with [Tiled](https://www.mapeditor.org), saved either as
+//! TMX (xml) or as JSON.
+//!
+//! Only orthogonal, finite maps are supported. Tilesets can be embedded
+//! in the map or stored in their own file, but they need a single image
+//! (image collection tilesets are not supported).
+//!
+//! Tiled places the origin in the top left corner of the map with y pointing
+//! down. Objects are converted so the bottom left corner of the map is at
+//! zero and y points up, the same as [`TileMap::position`].
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # fn run(mut context: Context, renderer: Renderer<VertexData>, viewport: Viewport) -> Result<()> {
+//! use nightmaregl::tiled::TiledMap;
+//!
+//! let map = TiledMap::from_disk("levels/level1.tmx")?;
+//!
+//! // One tile map and texture per tileset
+//! let mut tile_maps = map.tile_maps(50)?;
+//!
+//! // Every frame
+//! for (texture, tile_map) in &mut tile_maps {
+//!     tile_map.update(0.016);
+//!     tile_map.render(&renderer, texture, &viewport, &mut context)?;
+//! }
+//!
+//! // Spawn points, triggers and the like
+//! for object in map.object_layers().flat_map(|layer| &layer.objects) {
+//!     println!("{} at {:?}", object.name, object.position);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miniz_oxide::inflate;
+
+use crate::errors::NightmareError;
+use crate::tilemap::{Tile, TileMap, Tileset};
+use crate::{Color, Position, Rect, Result, Rotation, Size, Texture};
+
+mod tmj;
+mod tmx;
+
+// Flip flags stored in the high bits of a global tile id
+const FLIP_X: u32 = 0x8000_0000;
+const FLIP_Y: u32 = 0x4000_0000;
+const FLIP_DIAGONAL: u32 = 0x2000_0000;
+// Only used by hexagonal maps, but masked off all the same
+const ROTATE_HEX: u32 = 0x1000_0000;
+
+// -----------------------------------------------------------------------------
+//     - Properties -
+// -----------------------------------------------------------------------------
+/// A custom property set in Tiled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    /// Text
+    String(String),
+    /// Whole number
+    Int(i64),
+    /// Decimal number
+    Float(f64),
+    /// True or false
+    Bool(bool),
+    /// A colour
+    Color(Color),
+    /// Path to a file, as written in the map
+    File(String),
+    /// Id of an object in the map
+    Object(u32),
+}
+
+impl Property {
+    // Properties of the `class` type (and any type added to Tiled
+    // later on) are skipped.
+    fn parse(kind: &str, value: &str) -> Result<Option<Self>> {
+        let invalid = || error(format!("invalid {} property: {:?}", kind, value));
+
+        let property = match kind {
+            "string" => Property::String(value.to_string()),
+            "int" => Property::Int(value.parse().map_err(|_| invalid())?),
+            "float" => Property::Float(value.parse().map_err(|_| invalid())?),
+            "bool" => Property::Bool(value.parse().map_err(|_| invalid())?),
+            "color" => Property::Color(parse_color(value)?),
+            "file" => Property::File(value.to_string()),
+            "object" => Property::Object(value.parse().map_err(|_| invalid())?),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(property))
+    }
+}
+
+/// Custom properties by name.
+pub type Properties = HashMap<String, Property>;
+
+// -----------------------------------------------------------------------------
+//     - Tileset -
+// -----------------------------------------------------------------------------
+/// A tileset used by the map.
+#[derive(Debug, Clone)]
+pub struct TiledTileset {
+    /// The global id of the first tile in the set.
+    pub first_gid: u32,
+    /// Name of the tileset
+    pub name: String,
+    /// Path to the image, relative to the working directory
+    pub image: PathBuf,
+    /// Size of the image in pixels
+    pub image_size: Size<u32>,
+    /// Size of a tile in pixels
+    pub tile_size: Size<u32>,
+    /// Pixels between two tiles
+    pub spacing: u32,
+    /// Pixels between the edge of the image and the tiles
+    pub margin: u32,
+    /// Number of tiles in the set
+    pub tile_count: u32,
+    /// Number of tiles in a row
+    pub columns: u32,
+    /// Animated tiles: frames of tile ids (local to the set)
+    /// and how many seconds each frame is shown.
+    pub animations: HashMap<u32, Vec<(u32, f32)>>,
+    /// Custom properties of the tiles that have any, by local tile id.
+    pub tile_properties: HashMap<u32, Properties>,
+}
+
+impl TiledTileset {
+    /// Does the global tile id belong to this set.
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    /// A [`Tileset`] for a [`TileMap`], including the animations.
+    pub fn tileset(&self) -> Tileset {
+        let mut tileset = Tileset::new(self.image_size, self.tile_size)
+            .with_spacing(self.spacing)
+            .with_margin(self.margin);
+
+        for (id, frames) in &self.animations {
+            tileset.add_animation(*id, frames.clone());
+        }
+
+        tileset
+    }
+
+    /// Load the image of the tileset.
+    pub fn load_texture(&self) -> Result<Texture<f32>> {
+        Texture::from_disk(&self.image)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Objects -
+// -----------------------------------------------------------------------------
+/// The shape of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    /// A rectangle, the size of the object.
+    Rect,
+    /// An ellipse inside the size of the object.
+    Ellipse,
+    /// A single point
+    Point,
+    /// A closed shape. The points are relative to the position of the object.
+    Polygon(Vec<Position<f32>>),
+    /// An open line. The points are relative to the position of the object.
+    Polyline(Vec<Position<f32>>),
+}
+
+/// An object in an object layer.
+#[derive(Debug, Clone)]
+pub struct TiledObject {
+    /// Unique id of the object
+    pub id: u32,
+    /// Name of the object
+    pub name: String,
+    /// The class (or type) of the object
+    pub class: String,
+    /// Position in pixels, relative to the bottom left corner of the map.
+    ///
+    /// For rectangles and ellipses this is the top left corner, for
+    /// tile objects the bottom left corner (the same as in Tiled).
+    pub position: Position<f32>,
+    /// Size in pixels. Points and polygons have no size.
+    pub size: Size<f32>,
+    /// Rotation around [`TiledObject::position`].
+    pub rotation: Rotation<f32>,
+    /// The shape of the object
+    pub shape: ObjectShape,
+    /// Tile objects show a tile from a tileset. The id is a global id.
+    pub tile: Option<Tile>,
+    /// Is the object visible
+    pub visible: bool,
+    /// Custom properties
+    pub properties: Properties,
+}
+
+impl TiledObject {
+    /// The area covered by the object, ignoring the rotation.
+    pub fn rect(&self) -> Rect<f32> {
+        let mut origin = self.position.to_point();
+        if self.tile.is_none() {
+            origin.y -= self.size.height;
+        }
+        Rect::new(origin, self.size)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Layers -
+// -----------------------------------------------------------------------------
+/// A layer of tiles.
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    /// Name of the layer
+    pub name: String,
+    /// Is the layer visible
+    pub visible: bool,
+    /// Opacity of the layer, from 0.0 to 1.0
+    pub opacity: f32,
+    /// The tiles, a row at a time starting with the top row.
+    /// The ids are global ids.
+    pub tiles: Vec<Option<Tile>>,
+    /// Custom properties
+    pub properties: Properties,
+}
+
+/// A layer of objects.
+#[derive(Debug, Clone)]
+pub struct ObjectLayer {
+    /// Name of the layer
+    pub name: String,
+    /// Is the layer visible
+    pub visible: bool,
+    /// The objects in the layer
+    pub objects: Vec<TiledObject>,
+    /// Custom properties
+    pub properties: Properties,
+}
+
+/// A layer in the map.
+///
+/// Group layers are flattened: their layers are added in place of the group,
+/// hidden if the group is hidden and with the opacity of the group applied.
+/// Image layers are skipped.
+#[derive(Debug, Clone)]
+pub enum TiledLayer {
+    /// Tiles
+    Tiles(TileLayer),
+    /// Objects
+    Objects(ObjectLayer),
+}
+
+impl TiledLayer {
+    /// Name of the layer
+    pub fn name(&self) -> &str {
+        match self {
+            TiledLayer::Tiles(layer) => &layer.name,
+            TiledLayer::Objects(layer) => &layer.name,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Map -
+// -----------------------------------------------------------------------------
+/// A map loaded from Tiled.
+#[derive(Debug, Clone)]
+pub struct TiledMap {
+    /// Size in tiles
+    pub size: Size<u32>,
+    /// Size of a tile in pixels
+    pub tile_size: Size<u32>,
+    /// Background colour, if one is set
+    pub background: Option<Color>,
+    /// Tilesets, ordered by their first global id
+    pub tilesets: Vec<TiledTileset>,
+    /// Layers, from the bottom up
+    pub layers: Vec<TiledLayer>,
+    /// Custom properties
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    /// Load a map from disk. Both TMX and JSON maps are supported.
+    /// Paths in the map are relative to the directory of the map.
+    pub fn from_disk(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        match is_xml(&src) {
+            true => Self::from_tmx(&src, dir),
+            false => Self::from_tmj(&src, dir),
+        }
+    }
+
+    /// Parse a TMX map. External tilesets and images
+    /// are relative to `dir`.
+    pub fn from_tmx(src: &str, dir: impl AsRef<Path>) -> Result<Self> {
+        tmx::parse_map(src, dir.as_ref()).map(Self::sorted)
+    }
+
+    /// Parse a JSON map. External tilesets and images
+    /// are relative to `dir`.
+    pub fn from_tmj(src: &str, dir: impl AsRef<Path>) -> Result<Self> {
+        tmj::parse_map(src, dir.as_ref()).map(Self::sorted)
+    }
+
+    fn sorted(mut self) -> Self {
+        self.tilesets.sort_by_key(|tileset| tileset.first_gid);
+        self
+    }
+
+    /// The index of the tileset a global tile id belongs to.
+    pub fn tileset_for(&self, gid: u32) -> Option<usize> {
+        self.tilesets.iter().rposition(|tileset| tileset.contains(gid))
+    }
+
+    /// A layer by name.
+    pub fn layer(&self, name: &str) -> Option<&TiledLayer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    /// All tile layers, from the bottom up.
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TiledLayer::Tiles(layer) => Some(layer),
+            TiledLayer::Objects(_) => None,
+        })
+    }
+
+    /// All object layers, from the bottom up.
+    pub fn object_layers(&self) -> impl Iterator<Item = &ObjectLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            TiledLayer::Objects(layer) => Some(layer),
+            TiledLayer::Tiles(_) => None,
+        })
+    }
+
+    /// A [`TileMap`] with the tiles from one tileset.
+    ///
+    /// Every tile layer becomes a layer in the tile map (even if none of
+    /// its tiles are in the tileset), so layer indices are the same across
+    /// the tile maps of each tileset.
+    /// The top layer is given `z_index`, and every layer below it
+    /// one more than the layer above.
+    ///
+    /// Tiles are placed on the grid of the map, so a tileset with larger
+    /// tiles than the map draws them over the cells above and to the right.
+    /// The opacity of a layer is the alpha of its tint.
+    pub fn tile_map(&self, tileset: usize, z_index: i32) -> TileMap {
+        let set = &self.tilesets[tileset];
+        let mut tile_map = TileMap::new(set.tileset(), self.size).with_tile_size(self.tile_size);
+        let layer_count = self.tile_layers().count();
+
+        for (i, layer) in self.tile_layers().enumerate() {
+            let index = tile_map.add_layer(layer.name.clone(), z_index + (layer_count - 1 - i) as i32);
+            tile_map.set_visible(index, layer.visible);
+            tile_map.set_tint(index, Color::white().with_alpha(layer.opacity));
+
+            for (i, tile) in layer.tiles.iter().enumerate() {
+                let tile = match tile {
+                    Some(tile) if self.tileset_for(tile.id) == Some(tileset) => tile,
+                    _ => continue,
+                };
+
+                let (x, y) = (i as u32 % self.size.width, i as u32 / self.size.width);
+                let local = Tile {
+                    id: tile.id - set.first_gid,
+                    ..*tile
+                };
+                tile_map.set_tile(index, x, y, Some(local));
+            }
+        }
+
+        tile_map
+    }
+
+    /// Load the texture of every tileset, and create a tile map for each one.
+    /// See [`TiledMap::tile_map`].
+    pub fn tile_maps(&self, z_index: i32) -> Result<Vec<(Texture<f32>, TileMap)>> {
+        self.tilesets
+            .iter()
+            .enumerate()
+            .map(|(i, tileset)| Ok((tileset.load_texture()?, self.tile_map(i, z_index))))
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Shared by both formats -
+// -----------------------------------------------------------------------------
+fn error(message: impl Into<String>) -> NightmareError {
+    NightmareError::Tiled(message.into())
+}
+
+fn is_xml(src: &str) -> bool {
+    src.trim_start().starts_with('<')
+}
+
+fn check_map(orientation: &str, infinite: bool) -> Result<()> {
+    if orientation != "orthogonal" {
+        return Err(error(format!("{} maps are not supported", orientation)));
+    }
+    if infinite {
+        return Err(error("infinite maps are not supported"));
+    }
+    Ok(())
+}
+
+fn too_large(size: Size<u32>) -> NightmareError {
+    error(format!("map is too large: {}x{}", size.width, size.height))
+}
+
+// The height of the map in pixels, to flip the y axis of objects
+fn pixel_height(size: Size<u32>, tile_size: Size<u32>) -> Result<f32> {
+    match size.height.checked_mul(tile_size.height) {
+        Some(height) => Ok(height as f32),
+        None => Err(too_large(size)),
+    }
+}
+
+// Tilesets with an empty tile can't be turned into a `Tileset`
+fn check_tile_size(tile_size: Size<u32>) -> Result<Size<u32>> {
+    match tile_size.width > 0 && tile_size.height > 0 {
+        true => Ok(tile_size),
+        false => Err(error(format!("invalid tile size: {}x{}", tile_size.width, tile_size.height))),
+    }
+}
+
+// Load a tileset stored in its own file, in either format.
+fn load_tileset(first_gid: u32, path: &Path) -> Result<TiledTileset> {
+    let src = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    match is_xml(&src) {
+        true => tmx::parse_tileset_file(&src, first_gid, dir),
+        false => tmj::parse_tileset_file(&src, first_gid, dir),
+    }
+}
+
+fn decode_gid(gid: u32) -> Option<Tile> {
+    let id = gid & !(FLIP_X | FLIP_Y | FLIP_DIAGONAL | ROTATE_HEX);
+    if id == 0 {
+        return None;
+    }
+
+    Some(Tile {
+        id,
+        flip_x: gid & FLIP_X != 0,
+        flip_y: gid & FLIP_Y != 0,
+        flip_diagonal: gid & FLIP_DIAGONAL != 0,
+    })
+}
+
+fn decode_tiles(gids: Vec<u32>, size: Size<u32>) -> Result<Vec<Option<Tile>>> {
+    let expected = size.width.checked_mul(size.height).ok_or_else(|| too_large(size))? as usize;
+    if gids.len() != expected {
+        return Err(error(format!("expected {} tiles in a layer, found {}", expected, gids.len())));
+    }
+    Ok(gids.into_iter().map(decode_gid).collect())
+}
+
+// Layer data that is not stored as a list of numbers:
+// csv, or base64 that may be compressed.
+fn decode_data(data: &str, encoding: &str, compression: Option<&str>) -> Result<Vec<u32>> {
+    let bytes = match encoding {
+        "csv" => {
+            return data
+                .split(',')
+                .map(str::trim)
+                .filter(|gid| !gid.is_empty())
+                .map(|gid| gid.parse().map_err(|_| error(format!("invalid tile id: {:?}", gid))))
+                .collect();
+        }
+        "base64" => decode_base64(data)?,
+        _ => return Err(error(format!("unknown encoding: {}", encoding))),
+    };
+
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => inflate::decompress_to_vec_zlib(&bytes).map_err(|e| error(format!("zlib: {:?}", e)))?,
+        Some("gzip") => gunzip(&bytes)?,
+        Some(compression) => return Err(error(format!("{} compression is not supported", compression))),
+    };
+
+    if bytes.len() % 4 != 0 {
+        return Err(error("layer data is not a list of 32 bit tile ids"));
+    }
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+        .collect())
+}
+
+// Standard, padded base64. Whitespace is ignored.
+fn decode_base64(src: &str) -> Result<Vec<u8>> {
+    let src = src.bytes().filter(|c| !c.is_ascii_whitespace()).collect::<Vec<_>>();
+    if src.len() % 4 != 0 {
+        return Err(error("base64 data is truncated"));
+    }
+
+    let padding = src.iter().rev().take_while(|c| **c == b'=').count();
+    if padding > 2 {
+        return Err(error("invalid base64 padding"));
+    }
+
+    let mut bytes = Vec::with_capacity(src.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in &src[..src.len() - padding] {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(error(format!("invalid base64 character: {:?}", *c as char))),
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+// Skip the gzip header, inflate the deflate stream after it,
+// and check the output against the crc and size in the trailer.
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let invalid = || error("invalid gzip data");
+
+    if bytes.len() < 10 || bytes[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid());
+    }
+
+    let flags = bytes[3];
+    let mut offset = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = bytes.get(offset..offset + 2).ok_or_else(invalid)?;
+        offset += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+
+    for flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = bytes.get(offset..).ok_or_else(invalid)?;
+            offset += rest.iter().position(|b| *b == 0).ok_or_else(invalid)? + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    // Only a single member is supported, so the trailer is the last eight bytes
+    let trailer_start = bytes.len().checked_sub(8).filter(|start| *start >= offset).ok_or_else(invalid)?;
+    let (deflated, trailer) = bytes[offset..].split_at(trailer_start - offset);
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    let inflated = inflate::decompress_to_vec(deflated).map_err(|e| error(format!("gzip: {:?}", e)))?;
+
+    if inflated.len() as u32 != size {
+        return Err(error("gzip: size mismatch"));
+    }
+
+    if crc32fast::hash(&inflated) != crc {
+        return Err(error("gzip: crc mismatch"));
+    }
+
+    Ok(inflated)
+}
+
+// `#AARRGGBB` or `#RRGGBB`, the hash is optional.
+// An empty string is a transparent colour.
+fn parse_color(src: &str) -> Result<Color> {
+    let hex = src.trim_start_matches('#');
+    if hex.is_empty() {
+        return Ok(Color::transparent());
+    }
+
+    let value = u32::from_str_radix(hex, 16).map_err(|_| error(format!("invalid colour: {:?}", src)))?;
+    let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+
+    match hex.len() {
+        6 => Ok(Color { r: channel(16), g: channel(8), b: channel(0), a: 1.0 }),
+        8 => Ok(Color { r: channel(16), g: channel(8), b: channel(0), a: channel(24) }),
+        _ => Err(error(format!("invalid colour: {:?}", src))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use miniz_oxide::deflate;
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+            for i in 0..4 {
+                match i <= chunk.len() {
+                    true => out.push(CHARS[(n >> (18 - i * 6)) as usize & 63] as char),
+                    false => out.push('='),
+                }
+            }
+        }
+        out
+    }
+
+    fn gid_bytes(gids: &[u32]) -> Vec<u8> {
+        gids.iter().flat_map(|gid| gid.to_le_bytes().to_vec()).collect()
+    }
+
+    const TILESET: &str = r#"
+        <tileset firstgid="1" name="ground" tilewidth="16" tileheight="16" spacing="1" margin="2" tilecount="64" columns="8">
+            <image source="ground.png" width="140" height="140"/>
+            <tile id="3">
+                <properties>
+                    <property name="solid" type="bool" value="true"/>
+                </properties>
+                <animation>
+                    <frame tileid="3" duration="100"/>
+                    <frame tileid="4" duration="250"/>
+                </animation>
+            </tile>
+        </tileset>
+    "#;
+
+    fn tmx(layers: &str) -> String {
+        format!(
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+            <map version="1.10" orientation="orthogonal" renderorder="right-down" width="3" height="2"
+                 tilewidth="16" tileheight="16" infinite="0" backgroundcolor="#80ff0000">
+                <properties>
+                    <property name="music" value="cave.ogg"/>
+                    <property name="description">Dark
+and damp</property>
+                </properties>
+                {}
+                {}
+            </map>"##,
+            TILESET, layers
+        )
+    }
+
+    #[test]
+    fn tmx_tile_layers() {
+        let flipped = 2 | FLIP_X | FLIP_DIAGONAL;
+        let src = tmx(&format!(
+            r#"
+            <layer id="1" name="ground" width="3" height="2">
+                <data encoding="csv">
+                    1,0,3,
+                    0,{},0
+                </data>
+            </layer>
+            <group name="details" visible="0" opacity="0.5">
+                <layer id="2" name="grass" width="3" height="2" opacity="0.5">
+                    <data>
+                        <tile gid="4"/><tile/><tile/>
+                        <tile/><tile/><tile gid="5"/>
+                    </data>
+                </layer>
+            </group>
+            "#,
+            flipped
+        ));
+
+        let map = TiledMap::from_tmx(&src, "maps").unwrap();
+        assert_eq!(map.size, Size::new(3, 2));
+        assert_eq!(map.background, Some(Color { r: 1.0, g: 0.0, b: 0.0, a: 128.0 / 255.0 }));
+        assert_eq!(map.properties["music"], Property::String("cave.ogg".into()));
+        assert_eq!(map.properties["description"], Property::String("Dark\nand damp".into()));
+
+        let tileset = &map.tilesets[0];
+        assert_eq!(tileset.image, Path::new("maps").join("ground.png"));
+        assert_eq!((tileset.spacing, tileset.margin, tileset.columns), (1, 2, 8));
+        assert_eq!(tileset.animations[&3], vec![(3, 0.1), (4, 0.25)]);
+        assert_eq!(tileset.tile_properties[&3]["solid"], Property::Bool(true));
+
+        let ground = match &map.layers[0] {
+            TiledLayer::Tiles(layer) => layer,
+            _ => panic!("expected a tile layer"),
+        };
+        assert_eq!(ground.tiles[0], Some(Tile::new(1)));
+        assert_eq!(ground.tiles[1], None);
+        let flipped = ground.tiles[4].unwrap();
+        assert_eq!((flipped.id, flipped.flip_x, flipped.flip_y, flipped.flip_diagonal), (2, true, false, true));
+
+        // Flattened out of the group
+        let grass = match map.layer("grass") {
+            Some(TiledLayer::Tiles(layer)) => layer,
+            _ => panic!("expected a tile layer"),
+        };
+        assert!(!grass.visible);
+        assert_eq!(grass.opacity, 0.25);
+
+        // Local ids, and the top layer in front
+        let mut tile_map = map.tile_map(0, 10);
+        assert_eq!(tile_map.tile(0, 0, 0), Some(Tile::new(0)));
+        assert_eq!(tile_map.tile(0, 1, 1), Some(Tile { id: 1, ..flipped }));
+        assert_eq!(tile_map.tile(1, 2, 1), Some(Tile::new(4)));
+        assert_eq!(tile_map.layer("grass"), Some(1));
+        assert_eq!(tile_map.chunk_vertex_data(1, 0)[0].tint.a, 0.25);
+        assert_eq!(tile_map.tileset().texture_rect(9), Rect::new(crate::Point::new(19, 19), Size::new(16, 16)));
+    }
+
+    #[test]
+    fn tiles_larger_than_the_map_grid() {
+        let src = r#"<map orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+            <tileset firstgid="1" name="trees" tilewidth="32" tileheight="48" tilecount="2" columns="2">
+                <image source="trees.png" width="64" height="48"/>
+            </tileset>
+            <layer name="trees" width="2" height="2"><data encoding="csv">0,0,0,2</data></layer>
+        </map>"#;
+        let map = TiledMap::from_tmx(src, "").unwrap();
+
+        // The bottom left corner of the tree is the bottom left corner of its cell
+        let mut tile_map = map.tile_map(0, 0);
+        let vd = tile_map.chunk_vertex_data(0, 0)[0];
+        assert_eq!((vd.model[(0, 3)], vd.model[(1, 3)]), (16.0, 0.0));
+        assert_eq!((vd.model[(0, 0)], vd.model[(1, 1)]), (32.0, 48.0));
+    }
+
+    #[test]
+    fn compressed_data() {
+        let gids = [1, 0, 3, 0, 2 | FLIP_Y, 0];
+        let bytes = gid_bytes(&gids);
+
+        assert_eq!(decode_data(&encode_base64(&bytes), "base64", None).unwrap(), gids);
+
+        let zlib = deflate::compress_to_vec_zlib(&bytes, 6);
+        assert_eq!(decode_data(&encode_base64(&zlib), "base64", Some("zlib")).unwrap(), gids);
+
+        // Header with a file name, deflate data, then the crc and size
+        let mut gzip = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 3];
+        gzip.extend(b"map.bin\0");
+        gzip.extend(deflate::compress_to_vec(&bytes, 6));
+        gzip.extend(&crc32fast::hash(&bytes).to_le_bytes());
+        gzip.extend(&(bytes.len() as u32).to_le_bytes());
+        assert_eq!(decode_data(&encode_base64(&gzip), "base64", Some("gzip")).unwrap(), gids);
+
+        // Corrupt crc, wrong size, missing trailer
+        let len = gzip.len();
+        let mut bad_crc = gzip.clone();
+        bad_crc[len - 8] ^= 1;
+        assert!(decode_data(&encode_base64(&bad_crc), "base64", Some("gzip")).is_err());
+        let mut bad_size = gzip.clone();
+        bad_size[len - 4] += 1;
+        assert!(decode_data(&encode_base64(&bad_size), "base64", Some("gzip")).is_err());
+        assert!(decode_data(&encode_base64(&gzip[..len - 8]), "base64", Some("gzip")).is_err());
+
+        assert!(decode_data(&encode_base64(&bytes), "base64", Some("zstd")).is_err());
+
+        // Truncated or badly padded base64
+        let encoded = encode_base64(&bytes);
+        assert!(decode_data(&encoded[..encoded.len() - 1], "base64", None).is_err());
+        assert!(decode_data("AQAA=AAA", "base64", None).is_err());
+        assert!(decode_data("A===", "base64", None).is_err());
+        assert!(decode_data("1,2,x", "csv", None).is_err());
+
+        let src = tmx(&format!(
+            r#"<layer name="ground" width="3" height="2"><data encoding="base64" compression="zlib">{}</data></layer>"#,
+            encode_base64(&zlib)
+        ));
+        let map = TiledMap::from_tmx(&src, "").unwrap();
+        assert!(map.tile_layers().next().unwrap().tiles[4].unwrap().flip_y);
+    }
+
+    #[test]
+    fn tmx_objects() {
+        let src = tmx(
+            r##"
+            <objectgroup id="3" name="entities">
+                <object id="1" name="door" class="trigger" x="16" y="0" width="16" height="8" rotation="90">
+                    <properties>
+                        <property name="locked" type="bool" value="false"/>
+                        <property name="keys" type="int" value="2"/>
+                        <property name="speed" type="float" value="1.5"/>
+                        <property name="light" type="color" value="#ff00ff00"/>
+                        <property name="script" type="file" value="door.lua"/>
+                        <property name="target" type="object" value="2"/>
+                    </properties>
+                </object>
+                <object id="2" name="spawn" type="player" x="8" y="24"><point/></object>
+                <object id="3" x="0" y="32" width="10" height="10"><ellipse/></object>
+                <object id="4" x="4" y="4"><polygon points="0,0 8,0 8,8"/></object>
+                <object id="5" x="4" y="4" visible="0"><polyline points="0,0 -2,6.5"/></object>
+                <object id="6" gid="2147483652" x="32" y="32" width="16" height="16"/>
+            </objectgroup>
+            "##,
+        );
+        let map = TiledMap::from_tmx(&src, "").unwrap();
+        let objects = &map.object_layers().next().unwrap().objects;
+        assert_eq!(objects.len(), 6);
+
+        // The map is 32 pixels high
+        let door = &objects[0];
+        assert_eq!((door.name.as_str(), door.class.as_str()), ("door", "trigger"));
+        assert_eq!(door.position, Position::new(16.0, 32.0));
+        assert_eq!(door.rect(), Rect::new(crate::Point::new(16.0, 24.0), Size::new(16.0, 8.0)));
+        assert_eq!(door.rotation, Rotation::degrees(-90.0));
+        assert_eq!(door.shape, ObjectShape::Rect);
+        assert_eq!(door.properties["locked"], Property::Bool(false));
+        assert_eq!(door.properties["keys"], Property::Int(2));
+        assert_eq!(door.properties["speed"], Property::Float(1.5));
+        assert_eq!(door.properties["light"], Property::Color(Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 }));
+        assert_eq!(door.properties["script"], Property::File("door.lua".into()));
+        assert_eq!(door.properties["target"], Property::Object(2));
+
+        assert_eq!(objects[1].class, "player");
+        assert_eq!(objects[1].shape, ObjectShape::Point);
+        assert_eq!(objects[1].position, Position::new(8.0, 8.0));
+        assert_eq!(objects[2].shape, ObjectShape::Ellipse);
+        assert_eq!(
+            objects[3].shape,
+            ObjectShape::Polygon(vec![Position::new(0.0, 0.0), Position::new(8.0, 0.0), Position::new(8.0, -8.0)])
+        );
+        assert_eq!(objects[4].shape, ObjectShape::Polyline(vec![Position::new(0.0, 0.0), Position::new(-2.0, -6.5)]));
+        assert!(!objects[4].visible);
+
+        let tile = objects[5].tile.unwrap();
+        assert_eq!((tile.id, tile.flip_x), (4, true));
+        assert_eq!(objects[5].rect(), Rect::new(crate::Point::new(32.0, 0.0), Size::new(16.0, 16.0)));
+    }
+
+    #[test]
+    fn external_tilesets() {
+        let dir = std::env::temp_dir().join(format!("nightmaregl-tiled-tests-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tilesets")).unwrap();
+        fs::write(
+            dir.join("tilesets/things.tsx"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <tileset version="1.10" name="things" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+                <image source="things.png" width="32" height="32"/>
+            </tileset>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tilesets/items.tsj"),
+            r#"{
+                "name": "items", "image": "../items.png", "imagewidth": 64, "imageheight": 16,
+                "tilewidth": 16, "tileheight": 16, "tilecount": 4, "columns": 4,
+                "tiles": [{ "id": 1, "animation": [{ "tileid": 1, "duration": 200 }, { "tileid": 2, "duration": 200 }] }]
+            }"#,
+        )
+        .unwrap();
+
+        let src = r#"<map orientation="orthogonal" width="2" height="1" tilewidth="16" tileheight="16">
+            <tileset firstgid="5" source="tilesets/items.tsj"/>
+            <tileset firstgid="1" source="tilesets/things.tsx"/>
+            <layer name="layer" width="2" height="1"><data encoding="csv">2,6</data></layer>
+        </map>"#;
+        let map = TiledMap::from_tmx(src, &dir).unwrap();
+
+        assert_eq!(map.tilesets[0].name, "things");
+        assert_eq!(map.tilesets[0].image, dir.join("tilesets/things.png"));
+        assert_eq!(map.tilesets[1].image, dir.join("tilesets/../items.png"));
+        assert_eq!(map.tilesets[1].animations[&1], vec![(1, 0.2), (2, 0.2)]);
+
+        assert_eq!(map.tileset_for(2), Some(0));
+        assert_eq!(map.tileset_for(6), Some(1));
+        assert_eq!(map.tileset_for(9), None);
+
+        // Each tile map only has the tiles of its own tileset
+        let things = map.tile_map(0, 0);
+        let items = map.tile_map(1, 0);
+        assert_eq!((things.tile(0, 0, 0), things.tile(0, 1, 0)), (Some(Tile::new(1)), None));
+        assert_eq!((items.tile(0, 0, 0), items.tile(0, 1, 0)), (None, Some(Tile::new(1))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tmj_map() {
+        let gids = gid_bytes(&[0, 0, 3 | FLIP_X, 1, 0, 0]);
+        let src = format!(
+            r##"{{
+                "orientation": "orthogonal", "infinite": false, "width": 3, "height": 2,
+                "tilewidth": 16, "tileheight": 16,
+                "properties": [{{ "name": "gravity", "type": "float", "value": 9.8 }}],
+                "tilesets": [{{
+                    "firstgid": 1, "name": "ground", "image": "ground.png", "imagewidth": 48, "imageheight": 16,
+                    "tilewidth": 16, "tileheight": 16, "tilecount": 3, "columns": 3,
+                    "tiles": [{{ "id": 0, "properties": [{{ "name": "solid", "type": "bool", "value": true }}] }}]
+                }}],
+                "layers": [
+                    {{ "type": "tilelayer", "name": "ground", "width": 3, "height": 2, "data": [1, 2, 0, 0, 0, 2147483651] }},
+                    {{ "type": "group", "name": "group", "opacity": 0.5, "layers": [
+                        {{ "type": "tilelayer", "name": "detail", "width": 3, "height": 2,
+                           "encoding": "base64", "compression": "zlib", "data": "{}" }},
+                        {{ "type": "objectgroup", "name": "entities", "objects": [
+                            {{ "id": 1, "name": "spawn", "type": "player", "x": 8, "y": 24, "point": true,
+                               "properties": [{{ "name": "lives", "type": "int", "value": 3 }},
+                                              {{ "name": "tint", "type": "color", "value": "#ff0000ff" }}] }},
+                            {{ "id": 2, "x": 0, "y": 0, "polygon": [{{ "x": 0, "y": 0 }}, {{ "x": 4, "y": 4 }}, {{ "x": 0, "y": 4 }}] }},
+                            {{ "id": 3, "x": 0, "y": 8, "width": 4, "height": 8, "ellipse": true, "rotation": -45 }}
+                        ] }}
+                    ] }},
+                    {{ "type": "imagelayer", "name": "sky" }}
+                ]
+            }}"##,
+            encode_base64(&deflate::compress_to_vec_zlib(&gids, 6))
+        );
+
+        let map = TiledMap::from_tmj(&src, "").unwrap();
+        assert_eq!(map.properties["gravity"], Property::Float(9.8));
+        assert_eq!(map.tilesets[0].tile_properties[&0]["solid"], Property::Bool(true));
+        assert_eq!(map.layers.len(), 3);
+
+        let ground = map.tile_layers().next().unwrap();
+        assert_eq!(ground.tiles[1], Some(Tile::new(2)));
+        assert_eq!(ground.tiles[5].map(|t| (t.id, t.flip_x)), Some((3, true)));
+
+        let detail = map.tile_layers().nth(1).unwrap();
+        assert_eq!(detail.opacity, 0.5);
+        assert_eq!(detail.tiles[2].map(|t| (t.id, t.flip_x)), Some((3, true)));
+
+        let objects = &map.object_layers().next().unwrap().objects;
+        assert_eq!((objects[0].class.as_str(), objects[0].shape.clone()), ("player", ObjectShape::Point));
+        assert_eq!(objects[0].position, Position::new(8.0, 8.0));
+        assert_eq!(objects[0].properties["lives"], Property::Int(3));
+        assert_eq!(objects[0].properties["tint"], Property::Color(Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 }));
+        assert_eq!(
+            objects[1].shape,
+            ObjectShape::Polygon(vec![Position::new(0.0, 0.0), Position::new(4.0, -4.0), Position::new(0.0, -4.0)])
+        );
+        assert_eq!(objects[2].shape, ObjectShape::Ellipse);
+        assert_eq!(objects[2].rotation, Rotation::degrees(45.0));
+
+        let tile_map = map.tile_map(0, 0);
+        assert_eq!(tile_map.tile(1, 2, 0).map(|t| t.id), Some(2));
+    }
+
+    #[test]
+    fn unsupported_maps() {
+        let isometric = r#"<map orientation="isometric" width="1" height="1" tilewidth="16" tileheight="16"/>"#;
+        assert!(TiledMap::from_tmx(isometric, "").is_err());
+
+        let infinite = r#"{ "orientation": "orthogonal", "infinite": true, "width": 1, "height": 1,
+            "tilewidth": 16, "tileheight": 16, "layers": [], "tilesets": [] }"#;
+        assert!(TiledMap::from_tmj(infinite, "").is_err());
+
+        let short = r#"<map orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+            <layer name="layer" width="2" height="2"><data encoding="csv">1,2,3</data></layer>
+        </map>"#;
+        assert!(TiledMap::from_tmx(short, "").is_err());
+
+        let tall = r#"<map orientation="orthogonal" width="1" height="70000" tilewidth="16" tileheight="70000"/>"#;
+        assert!(TiledMap::from_tmx(tall, "").is_err());
+
+        let huge = r#"{ "orientation": "orthogonal", "width": 70000, "height": 70000,
+            "tilewidth": 1, "tileheight": 1, "tilesets": [],
+            "layers": [{ "type": "tilelayer", "name": "layer", "data": [] }] }"#;
+        match TiledMap::from_tmj(huge, "") {
+            Err(NightmareError::Tiled(message)) => assert_eq!(message, "map is too large: 70000x70000"),
+            other => panic!("expected a map that is too large, got {:?}", other.map(|_| ())),
+        }
+
+        let empty_tiles = TILESET.replace(r#"tilewidth="16""#, r#"tilewidth="0""#);
+        let empty_tiles = format!(r#"<map orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16">{}</map>"#, empty_tiles);
+        match TiledMap::from_tmx(&empty_tiles, "") {
+            Err(NightmareError::Tiled(message)) => assert_eq!(message, "invalid tile size: 0x16"),
+            other => panic!("expected an invalid tile size, got {:?}", other.map(|_| ())),
+        }
+    }
+}
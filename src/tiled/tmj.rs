@@ -0,0 +1,254 @@
+// -----------------------------------------------------------------------------
+//     - TMJ -
+//     Tiled's json format, for both maps (.tmj) and tilesets (.tsj)
+// -----------------------------------------------------------------------------
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::json::{self, Value};
+
+use super::*;
+
+fn number(value: &Value, name: &str) -> Result<f64> {
+    value
+        .get(name)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| error(format!("missing or invalid field `{}`", name)))
+}
+
+fn number_or(value: &Value, name: &str, default: f64) -> f64 {
+    value.get(name).and_then(Value::as_f64).unwrap_or(default)
+}
+
+fn string_or<'a>(value: &'a Value, name: &str, default: &'a str) -> &'a str {
+    value.get(name).and_then(Value::as_str).unwrap_or(default)
+}
+
+fn flag(value: &Value, name: &str, default: bool) -> bool {
+    value.get(name).and_then(Value::as_bool).unwrap_or(default)
+}
+
+fn array<'a>(value: &'a Value, name: &str) -> &'a [Value] {
+    value.get(name).and_then(json::as_array).unwrap_or_default()
+}
+
+fn unsigned(value: &Value, name: &str) -> Result<u32> {
+    value
+        .get(name)
+        .and_then(json::as_u32)
+        .ok_or_else(|| error(format!("missing or invalid field `{}`", name)))
+}
+
+fn size(value: &Value, width: &str, height: &str) -> Result<Size<u32>> {
+    Ok(Size::new(unsigned(value, width)?, unsigned(value, height)?))
+}
+
+// -----------------------------------------------------------------------------
+//     - Map -
+// -----------------------------------------------------------------------------
+pub(super) fn parse_map(src: &str, dir: &Path) -> Result<TiledMap> {
+    let root = json::parse(src)?;
+
+    check_map(string_or(&root, "orientation", "orthogonal"), flag(&root, "infinite", false))?;
+
+    let size = size(&root, "width", "height")?;
+    let tile_size = self::size(&root, "tilewidth", "tileheight")?;
+
+    let tilesets = array(&root, "tilesets")
+        .iter()
+        .map(|tileset| {
+            let first_gid = number(tileset, "firstgid")? as u32;
+            match tileset.get("source").and_then(Value::as_str) {
+                Some(source) => load_tileset(first_gid, &dir.join(source)),
+                None => parse_tileset(tileset, first_gid, dir),
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let mut layers = Vec::new();
+    let height = pixel_height(size, tile_size)?;
+    parse_layers(array(&root, "layers"), size, height, (true, 1.0), &mut layers)?;
+
+    Ok(TiledMap {
+        size,
+        tile_size,
+        background: root.get("backgroundcolor").and_then(Value::as_str).map(parse_color).transpose()?,
+        tilesets,
+        layers,
+        properties: parse_properties(&root)?,
+    })
+}
+
+// -----------------------------------------------------------------------------
+//     - Tilesets -
+// -----------------------------------------------------------------------------
+pub(super) fn parse_tileset_file(src: &str, first_gid: u32, dir: &Path) -> Result<TiledTileset> {
+    parse_tileset(&json::parse(src)?, first_gid, dir)
+}
+
+fn parse_tileset(tileset: &Value, first_gid: u32, dir: &Path) -> Result<TiledTileset> {
+    let image = tileset
+        .get("image")
+        .and_then(Value::as_str)
+        .ok_or_else(|| error("image collection tilesets are not supported"))?;
+
+    let mut animations = HashMap::new();
+    let mut tile_properties = HashMap::new();
+
+    for tile in array(tileset, "tiles") {
+        let id = number(tile, "id")? as u32;
+
+        if let Some(animation) = tile.get("animation").and_then(json::as_array) {
+            let frames = animation
+                .iter()
+                .map(|frame| Ok((number(frame, "tileid")? as u32, number(frame, "duration")? as f32 / 1000.0)))
+                .collect::<Result<_>>()?;
+            animations.insert(id, frames);
+        }
+
+        let properties = parse_properties(tile)?;
+        if !properties.is_empty() {
+            tile_properties.insert(id, properties);
+        }
+    }
+
+    Ok(TiledTileset {
+        first_gid,
+        name: string_or(tileset, "name", "").to_string(),
+        image: dir.join(image),
+        image_size: size(tileset, "imagewidth", "imageheight")?,
+        tile_size: check_tile_size(size(tileset, "tilewidth", "tileheight")?)?,
+        spacing: number_or(tileset, "spacing", 0.0) as u32,
+        margin: number_or(tileset, "margin", 0.0) as u32,
+        tile_count: number(tileset, "tilecount")? as u32,
+        columns: number(tileset, "columns")? as u32,
+        animations,
+        tile_properties,
+    })
+}
+
+// -----------------------------------------------------------------------------
+//     - Layers -
+// -----------------------------------------------------------------------------
+// Add the layers of the map, or of a group, flattening nested groups.
+// `parent` is the visibility and opacity of the group.
+fn parse_layers(
+    values: &[Value],
+    size: Size<u32>,
+    height: f32,
+    parent: (bool, f32),
+    layers: &mut Vec<TiledLayer>,
+) -> Result<()> {
+    for layer in values {
+        let visible = parent.0 && flag(layer, "visible", true);
+        let opacity = parent.1 * number_or(layer, "opacity", 1.0) as f32;
+        let name = string_or(layer, "name", "").to_string();
+
+        match string_or(layer, "type", "") {
+            "tilelayer" => layers.push(TiledLayer::Tiles(TileLayer {
+                name,
+                visible,
+                opacity,
+                tiles: parse_data(layer, size)?,
+                properties: parse_properties(layer)?,
+            })),
+            "objectgroup" => layers.push(TiledLayer::Objects(ObjectLayer {
+                name,
+                visible,
+                objects: array(layer, "objects")
+                    .iter()
+                    .map(|object| parse_object(object, height))
+                    .collect::<Result<_>>()?,
+                properties: parse_properties(layer)?,
+            })),
+            "group" => parse_layers(array(layer, "layers"), size, height, (visible, opacity), layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_data(layer: &Value, size: Size<u32>) -> Result<Vec<Option<Tile>>> {
+    let gids = match layer.get("data") {
+        Some(Value::Array(gids)) => gids
+            .iter()
+            .map(|gid| json::as_u32(gid).ok_or_else(|| error(format!("invalid tile id: {}", gid))))
+            .collect::<Result<_>>()?,
+        Some(Value::String(data)) => decode_data(
+            data,
+            string_or(layer, "encoding", "base64"),
+            layer.get("compression").and_then(Value::as_str),
+        )?,
+        _ => return Err(error("tile layer without data")),
+    };
+
+    decode_tiles(gids, size)
+}
+
+// -----------------------------------------------------------------------------
+//     - Objects -
+// -----------------------------------------------------------------------------
+fn parse_object(object: &Value, height: f32) -> Result<TiledObject> {
+    let shape = if flag(object, "ellipse", false) {
+        ObjectShape::Ellipse
+    } else if flag(object, "point", false) {
+        ObjectShape::Point
+    } else if let Some(polygon) = object.get("polygon").and_then(json::as_array) {
+        ObjectShape::Polygon(parse_points(polygon)?)
+    } else if let Some(polyline) = object.get("polyline").and_then(json::as_array) {
+        ObjectShape::Polyline(parse_points(polyline)?)
+    } else {
+        ObjectShape::Rect
+    };
+
+    let class = object.get("class").and_then(Value::as_str).unwrap_or_else(|| string_or(object, "type", ""));
+    let coord = |name: &str| number_or(object, name, 0.0) as f32;
+
+    Ok(TiledObject {
+        id: coord("id") as u32,
+        name: string_or(object, "name", "").to_string(),
+        class: class.to_string(),
+        position: Position::new(coord("x"), height - coord("y")),
+        size: Size::new(coord("width"), coord("height")),
+        rotation: Rotation::degrees(-coord("rotation")),
+        shape,
+        tile: object.get("gid").and_then(json::as_u32).and_then(decode_gid),
+        visible: flag(object, "visible", true),
+        properties: parse_properties(object)?,
+    })
+}
+
+// Points relative to the object
+fn parse_points(points: &[Value]) -> Result<Vec<Position<f32>>> {
+    points
+        .iter()
+        .map(|point| Ok(Position::new(number(point, "x")? as f32, -number(point, "y")? as f32)))
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+//     - Properties -
+// -----------------------------------------------------------------------------
+fn parse_properties(value: &Value) -> Result<Properties> {
+    let mut properties = Properties::new();
+
+    for property in array(value, "properties") {
+        let name = string_or(property, "name", "").to_string();
+        let kind = string_or(property, "type", "string");
+
+        // Parse the value the same way as a TMX property
+        let value = match property.get("value") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::Bool(b)) => b.to_string(),
+            _ => continue,
+        };
+
+        if let Some(value) = Property::parse(kind, &value)? {
+            properties.insert(name, value);
+        }
+    }
+
+    Ok(properties)
+}
@@ -0,0 +1,298 @@
+// -----------------------------------------------------------------------------
+//     - TMX -
+//     Tiled's xml format, for both maps (.tmx) and tilesets (.tsx)
+// -----------------------------------------------------------------------------
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use xml::reader::{EventReader, XmlEvent};
+
+use super::*;
+
+// -----------------------------------------------------------------------------
+//     - Element -
+//     Just enough of a document tree to walk a map
+// -----------------------------------------------------------------------------
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn parse(src: &str) -> Result<Element> {
+        let mut stack: Vec<Element> = Vec::new();
+
+        for event in EventReader::new(src.as_bytes()) {
+            match event.map_err(|e| error(e.to_string()))? {
+                XmlEvent::StartElement { name, attributes, .. } => stack.push(Element {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|a| (a.name.local_name, a.value)).collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or_else(|| error("unbalanced xml"))?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Err(error("no root element"))
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    // An attribute that has to be there
+    fn required<T: FromStr>(&self, name: &str) -> Result<T> {
+        self.attr(name)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| error(format!("missing or invalid attribute `{}` on <{}>", name, self.name)))
+    }
+
+    fn optional<T: FromStr>(&self, name: &str, default: T) -> Result<T> {
+        match self.attr(name) {
+            Some(_) => self.required(name),
+            None => Ok(default),
+        }
+    }
+
+    // Booleans are stored as 0 and 1
+    fn flag(&self, name: &str, default: bool) -> Result<bool> {
+        self.optional(name, default as u8).map(|value| value != 0)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Map -
+// -----------------------------------------------------------------------------
+pub(super) fn parse_map(src: &str, dir: &Path) -> Result<TiledMap> {
+    let root = Element::parse(src)?;
+    if root.name != "map" {
+        return Err(error("expected a <map> element"));
+    }
+
+    check_map(root.attr("orientation").unwrap_or("orthogonal"), root.flag("infinite", false)?)?;
+
+    let size = Size::new(root.required("width")?, root.required("height")?);
+    let tile_size = Size::new(root.required("tilewidth")?, root.required("tileheight")?);
+
+    let tilesets = root
+        .children("tileset")
+        .map(|tileset| {
+            let first_gid = tileset.required("firstgid")?;
+            match tileset.attr("source") {
+                Some(source) => load_tileset(first_gid, &dir.join(source)),
+                None => parse_tileset(tileset, first_gid, dir),
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let mut layers = Vec::new();
+    let height = pixel_height(size, tile_size)?;
+    parse_layers(&root, size, height, (true, 1.0), &mut layers)?;
+
+    Ok(TiledMap {
+        size,
+        tile_size,
+        background: root.attr("backgroundcolor").map(parse_color).transpose()?,
+        tilesets,
+        layers,
+        properties: parse_properties(&root)?,
+    })
+}
+
+// -----------------------------------------------------------------------------
+//     - Tilesets -
+// -----------------------------------------------------------------------------
+pub(super) fn parse_tileset_file(src: &str, first_gid: u32, dir: &Path) -> Result<TiledTileset> {
+    let root = Element::parse(src)?;
+    if root.name != "tileset" {
+        return Err(error("expected a <tileset> element"));
+    }
+    parse_tileset(&root, first_gid, dir)
+}
+
+fn parse_tileset(tileset: &Element, first_gid: u32, dir: &Path) -> Result<TiledTileset> {
+    let image = tileset
+        .child("image")
+        .ok_or_else(|| error("image collection tilesets are not supported"))?;
+
+    let mut animations = HashMap::new();
+    let mut tile_properties = HashMap::new();
+
+    for tile in tileset.children("tile") {
+        let id = tile.required("id")?;
+
+        if let Some(animation) = tile.child("animation") {
+            let frames = animation
+                .children("frame")
+                .map(|frame| Ok((frame.required("tileid")?, frame.required::<f32>("duration")? / 1000.0)))
+                .collect::<Result<_>>()?;
+            animations.insert(id, frames);
+        }
+
+        let properties = parse_properties(tile)?;
+        if !properties.is_empty() {
+            tile_properties.insert(id, properties);
+        }
+    }
+
+    Ok(TiledTileset {
+        first_gid,
+        name: tileset.optional("name", String::new())?,
+        image: dir.join(image.required::<String>("source")?),
+        image_size: Size::new(image.required("width")?, image.required("height")?),
+        tile_size: check_tile_size(Size::new(tileset.required("tilewidth")?, tileset.required("tileheight")?))?,
+        spacing: tileset.optional("spacing", 0)?,
+        margin: tileset.optional("margin", 0)?,
+        tile_count: tileset.required("tilecount")?,
+        columns: tileset.required("columns")?,
+        animations,
+        tile_properties,
+    })
+}
+
+// -----------------------------------------------------------------------------
+//     - Layers -
+// -----------------------------------------------------------------------------
+// Add the layers of the map, or of a group, flattening nested groups.
+// `parent` is the visibility and opacity of the group.
+fn parse_layers(
+    element: &Element,
+    size: Size<u32>,
+    height: f32,
+    parent: (bool, f32),
+    layers: &mut Vec<TiledLayer>,
+) -> Result<()> {
+    for layer in &element.children {
+        let visible = parent.0 && layer.flag("visible", true)?;
+        let opacity = parent.1 * layer.optional("opacity", 1.0)?;
+
+        match layer.name.as_str() {
+            "layer" => layers.push(TiledLayer::Tiles(TileLayer {
+                name: layer.optional("name", String::new())?,
+                visible,
+                opacity,
+                tiles: parse_data(layer, size)?,
+                properties: parse_properties(layer)?,
+            })),
+            "objectgroup" => layers.push(TiledLayer::Objects(ObjectLayer {
+                name: layer.optional("name", String::new())?,
+                visible,
+                objects: layer
+                    .children("object")
+                    .map(|object| parse_object(object, height))
+                    .collect::<Result<_>>()?,
+                properties: parse_properties(layer)?,
+            })),
+            "group" => parse_layers(layer, size, height, (visible, opacity), layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_data(layer: &Element, size: Size<u32>) -> Result<Vec<Option<Tile>>> {
+    let data = layer.child("data").ok_or_else(|| error("tile layer without <data>"))?;
+
+    let gids = match data.attr("encoding") {
+        Some(encoding) => decode_data(&data.text, encoding, data.attr("compression"))?,
+        None => data
+            .children("tile")
+            .map(|tile| tile.optional("gid", 0))
+            .collect::<Result<_>>()?,
+    };
+
+    decode_tiles(gids, size)
+}
+
+// -----------------------------------------------------------------------------
+//     - Objects -
+// -----------------------------------------------------------------------------
+fn parse_object(object: &Element, height: f32) -> Result<TiledObject> {
+    let shape = if object.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if object.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = object.child("polygon") {
+        ObjectShape::Polygon(parse_points(polygon)?)
+    } else if let Some(polyline) = object.child("polyline") {
+        ObjectShape::Polyline(parse_points(polyline)?)
+    } else {
+        ObjectShape::Rect
+    };
+
+    let class = object.attr("class").or_else(|| object.attr("type")).unwrap_or_default();
+
+    Ok(TiledObject {
+        id: object.optional("id", 0)?,
+        name: object.optional("name", String::new())?,
+        class: class.to_string(),
+        position: Position::new(object.optional("x", 0.0)?, height - object.optional("y", 0.0)?),
+        size: Size::new(object.optional("width", 0.0)?, object.optional("height", 0.0)?),
+        rotation: Rotation::degrees(-object.optional("rotation", 0.0)?),
+        shape,
+        tile: decode_gid(object.optional("gid", 0)?),
+        visible: object.flag("visible", true)?,
+        properties: parse_properties(object)?,
+    })
+}
+
+// "x,y x,y ...", relative to the object
+fn parse_points(element: &Element) -> Result<Vec<Position<f32>>> {
+    let points = element.attr("points").unwrap_or_default();
+
+    points
+        .split_whitespace()
+        .map(|point| {
+            let mut coords = point.split(',').map(str::parse::<f32>);
+            match (coords.next(), coords.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Ok(Position::new(x, -y)),
+                _ => Err(error(format!("invalid point: {:?}", point))),
+            }
+        })
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+//     - Properties -
+// -----------------------------------------------------------------------------
+fn parse_properties(element: &Element) -> Result<Properties> {
+    let mut properties = Properties::new();
+
+    for property in element.child("properties").into_iter().flat_map(|p| p.children("property")) {
+        let name = property.required("name")?;
+        // Multi line strings are stored as text instead of an attribute
+        let value = property.attr("value").unwrap_or(&property.text);
+        let kind = property.attr("type").unwrap_or("string");
+
+        if let Some(value) = Property::parse(kind, value)? {
+            properties.insert(name, value);
+        }
+    }
+
+    Ok(properties)
+}
@@ -0,0 +1,718 @@
+#![deny(missing_docs)]
+//! # Tile maps
+//!
+//! A grid of tiles from a single tileset texture, split into layers and chunks.
+//!
+//! The vertex data of a chunk is built once and kept on the GPU. It is only
+//! uploaded again when a tile in the chunk changes (or an animated tile moves
+//! to its next frame), and chunks outside the viewport are not drawn at all.
+//!
+//! Row zero is the top row of the map, the same as in most map editors.
+//!
+//! ```
+//! # use nightmaregl::*;
+//! # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, viewport: Viewport) -> Result<()> {
+//! use nightmaregl::tilemap::{Tile, TileMap, Tileset};
+//!
+//! let tileset = Tileset::new(Size::new(256, 256), Size::new(16, 16));
+//! let mut map = TileMap::new(tileset, Size::new(256, 256));
+//! let ground = map.add_layer("ground", 50);
+//!
+//! for x in 0..256 {
+//!     map.set_tile(ground, x, 255, Some(Tile::new(3)));
+//! }
+//!
+//! // Every frame
+//! map.update(0.016);
+//! map.render(&renderer, &texture, &viewport, &mut context)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+use std::ops::Range;
+
+use num_traits::cast::NumCast;
+
+use crate::renderer::InstanceBuffer;
+use crate::{
+    Affine, Color, Context, Point, Position, Rect, Renderer, Result, Size, Sprite, Texture, Transform, UvRotation,
+    Vector, VertexData, Viewport,
+};
+
+// -----------------------------------------------------------------------------
+//     - Tile -
+// -----------------------------------------------------------------------------
+/// A tile in a layer: the index of the tile in the tileset,
+/// and how it is flipped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Tile {
+    /// Index in the tileset, left to right, top to bottom.
+    pub id: u32,
+    /// Mirror horizontally
+    pub flip_x: bool,
+    /// Mirror vertically
+    pub flip_y: bool,
+    /// Swap the x and y axis. This is applied before the other flips.
+    /// Combined with the flips this rotates the tile in steps of 90 degrees.
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    /// A tile that isn't flipped.
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    // Flips as sprite flags. A diagonal flip is the same as
+    // a quarter turn clockwise followed by a horizontal flip.
+    fn apply(&self, sprite: &mut Sprite<f32>) {
+        sprite.flip_x = self.flip_x ^ self.flip_diagonal;
+        sprite.flip_y = self.flip_y;
+        sprite.uv_rotation = match self.flip_diagonal {
+            true => UvRotation::Clockwise90,
+            false => UvRotation::None,
+        };
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Tileset -
+// -----------------------------------------------------------------------------
+/// A texture made up of tiles of the same size.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    texture_size: Size<u32>,
+    tile_size: Size<u32>,
+    spacing: u32,
+    margin: u32,
+    animations: HashMap<u32, Vec<(u32, f32)>>,
+}
+
+impl Tileset {
+    /// Create a tileset for a texture of `texture_size`, in pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the width or the height of a tile is zero.
+    pub fn new(texture_size: Size<u32>, tile_size: Size<u32>) -> Self {
+        assert!(
+            tile_size.width > 0 && tile_size.height > 0,
+            "invalid tile size: {}x{}",
+            tile_size.width,
+            tile_size.height
+        );
+
+        Self {
+            texture_size,
+            tile_size,
+            spacing: 0,
+            margin: 0,
+            animations: HashMap::new(),
+        }
+    }
+
+    /// Pixels between two tiles.
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Pixels between the edge of the texture and the tiles.
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Size of a tile in pixels.
+    pub fn tile_size(&self) -> Size<u32> {
+        self.tile_size
+    }
+
+    /// Number of tiles in a row of the texture.
+    pub fn columns(&self) -> u32 {
+        let width = self.texture_size.width.saturating_sub(self.margin * 2) + self.spacing;
+        (width / (self.tile_size.width + self.spacing)).max(1)
+    }
+
+    /// The area of the texture covered by a tile.
+    pub fn texture_rect(&self, id: u32) -> Rect<u32> {
+        let columns = self.columns();
+        let (column, row) = (id % columns, id / columns);
+        let origin = Point::new(
+            self.margin + column * (self.tile_size.width + self.spacing),
+            self.margin + row * (self.tile_size.height + self.spacing),
+        );
+        Rect::new(origin, self.tile_size)
+    }
+
+    /// Animate a tile. Wherever the tile is placed it cycles through
+    /// the frames: tile ids, each shown for a number of seconds.
+    pub fn add_animation(&mut self, id: u32, frames: Vec<(u32, f32)>) {
+        self.animations.insert(id, frames);
+    }
+
+    // The tile id to show for a tile at a point in time
+    fn frame(&self, id: u32, time: f32) -> u32 {
+        let frames = match self.animations.get(&id) {
+            Some(frames) => frames,
+            None => return id,
+        };
+
+        let duration = frames.iter().map(|(_, d)| d).sum::<f32>();
+        if duration <= 0.0 {
+            return id;
+        }
+
+        let mut time = time % duration;
+        for (frame, frame_duration) in frames {
+            if time < *frame_duration {
+                return *frame;
+            }
+            time -= frame_duration;
+        }
+
+        frames.last().map(|(frame, _)| *frame).unwrap_or(id)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Layer -
+// -----------------------------------------------------------------------------
+struct Chunk {
+    vertex_data: Vec<VertexData>,
+    buffer: Option<InstanceBuffer<VertexData>>,
+    // The vertex data needs to be built again
+    dirty: bool,
+    // The buffer needs to be uploaded again
+    uploaded: bool,
+    // Ids of the animated tiles in the chunk
+    animated: Vec<u32>,
+}
+
+struct Layer {
+    name: String,
+    z_index: i32,
+    visible: bool,
+    tint: Color,
+    tiles: Vec<Option<Tile>>,
+    chunks: Vec<Chunk>,
+}
+
+// -----------------------------------------------------------------------------
+//     - Tile map -
+// -----------------------------------------------------------------------------
+/// Layers of tiles, drawn with a [`Renderer`].
+///
+/// Tile sizes are in the same units as the size of a sprite.
+pub struct TileMap {
+    tileset: Tileset,
+    size: Size<u32>,
+    tile_size: Size<u32>,
+    chunk_size: u32,
+    layers: Vec<Layer>,
+    time: f32,
+    /// Position of the bottom left corner of the map.
+    pub position: Position<f32>,
+}
+
+impl TileMap {
+    /// Create an empty map, `size` tiles wide and high.
+    pub fn new(tileset: Tileset, size: Size<u32>) -> Self {
+        Self {
+            tile_size: tileset.tile_size,
+            tileset,
+            size,
+            chunk_size: 16,
+            layers: Vec::new(),
+            time: 0.0,
+            position: Position::zero(),
+        }
+    }
+
+    /// Number of tiles along each side of a chunk.
+    /// Set this before adding layers.
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Size of a cell of the map, when it differs from the size of the
+    /// tiles in the tileset. Tiles larger than a cell are drawn from the
+    /// bottom left corner of their cell, covering the cells above and to
+    /// the right, the same as in Tiled.
+    pub fn with_tile_size(mut self, tile_size: Size<u32>) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// The tileset.
+    pub fn tileset(&self) -> &Tileset {
+        &self.tileset
+    }
+
+    /// Size in tiles.
+    pub fn size(&self) -> Size<u32> {
+        self.size
+    }
+
+    /// Size of a cell of the map.
+    pub fn tile_size(&self) -> Size<u32> {
+        self.tile_size
+    }
+
+    /// Add an empty layer, returning its index.
+    /// Layers are drawn in the order they were added.
+    pub fn add_layer(&mut self, name: impl Into<String>, z_index: i32) -> usize {
+        let chunks = (0..self.chunk_count()).map(|_| Chunk {
+            vertex_data: Vec::new(),
+            buffer: None,
+            dirty: false,
+            uploaded: true,
+            animated: Vec::new(),
+        });
+
+        self.layers.push(Layer {
+            name: name.into(),
+            z_index,
+            visible: true,
+            tint: Color::white(),
+            tiles: vec![None; (self.size.width * self.size.height) as usize],
+            chunks: chunks.collect(),
+        });
+
+        self.layers.len() - 1
+    }
+
+    /// The index of a layer.
+    pub fn layer(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    /// Show or hide a layer.
+    pub fn set_visible(&mut self, layer: usize, visible: bool) {
+        self.layers[layer].visible = visible;
+    }
+
+    /// Multiply the colour of every tile in a layer,
+    /// e.g. set the alpha to fade the layer out.
+    pub fn set_tint(&mut self, layer: usize, tint: Color) {
+        let layer = &mut self.layers[layer];
+        if layer.tint != tint {
+            layer.tint = tint;
+            layer.chunks.iter_mut().for_each(|chunk| chunk.dirty = true);
+        }
+    }
+
+    /// Set or remove a tile. Tiles outside the map are ignored.
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: Option<Tile>) {
+        if x >= self.size.width || y >= self.size.height {
+            return;
+        }
+
+        let chunk = self.chunk_index(x, y);
+        let index = (y * self.size.width + x) as usize;
+        let layer = &mut self.layers[layer];
+        if layer.tiles[index] != tile {
+            layer.tiles[index] = tile;
+            layer.chunks[chunk].dirty = true;
+        }
+    }
+
+    /// The tile at a position in the map.
+    pub fn tile(&self, layer: usize, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.size.width || y >= self.size.height {
+            return None;
+        }
+        self.layers[layer].tiles[(y * self.size.width + x) as usize]
+    }
+
+    /// The tile coordinates at a position in the world.
+    pub fn tile_at(&self, position: Position<f32>) -> Option<(u32, u32)> {
+        let tile_size = self.tile_size.to_f32();
+        let local = position - self.position;
+        let x = (local.x / tile_size.width).floor();
+        let row = (local.y / tile_size.height).floor();
+        let y = self.size.height as f32 - 1.0 - row;
+
+        let inside = x >= 0.0 && y >= 0.0 && x < self.size.width as f32 && y < self.size.height as f32;
+        match inside {
+            true => Some((x as u32, y as u32)),
+            false => None,
+        }
+    }
+
+    /// Advance animated tiles.
+    /// Only the chunks containing a tile that moved to its next frame are rebuilt.
+    /// A `dt` that is NaN or infinite is ignored, as it would
+    /// freeze every animated tile for good.
+    pub fn update(&mut self, dt: f32) {
+        if !dt.is_finite() {
+            return;
+        }
+
+        let before = self.time;
+        self.time += dt;
+
+        let tileset = &self.tileset;
+        let changed = tileset
+            .animations
+            .keys()
+            .filter(|id| tileset.frame(**id, before) != tileset.frame(**id, self.time))
+            .collect::<Vec<_>>();
+
+        if changed.is_empty() {
+            return;
+        }
+
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| layer.chunks.iter_mut())
+            .filter(|chunk| chunk.animated.iter().any(|id| changed.contains(&id)))
+            .for_each(|chunk| chunk.dirty = true);
+    }
+
+    /// Draw the visible chunks of every visible layer,
+    /// uploading the chunks that changed.
+    pub fn render<U: Copy + NumCast>(
+        &mut self,
+        renderer: &Renderer<VertexData>,
+        texture: &Texture<U>,
+        viewport: &Viewport,
+        context: &mut Context,
+    ) -> Result<()> {
+        let visible = visible_rect(viewport, renderer.pixel_size);
+        let chunks = self.visible_chunks(visible);
+
+        for layer in 0..self.layers.len() {
+            if !self.layers[layer].visible {
+                continue;
+            }
+
+            for &index in &chunks {
+                self.build(layer, index);
+
+                let chunk = &mut self.layers[layer].chunks[index];
+                if chunk.vertex_data.is_empty() {
+                    continue;
+                }
+
+                let buffer = chunk.buffer.get_or_insert_with(InstanceBuffer::new);
+                if !chunk.uploaded {
+                    buffer.load(&chunk.vertex_data);
+                    chunk.uploaded = true;
+                }
+
+                renderer.render_buffer(texture, buffer, viewport, context)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Indices of the chunks overlapping an area of the world.
+    pub fn visible_chunks(&self, area: Rect<f32>) -> Vec<usize> {
+        (0..self.chunk_count())
+            .filter(|&index| self.chunk_rect(index).intersects(&area))
+            .collect()
+    }
+
+    /// The vertex data of a chunk in a layer, built if the chunk changed.
+    pub fn chunk_vertex_data(&mut self, layer: usize, chunk: usize) -> &[VertexData] {
+        self.build(layer, chunk);
+        &self.layers[layer].chunks[chunk].vertex_data
+    }
+
+    fn chunk_columns(&self) -> u32 {
+        self.size.width.div_ceil(self.chunk_size)
+    }
+
+    fn chunk_count(&self) -> usize {
+        let rows = self.size.height.div_ceil(self.chunk_size);
+        (self.chunk_columns() * rows) as usize
+    }
+
+    fn chunk_index(&self, x: u32, y: u32) -> usize {
+        ((y / self.chunk_size) * self.chunk_columns() + x / self.chunk_size) as usize
+    }
+
+    // Tiles covered by a chunk, as ranges of columns and rows
+    fn chunk_tiles(&self, index: usize) -> (Range<u32>, Range<u32>) {
+        let columns = self.chunk_columns();
+        let (cx, cy) = (index as u32 % columns, index as u32 / columns);
+        let x = cx * self.chunk_size..((cx + 1) * self.chunk_size).min(self.size.width);
+        let y = cy * self.chunk_size..((cy + 1) * self.chunk_size).min(self.size.height);
+        (x, y)
+    }
+
+    // Includes the parts of tiles larger than a cell that stick out
+    // above and to the right of the chunk
+    fn chunk_rect(&self, index: usize) -> Rect<f32> {
+        let (x, y) = self.chunk_tiles(index);
+        let tile_size = self.tile_size.to_f32();
+        let overhang = (self.tileset.tile_size.to_f32() - tile_size).max(Size::zero());
+        let origin = Point::new(
+            x.start as f32 * tile_size.width,
+            (self.size.height - y.end) as f32 * tile_size.height,
+        ) + self.position;
+        let size = Size::new(x.len() as f32 * tile_size.width, y.len() as f32 * tile_size.height) + overhang;
+        Rect::new(origin, size)
+    }
+
+    fn build(&mut self, layer_index: usize, chunk_index: usize) {
+        if !self.layers[layer_index].chunks[chunk_index].dirty {
+            return;
+        }
+
+        let (columns, rows) = self.chunk_tiles(chunk_index);
+        let tile_size = self.tile_size.to_f32();
+        let texture_size = self.tileset.texture_size.to_f32();
+        let layer = &self.layers[layer_index];
+
+        let mut vertex_data = Vec::new();
+        let mut animated = Vec::new();
+
+        for y in rows {
+            for x in columns.clone() {
+                let tile = match layer.tiles[(y * self.size.width + x) as usize] {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+
+                if self.tileset.animations.contains_key(&tile.id) && !animated.contains(&tile.id) {
+                    animated.push(tile.id);
+                }
+                let id = self.tileset.frame(tile.id, self.time);
+
+                let mut sprite = Sprite::from_size(texture_size);
+                sprite.texture_rect = self.tileset.texture_rect(id).to_f32();
+                sprite.size = self.tileset.tile_size.to_f32();
+                sprite.z_index = layer.z_index;
+                sprite.tint = layer.tint;
+                tile.apply(&mut sprite);
+
+                let position = Vector::new(
+                    x as f32 * tile_size.width,
+                    (self.size.height - 1 - y) as f32 * tile_size.height,
+                ) + self.position;
+
+                vertex_data.push(VertexData::new(&sprite, &Transform::new(position)));
+            }
+        }
+
+        let chunk = &mut self.layers[layer_index].chunks[chunk_index];
+        chunk.vertex_data = vertex_data;
+        chunk.animated = animated;
+        chunk.dirty = false;
+        chunk.uploaded = false;
+    }
+}
+
+// The area of the world visible in the viewport, taking the view
+// (e.g. from a camera) and the pixel size into account.
+fn visible_rect(viewport: &Viewport, pixel_size: i32) -> Rect<f32> {
+    let size = viewport.size().to_f32();
+    let inverse = Affine::from(*viewport.view()).inverse().unwrap_or_default();
+    let corners = [
+        Point::zero(),
+        Point::new(size.width, 0.0),
+        Point::new(0.0, size.height),
+        Point::new(size.width, size.height),
+    ]
+    .map(|p| (inverse.transform_point(p).to_vector() / pixel_size as f32).to_point());
+
+    Rect::from_points(corners.iter())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn map() -> TileMap {
+        let tileset = Tileset::new(Size::new(64, 32), Size::new(8, 8))
+            .with_margin(1)
+            .with_spacing(2);
+        TileMap::new(tileset, Size::new(40, 20)).with_chunk_size(16)
+    }
+
+    fn position(vd: &VertexData) -> (f32, f32) {
+        (vd.model[(0, 3)], vd.model[(1, 3)])
+    }
+
+    #[test]
+    fn tileset_with_spacing_and_margin() {
+        let tileset = map().tileset;
+        // (64 - 2 + 2) / 10
+        assert_eq!(tileset.columns(), 6);
+        assert_eq!(tileset.texture_rect(0), Rect::new(Point::new(1, 1), Size::new(8, 8)));
+        assert_eq!(tileset.texture_rect(7), Rect::new(Point::new(11, 11), Size::new(8, 8)));
+    }
+
+    #[test]
+    fn tiles_are_placed_from_the_top() {
+        let mut map = map();
+        let layer = map.add_layer("ground", 10);
+        map.set_tile(layer, 0, 0, Some(Tile::new(1)));
+        map.set_tile(layer, 17, 19, Some(Tile::new(1)));
+
+        let top_left = map.chunk_vertex_data(layer, 0);
+        assert_eq!(top_left.len(), 1);
+        assert_eq!(position(&top_left[0]), (0.0, 19.0 * 8.0));
+        assert_eq!(top_left[0].model[(2, 3)], 10.0);
+
+        // 3 chunks per row, the second row holds rows 16 to 19
+        let bottom = map.chunk_vertex_data(layer, 4);
+        assert_eq!(position(&bottom[0]), (17.0 * 8.0, 0.0));
+
+        assert_eq!(map.tile_at(Position::new(17.0 * 8.0 + 1.0, 1.0)), Some((17, 19)));
+        assert_eq!(map.tile_at(Position::new(-1.0, 1.0)), None);
+    }
+
+    #[test]
+    fn tiles_larger_than_a_cell() {
+        let tileset = Tileset::new(Size::new(64, 64), Size::new(16, 32));
+        let mut map = TileMap::new(tileset, Size::new(4, 4)).with_tile_size(Size::new(8, 8)).with_chunk_size(2);
+        let layer = map.add_layer("trees", 10);
+        map.set_tile(layer, 1, 3, Some(Tile::new(0)));
+
+        // Placed on the grid of the map, drawn at the size of the tileset
+        let vd = map.chunk_vertex_data(layer, 2)[0];
+        assert_eq!(position(&vd), (8.0, 0.0));
+        assert_eq!((vd.model[(0, 0)], vd.model[(1, 1)]), (16.0, 32.0));
+        assert_eq!(map.tile_at(Position::new(9.0, 7.0)), Some((1, 3)));
+
+        // The tree sticks out of the bottom left chunk into the chunks above it
+        assert_eq!(map.chunk_rect(2), Rect::new(Point::zero(), Size::new(24.0, 40.0)));
+        assert_eq!(map.visible_chunks(Rect::new(Point::new(1.0, 30.0), Size::new(1.0, 1.0))), vec![0, 2]);
+    }
+
+    #[test]
+    fn layer_tint() {
+        let mut map = map();
+        let layer = map.add_layer("ground", 10);
+        map.set_tile(layer, 0, 0, Some(Tile::new(1)));
+        assert_eq!(map.chunk_vertex_data(layer, 0)[0].tint, Color::white());
+
+        map.set_tint(layer, Color::white().with_alpha(0.5));
+        assert!(map.layers[layer].chunks[0].dirty);
+        assert_eq!(map.chunk_vertex_data(layer, 0)[0].tint.a, 0.5);
+    }
+
+    #[test]
+    fn chunks_rebuild_only_when_changed() {
+        let mut map = map();
+        let layer = map.add_layer("ground", 10);
+        map.set_tile(layer, 1, 1, Some(Tile::new(1)));
+        map.chunk_vertex_data(layer, 0);
+        assert!(!map.layers[layer].chunks[0].dirty);
+
+        // Setting the same tile again changes nothing
+        map.set_tile(layer, 1, 1, Some(Tile::new(1)));
+        assert!(!map.layers[layer].chunks[0].dirty);
+
+        map.set_tile(layer, 1, 1, None);
+        assert!(map.layers[layer].chunks[0].dirty);
+        assert!(!map.layers[layer].chunks[1].dirty);
+        assert!(map.chunk_vertex_data(layer, 0).is_empty());
+    }
+
+    #[test]
+    fn culling() {
+        let map = map();
+        // Each chunk is 128x128 and the map is 320x160, so the bottom
+        // row of chunks is only 32 high, and the top row starts at y = 32
+        let visible = map.visible_chunks(Rect::new(Point::new(0.0, 0.0), Size::new(100.0, 20.0)));
+        assert_eq!(visible, vec![3]);
+
+        let visible = map.visible_chunks(Rect::new(Point::new(120.0, 20.0), Size::new(20.0, 20.0)));
+        assert_eq!(visible, vec![0, 1, 3, 4]);
+
+        let mut viewport = Viewport::new(Position::zero(), Size::new(100, 100));
+        viewport.set_view(Affine(nalgebra::Matrix3::new_translation(&nalgebra::Vector2::new(-300.0, -200.0))).into());
+        let visible = visible_rect(&viewport, 2);
+        assert_eq!(visible, Rect::new(Point::new(150.0, 100.0), Size::new(50.0, 50.0)));
+    }
+
+    #[test]
+    fn flips() {
+        let mut map = map();
+        let layer = map.add_layer("ground", 10);
+        let tile = Tile {
+            id: 0,
+            flip_x: true,
+            flip_y: false,
+            flip_diagonal: true,
+        };
+        map.set_tile(layer, 0, 0, Some(tile));
+
+        // Diagonal then horizontal: a quarter turn clockwise
+        let vd = map.chunk_vertex_data(layer, 0)[0];
+        let mut sprite = Sprite::<f32>::from_size(Size::new(8.0, 8.0));
+        sprite.uv_rotation = UvRotation::Clockwise90;
+        let expected = VertexData::new(&sprite, &Transform::default());
+        assert_eq!(vd.uv_transform, expected.uv_transform);
+    }
+
+    #[test]
+    fn animated_tiles() {
+        let mut map = map();
+        map.tileset.add_animation(2, vec![(2, 0.5), (3, 0.25)]);
+        let layer = map.add_layer("water", 10);
+        map.set_tile(layer, 20, 0, Some(Tile::new(2)));
+        map.set_tile(layer, 0, 0, Some(Tile::new(1)));
+
+        let texture_x = |map: &mut TileMap, chunk| map.chunk_vertex_data(layer, chunk)[0].texture_position.0;
+        assert_eq!(texture_x(&mut map, 1), 21.0 / 64.0);
+        assert_eq!(texture_x(&mut map, 0), 11.0 / 64.0);
+
+        map.update(0.25);
+        assert!(!map.layers[layer].chunks[1].dirty);
+
+        map.update(0.25);
+        assert!(map.layers[layer].chunks[1].dirty);
+        // Chunks without animated tiles are left alone
+        assert!(!map.layers[layer].chunks[0].dirty);
+        assert_eq!(texture_x(&mut map, 1), 31.0 / 64.0);
+
+        // Loops back to the first frame
+        map.update(0.25);
+        assert_eq!(texture_x(&mut map, 1), 21.0 / 64.0);
+
+        // A non-finite dt doesn't stop the animation
+        map.update(f32::NAN);
+        map.update(f32::INFINITY);
+        map.update(0.5);
+        assert_eq!(texture_x(&mut map, 1), 31.0 / 64.0);
+    }
+
+    #[test]
+    fn only_chunks_with_a_changed_animation_rebuild() {
+        let mut map = map();
+        map.tileset.add_animation(2, vec![(2, 0.5), (3, 0.5)]);
+        map.tileset.add_animation(4, vec![(4, 0.25), (5, 0.25)]);
+        let layer = map.add_layer("water", 10);
+        map.set_tile(layer, 0, 0, Some(Tile::new(2)));
+        map.set_tile(layer, 20, 0, Some(Tile::new(4)));
+        map.chunk_vertex_data(layer, 0);
+        map.chunk_vertex_data(layer, 1);
+
+        // Only tile 4 changes frame
+        map.update(0.25);
+        assert!(!map.layers[layer].chunks[0].dirty);
+        assert!(map.layers[layer].chunks[1].dirty);
+        map.chunk_vertex_data(layer, 1);
+
+        // Both change frame
+        map.update(0.25);
+        assert!(map.layers[layer].chunks[0].dirty);
+        assert!(map.layers[layer].chunks[1].dirty);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_tile_size() {
+        Tileset::new(Size::new(64, 64), Size::new(0, 8));
+    }
+}
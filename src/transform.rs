@@ -1,14 +1,130 @@
-// #![deny(missing_docs)]
-use std::ops::{MulAssign, Div};
+#![deny(missing_docs)]
+use std::ops::{Div, Mul, MulAssign};
 
-use crate::{Position, Rotation, Vector};
-use nalgebra::{Matrix4, Scalar, Vector as NalVector};
+use crate::{Point, Position, Rotation, Vector};
+use nalgebra::{Matrix3, Matrix4, Scalar, Vector as NalVector};
 use num_traits::{One, Zero, NumCast};
 
+// -----------------------------------------------------------------------------
+//     - Affine -
+// -----------------------------------------------------------------------------
+/// A 2D affine transformation (translation, rotation, scale and shear)
+/// as a 3x3 matrix.
+///
+/// Converts to and from a `Matrix4<f32>` as used by [`crate::VertexData`],
+/// ignoring the z axis.
+///
+/// ```
+/// use nightmaregl::{Affine, Point, Position, Transform};
+///
+/// let transform = Transform::new(Position::new(10.0, 0.0));
+/// let affine = transform.affine();
+/// assert_eq!(affine.transform_point(Point::new(1.0, 1.0)), Point::new(11.0, 1.0));
+///
+/// let back = affine.inverse().unwrap();
+/// assert_eq!(back.transform_point(Point::new(11.0, 1.0)), Point::new(1.0, 1.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine(pub Matrix3<f32>);
+
+impl Affine {
+    /// No transformation.
+    pub fn identity() -> Self {
+        Self(Matrix3::identity())
+    }
+
+    /// The transformation undoing this one, or `None`
+    /// if it can't be undone (e.g. a scale of zero).
+    pub fn inverse(&self) -> Option<Self> {
+        self.0.try_inverse().map(Self)
+    }
+
+    /// Apply `self`, then `other`.
+    pub fn then(&self, other: &Affine) -> Self {
+        Self(other.0 * self.0)
+    }
+
+    /// Transform a point.
+    pub fn transform_point(&self, point: Point<f32>) -> Point<f32> {
+        let m = &self.0;
+        Point::new(
+            m[(0, 0)] * point.x + m[(0, 1)] * point.y + m[(0, 2)],
+            m[(1, 0)] * point.x + m[(1, 1)] * point.y + m[(1, 2)],
+        )
+    }
+
+    /// Transform a vector (a direction), ignoring the translation.
+    pub fn transform_vector(&self, vector: Vector<f32>) -> Vector<f32> {
+        let m = &self.0;
+        Vector::new(
+            m[(0, 0)] * vector.x + m[(0, 1)] * vector.y,
+            m[(1, 0)] * vector.x + m[(1, 1)] * vector.y,
+        )
+    }
+
+    /// The translation.
+    pub fn translation(&self) -> Vector<f32> {
+        Vector::new(self.0[(0, 2)], self.0[(1, 2)])
+    }
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// `a * b` applies `b` first, same as matrix multiplication.
+impl Mul for Affine {
+    type Output = Affine;
+
+    fn mul(self, rhs: Affine) -> Self::Output {
+        Affine(self.0 * rhs.0)
+    }
+}
+
+impl From<Matrix4<f32>> for Affine {
+    fn from(m: Matrix4<f32>) -> Self {
+        Self(Matrix3::new(
+            m[(0, 0)], m[(0, 1)], m[(0, 3)],
+            m[(1, 0)], m[(1, 1)], m[(1, 3)],
+            0.0, 0.0, 1.0,
+        ))
+    }
+}
+
+impl From<Affine> for Matrix4<f32> {
+    fn from(a: Affine) -> Self {
+        let m = a.0;
+        Matrix4::new(
+            m[(0, 0)], m[(0, 1)], 0.0, m[(0, 2)],
+            m[(1, 0)], m[(1, 1)], 0.0, m[(1, 2)],
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        )
+    }
+}
+
+impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output = T>> From<&Transform<T>> for Affine {
+    fn from(transform: &Transform<T>) -> Self {
+        transform.affine()
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Transform -
+// -----------------------------------------------------------------------------
+/// Translation, rotation and scale.
+///
+/// Points are scaled first, then rotated around the origin,
+/// then translated.
 #[derive(Debug, Copy, Clone)]
 pub struct Transform<T> {
+    /// Translation
     pub translation: Position<T>,
+    /// Scale along the x and y axis
     pub scale: Vector<T>,
+    /// Rotation
     pub rotation: Rotation<T>,
 }
 
@@ -23,6 +139,7 @@ impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output
 }
 
 impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output = T>> Transform<T> {
+    /// Create a new transform with a translation, no rotation and a scale of one.
     pub fn new(translation: Position<T>) -> Self {
         Self {
             translation,
@@ -31,6 +148,37 @@ impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output
         }
     }
 
+    /// Split an affine transformation into translation, rotation and scale.
+    ///
+    /// Shearing can't be represented by a `Transform` and is lost.
+    /// Shearing happens when a rotated transform is placed inside
+    /// a transform with a non-uniform scale.
+    pub fn from_affine(affine: &Affine) -> Self {
+        let m = &affine.0;
+        let (a, b) = (m[(0, 0)], m[(1, 0)]);
+        let (c, d) = (m[(0, 1)], m[(1, 1)]);
+
+        let scale_x = (a * a + b * b).sqrt();
+        let scale_y = match scale_x > 0.0 {
+            true => (a * d - b * c) / scale_x,
+            false => (c * c + d * d).sqrt(),
+        };
+
+        let translation = affine.translation();
+
+        Self {
+            translation: Position::new(cast(translation.x), cast(translation.y)),
+            scale: Vector::new(cast(scale_x), cast(scale_y)),
+            rotation: Rotation::radians(cast(b.atan2(a))),
+        }
+    }
+
+    /// A copy of `other`, with the rotation of this transform added.
+    ///
+    /// This does not compose the transforms: the translation and scale
+    /// of this transform are ignored. Use [`Transform::compose`] to place
+    /// one transform inside another, or [`Transform::rotated`] to rotate.
+    #[deprecated(note = "use Transform::compose / rotated / translated")]
     pub fn rotate(&self, other: Transform<T>) -> Transform<T> {
         Transform {
             rotation: self.rotation + other.rotation,
@@ -38,10 +186,25 @@ impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output
         }
     }
 
+    /// A copy of the transform, rotated by `rotation`.
+    pub fn rotated(&self, rotation: Rotation<T>) -> Transform<T> {
+        Transform {
+            rotation: self.rotation + rotation,
+            ..*self
+        }
+    }
+
+    /// Set the rotation.
     pub fn rotate_mut(&mut self, rot: Rotation<T>) {
         self.rotation = rot;
     }
 
+    /// A copy of `other`, with the translation of this transform added.
+    ///
+    /// This does not compose the transforms: the rotation and scale
+    /// of this transform are ignored. Use [`Transform::compose`] to place
+    /// one transform inside another, or [`Transform::translated`] to move.
+    #[deprecated(note = "use Transform::compose / rotated / translated")]
     pub fn translate(&self, other: Transform<T>) -> Transform<T> {
         Transform {
             translation: self.translation + other.translation,
@@ -49,18 +212,107 @@ impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output
         }
     }
 
+    /// A copy of the transform, moved by `offset`.
+    pub fn translated(&self, offset: Position<T>) -> Transform<T> {
+        Transform {
+            translation: self.translation + offset,
+            ..*self
+        }
+    }
+
+    /// Set the translation.
     pub fn translate_mut(&mut self, translation: Position<T>) {
         self.translation = translation;
     }
 
+    /// Set the scale.
     pub fn scale_mut(&mut self, scale: Vector<T>) {
         self.scale = scale;
     }
 
+    /// Place `child` inside this transform: the result transforms
+    /// from the space of the child to the space this transform is in.
+    ///
+    /// ```
+    /// use nightmaregl::{Point, Position, Rotation, Transform, Vector};
+    ///
+    /// let mut parent = Transform::new(Position::new(10.0, 0.0));
+    /// parent.scale = Vector::new(2.0, 1.0);
+    /// let child = Transform::new(Position::new(5.0, 5.0));
+    ///
+    /// let world = parent.compose(&child);
+    /// assert_eq!(world.translation, Position::new(20.0, 5.0));
+    /// assert_eq!(world.scale, Vector::new(2.0, 1.0));
+    /// ```
+    ///
+    /// As with [`Transform::from_affine`], shearing is lost. Use
+    /// `parent.affine() * child.affine()` to keep it.
+    pub fn compose(&self, child: &Transform<T>) -> Transform<T> {
+        Self::from_affine(&(self.affine() * child.affine()))
+    }
+
+    /// The transform undoing this one, or `None` if it
+    /// can't be undone (a scale of zero), same as [`Affine::inverse`].
+    ///
+    /// This is exact when the scale is uniform; otherwise a rotated
+    /// transform can't be undone without shearing. Use
+    /// `transform.affine().inverse()` or [`Transform::inverse_transform_point`] for that.
+    pub fn inverse(&self) -> Option<Transform<T>> {
+        self.affine().inverse().map(|inverse| Self::from_affine(&inverse))
+    }
+
+    /// Transform a point from the local space to the space of the transform.
+    pub fn transform_point(&self, point: Point<f32>) -> Point<f32> {
+        self.affine().transform_point(point)
+    }
+
+    /// Transform a point from the space of the transform to the local space,
+    /// e.g. to find out where the mouse is relative to a sprite.
+    ///
+    /// ```
+    /// use nightmaregl::{Point, Position, Rotation, Transform};
+    ///
+    /// let mut transform = Transform::new(Position::new(100.0, 100.0));
+    /// transform.rotation = Rotation::degrees(90.0);
+    ///
+    /// let local = transform.inverse_transform_point(Point::new(100.0, 110.0)).unwrap();
+    /// assert!((local - Point::new(10.0, 0.0)).length() < 1e-4);
+    /// ```
+    ///
+    /// Returns `None` if the transform can't be undone (a scale of zero),
+    /// as no point in the local space ends up at `point`, or they all do.
+    pub fn inverse_transform_point(&self, point: Point<f32>) -> Option<Point<f32>> {
+        self.affine().inverse().map(|inverse| inverse.transform_point(point))
+    }
+
+    /// Rotate so the x axis points at `target`.
+    pub fn look_at(&mut self, target: Position<T>) {
+        let dir = target.to_f32() - self.translation.to_f32();
+        if dir.x != 0.0 || dir.y != 0.0 {
+            self.rotation = Rotation::radians(cast(dir.y.atan2(dir.x)));
+        }
+    }
+
+    /// Combine the matrix of two transforms.
     pub fn transform(&self, other: &Transform<T>) -> Matrix4<f32> {
         self.matrix() * other.matrix()
     }
 
+    /// The transform as a 2D affine matrix.
+    pub fn affine(&self) -> Affine {
+        let position = self.translation.to_f32();
+        let rotation = self.rotation.to_f32().radians;
+        let scale = self.scale.to_f32();
+        let (sin, cos) = rotation.sin_cos();
+
+        Affine(Matrix3::new(
+            cos * scale.x, -sin * scale.y, position.x,
+            sin * scale.x, cos * scale.y, position.y,
+            0.0, 0.0, 1.0,
+        ))
+    }
+
+    /// The transform as a matrix, with no translation along the z axis.
     pub fn matrix(&self) -> Matrix4<f32> {
         let position = self.translation.to_f32();
         let rotation = self.rotation.to_f32();
@@ -71,13 +323,141 @@ impl<T: Copy + NumCast + Zero + One + MulAssign + Default + Scalar + Div<Output
         Matrix4::new_translation(&NalVector::from([
             position.x,
             position.y,
-            1.0
+            0.0
         ])) * Matrix4::new_rotation(rotation)
             * Matrix4::new_nonuniform_scaling(&NalVector::from([
                 scale.x,
-                scale.y, 
+                scale.y,
                 1.0
             ]))
     }
 
 }
+
+// Integer transforms round to the nearest value
+fn cast<T: NumCast + Zero>(value: f32) -> T {
+    let is_integer = T::from(0.5f32).map(|half: T| half.is_zero()).unwrap_or(false);
+    let value = match is_integer {
+        true => value.round(),
+        false => value,
+    };
+    T::from(value).unwrap_or_else(T::zero)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close(a: Point<f32>, b: Point<f32>) -> bool {
+        (a - b).length() < 1e-4
+    }
+
+    fn transform(x: f32, y: f32, rotation: f32, sx: f32, sy: f32) -> Transform<f32> {
+        let mut t = Transform::new(Position::new(x, y));
+        t.rotation = Rotation::radians(rotation);
+        t.scale = Vector::new(sx, sy);
+        t
+    }
+
+    #[test]
+    fn affine_matches_matrix() {
+        let t = transform(3.0, -2.0, 0.7, 2.0, 0.5);
+        let from_matrix = Affine::from(t.matrix());
+        assert!((from_matrix.0 - t.affine().0).abs().max() < 1e-5);
+        assert!((Matrix4::from(t.affine()) - t.matrix()).abs().max() < 1e-5);
+    }
+
+    #[test]
+    fn matrix_has_no_z_translation() {
+        // This used to be 1.0, which added up when composing transforms
+        let t = transform(3.0, -2.0, 0.7, 2.0, 0.5);
+        assert_eq!(t.matrix()[(2, 3)], 0.0);
+        assert_eq!(t.compose(&t).matrix()[(2, 3)], 0.0);
+        assert_eq!((t.matrix() * t.matrix())[(2, 3)], 0.0);
+    }
+
+    #[test]
+    fn compose_with_non_uniform_scale() {
+        let parent = transform(10.0, 0.0, FRAC_PI_2, 2.0, 3.0);
+        let child = transform(1.0, 1.0, 0.0, 0.5, 1.0);
+
+        let world = parent.compose(&child);
+        let point = Point::new(2.0, 1.0);
+        let expected = parent.transform_point(child.transform_point(point));
+        assert!(close(world.transform_point(point), expected));
+    }
+
+    #[test]
+    fn inverse() {
+        let t = transform(5.0, 7.0, 1.2, 2.0, 2.0);
+        let point = Point::new(-3.0, 4.0);
+
+        assert!(close(t.inverse().unwrap().transform_point(t.transform_point(point)), point));
+
+        let t = transform(5.0, 7.0, 1.2, 2.0, 0.25);
+        assert!(close(t.inverse_transform_point(t.transform_point(point)).unwrap(), point));
+    }
+
+    #[test]
+    fn singular_inverse() {
+        let t = transform(5.0, 7.0, 1.2, 0.0, 2.0);
+        assert!(t.inverse().is_none());
+        assert!(t.inverse_transform_point(Point::new(5.0, 7.0)).is_none());
+    }
+
+    #[test]
+    fn look_at() {
+        let mut t = Transform::new(Position::new(1.0, 1.0));
+        t.look_at(Position::new(1.0, 5.0));
+        assert!((t.rotation.radians - FRAC_PI_2).abs() < 1e-5);
+        assert!(close(t.transform_point(Point::new(1.0, 0.0)), Point::new(1.0, 2.0)));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn rotate_and_translate() {
+        let t = transform(1.0, 2.0, 0.5, 2.0, 2.0);
+        let moved = t.translated(Position::new(1.0, 1.0)).rotated(Rotation::radians(0.5));
+        assert_eq!(moved.translation, Position::new(2.0, 3.0));
+        assert_eq!(moved.rotation, Rotation::radians(1.0));
+        assert_eq!(moved.scale, t.scale);
+
+        let other = transform(4.0, 4.0, 0.25, 3.0, 3.0);
+        assert_eq!(t.translate(other).translation, Position::new(5.0, 6.0));
+        assert_eq!(t.rotate(other).rotation, Rotation::radians(0.75));
+        assert_eq!(t.rotate(other).scale, other.scale);
+    }
+
+    #[test]
+    fn shear_is_lost() {
+        // A rotated child inside a non-uniformly scaled parent is sheared
+        let parent = transform(0.0, 0.0, 0.0, 2.0, 1.0);
+        let child = transform(0.0, 0.0, FRAC_PI_2 / 2.0, 1.0, 1.0);
+        let exact = parent.affine() * child.affine();
+        let world = parent.compose(&child);
+
+        // The x axis of the child and the area are kept, the y axis is not
+        let x_axis = Point::new(1.0, 0.0);
+        let y_axis = Point::new(0.0, 1.0);
+        assert!(close(world.transform_point(x_axis), exact.transform_point(x_axis)));
+        assert!((world.affine().0.determinant() - exact.0.determinant()).abs() < 1e-5);
+        assert!(close(exact.transform_point(y_axis), Point::new(-2.0f32.sqrt(), 0.5f32.sqrt())));
+        assert!(close(world.transform_point(y_axis), Point::new(-0.4 * 2.0f32.sqrt(), 0.8 * 2.0f32.sqrt())));
+
+        // The inverse of a rotated, non-uniformly scaled transform is sheared,
+        // so only its translation is exact
+        let t = transform(5.0, 7.0, 1.2, 2.0, 0.25);
+        let exact = t.affine().inverse().unwrap();
+        let point = Point::new(-3.0, 4.0);
+        let inverse = t.inverse().unwrap();
+        assert!(close(inverse.transform_point(Point::zero()), exact.transform_point(Point::zero())));
+        assert!(!close(inverse.transform_point(t.transform_point(point)), point));
+    }
+
+    #[test]
+    fn integer_transform() {
+        let t = Transform::<i32>::from_affine(&transform(2.6, -1.4, 0.0, 1.0, 1.0).affine());
+        assert_eq!(t.translation, Position::new(3, -1));
+    }
+}
@@ -0,0 +1,219 @@
+use std::f32::consts::PI;
+
+/// Easing curves, mapping the progress of a tween (0.0 to 1.0)
+/// to how far the value has moved from start to end.
+///
+/// All curves start at 0.0 and end at 1.0, but `Back` and `Elastic`
+/// go outside that range in between.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear,
+    /// Quadratic, slow start
+    QuadIn,
+    /// Quadratic, slow end
+    QuadOut,
+    /// Quadratic, slow start and end
+    QuadInOut,
+    /// Cubic, slow start
+    CubicIn,
+    /// Cubic, slow end
+    CubicOut,
+    /// Cubic, slow start and end
+    CubicInOut,
+    /// Wind up before moving
+    BackIn,
+    /// Overshoot the end and come back
+    BackOut,
+    /// Wind up and overshoot
+    BackInOut,
+    /// Wobble at the start
+    ElasticIn,
+    /// Wobble at the end
+    ElasticOut,
+    /// Wobble at the start and end
+    ElasticInOut,
+    /// Bounce at the start
+    BounceIn,
+    /// Bounce at the end, like a dropped ball
+    BounceOut,
+    /// Bounce at the start and end
+    BounceInOut,
+    /// A cubic bezier curve from (0, 0) to (1, 1) with the control
+    /// points (x1, y1) and (x2, y2), same as `cubic-bezier` in css.
+    /// The x values are clamped to 0.0 to 1.0.
+    Bezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// Apply the curve to `t`.
+    /// `t` is clamped to 0.0 to 1.0.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut if t < 0.5 => 2.0 * t * t,
+            Easing::QuadInOut => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0,
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::CubicInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::BackIn => back_in(t),
+            Easing::BackOut => 1.0 - back_in(1.0 - t),
+            Easing::BackInOut => {
+                let c = BACK * 1.525;
+                match t < 0.5 {
+                    true => (2.0 * t).powi(2) * ((c + 1.0) * 2.0 * t - c) / 2.0,
+                    false => ((2.0 * t - 2.0).powi(2) * ((c + 1.0) * (t * 2.0 - 2.0) + c) + 2.0) / 2.0,
+                }
+            }
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => 1.0 - elastic_in(1.0 - t),
+            Easing::ElasticInOut if t < 0.5 => elastic_in(2.0 * t) / 2.0,
+            Easing::ElasticInOut => 1.0 - elastic_in(2.0 - 2.0 * t) / 2.0,
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut if t < 0.5 => (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0,
+            Easing::BounceInOut => (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0,
+            Easing::Bezier(x1, y1, x2, y2) => bezier(t, x1, y1, x2, y2),
+        }
+    }
+}
+
+const BACK: f32 = 1.70158;
+
+fn back_in(t: f32) -> f32 {
+    (BACK + 1.0) * t * t * t - BACK * t * t
+}
+
+fn elastic_in(t: f32) -> f32 {
+    match t {
+        t if t <= 0.0 => 0.0,
+        t if t >= 1.0 => 1.0,
+        t => -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin(),
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+// Find `s` where x(s) = t, then return y(s).
+fn bezier(t: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    let x1 = x1.clamp(0.0, 1.0);
+    let x2 = x2.clamp(0.0, 1.0);
+
+    // One dimension of a cubic bezier from 0 to 1
+    let curve = |s: f32, p1: f32, p2: f32| {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+    };
+
+    // x(s) is increasing as the x values are within 0 to 1, so bisect
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    let mut s = t;
+    for _ in 0..32 {
+        let x = curve(s, x1, x2);
+        if (x - t).abs() < 1e-6 {
+            break;
+        }
+        match x < t {
+            true => low = s,
+            false => high = s,
+        }
+        s = (low + high) / 2.0;
+    }
+
+    curve(s, y1, y2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ALL: [Easing; 17] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+        Easing::Bezier(0.25, 0.1, 0.25, 1.0),
+    ];
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn curves_start_at_zero_and_end_at_one() {
+        for easing in ALL.iter() {
+            assert!(close(easing.apply(0.0), 0.0), "{:?}", easing);
+            assert!(close(easing.apply(1.0), 1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn in_out_curves_are_symmetric() {
+        let pairs = [
+            (Easing::QuadIn, Easing::QuadOut),
+            (Easing::CubicIn, Easing::CubicOut),
+            (Easing::BackIn, Easing::BackOut),
+            (Easing::ElasticIn, Easing::ElasticOut),
+            (Easing::BounceIn, Easing::BounceOut),
+        ];
+
+        for (ease_in, ease_out) in pairs.iter() {
+            for i in 0..=10 {
+                let t = i as f32 / 10.0;
+                assert!(close(ease_in.apply(t), 1.0 - ease_out.apply(1.0 - t)), "{:?}", ease_in);
+            }
+        }
+
+        assert!(close(Easing::QuadInOut.apply(0.5), 0.5));
+        assert!(close(Easing::BounceInOut.apply(0.5), 0.5));
+    }
+
+    #[test]
+    fn linear_bezier() {
+        let easing = Easing::Bezier(0.0, 0.0, 1.0, 1.0);
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!(close(easing.apply(t), t));
+        }
+    }
+
+    #[test]
+    fn back_overshoots() {
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+    }
+}
@@ -0,0 +1,517 @@
+#![deny(missing_docs)]
+//! # Tweening
+//!
+//! Interpolate values over time using easing curves.
+//!
+//! A [`Tween`] moves one value of a target (e.g. the translation of a
+//! [`Transform`]) from a start value to an end value. Tweens are combined
+//! into [`Sequence`]s, [`Parallel`] groups and [`Repeat`]s, and played
+//! with a [`TweenPlayer`].
+//!
+//! Everything here is a pure function of time: sampling a tween at a
+//! given time always gives the same result, no matter how the time
+//! was split up between calls to [`TweenPlayer::update`].
+//!
+//! ```
+//! use nightmaregl::tween::{Easing, Tween, TweenPlayer, Tweenable};
+//! use nightmaregl::{Position, Transform};
+//!
+//! let slide = Tween::new(
+//!     Position::new(0.0, 0.0),
+//!     Position::new(100.0, 0.0),
+//!     1.0,
+//!     |t: &mut Transform<f32>, v| t.translation = v,
+//! )
+//! .easing(Easing::QuadOut);
+//!
+//! // Slide in and out, twice
+//! let mut player = TweenPlayer::new(slide.yoyo(4));
+//! let mut transform = Transform::default();
+//!
+//! player.update(0.5, &mut transform);
+//! assert_eq!(transform.translation.x, 75.0);
+//!
+//! player.update(1.0, &mut transform);
+//! assert_eq!(transform.translation.x, 75.0);
+//! assert!(!player.is_finished());
+//! ```
+use crate::{Color, Rotation, Size, Transform, Vector};
+
+mod easing;
+
+pub use easing::Easing;
+
+// -----------------------------------------------------------------------------
+//     - Lerp -
+// -----------------------------------------------------------------------------
+/// Linear interpolation between two values.
+pub trait Lerp: Copy {
+    /// Interpolate from `self` to `to`, where `t` is 0.0 at `self` and 1.0 at `to`.
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vector<f32> {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vector::new(self.x.lerp(to.x, t), self.y.lerp(to.y, t))
+    }
+}
+
+impl Lerp for Size<f32> {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Size::new(self.width.lerp(to.width, t), self.height.lerp(to.height, t))
+    }
+}
+
+/// Rotations are interpolated by angle, not along the shortest path:
+/// from 350 to 10 degrees is a turn of 340 degrees.
+impl Lerp for Rotation<f32> {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Rotation::radians(self.radians.lerp(to.radians, t))
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Color {
+            r: self.r.lerp(to.r, t),
+            g: self.g.lerp(to.g, t),
+            b: self.b.lerp(to.b, t),
+            a: self.a.lerp(to.a, t),
+        }
+    }
+}
+
+impl Lerp for Transform<f32> {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Transform {
+            translation: self.translation.lerp(to.translation, t),
+            scale: self.scale.lerp(to.scale, t),
+            rotation: self.rotation.lerp(to.rotation, t),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Tweenable -
+// -----------------------------------------------------------------------------
+/// Something that changes a target over time.
+pub trait Tweenable<S> {
+    /// The total duration in seconds.
+    /// This is infinite for something that repeats forever.
+    fn duration(&self) -> f32;
+
+    /// Apply the state at `time` (in seconds from the start) to the target.
+    /// `time` is clamped to the duration.
+    fn sample(&self, time: f32, target: &mut S);
+
+    /// Wait before starting.
+    fn delay(self, seconds: f32) -> Sequence<S>
+    where
+        Self: Sized + 'static,
+    {
+        Sequence::new().wait(seconds).then(self)
+    }
+
+    /// Play a number of times.
+    fn repeat(self, count: u32) -> Repeat<S>
+    where
+        Self: Sized + 'static,
+    {
+        Repeat::new(self, Some(count), false)
+    }
+
+    /// Play a number of times, every other time backwards.
+    /// A count of two plays forward then backward.
+    fn yoyo(self, count: u32) -> Repeat<S>
+    where
+        Self: Sized + 'static,
+    {
+        Repeat::new(self, Some(count), true)
+    }
+
+    /// Play forever.
+    fn forever(self) -> Repeat<S>
+    where
+        Self: Sized + 'static,
+    {
+        Repeat::new(self, None, false)
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Tween -
+// -----------------------------------------------------------------------------
+/// Change one value of the target from `from` to `to`.
+///
+/// The value is written to the target with the `lens` function.
+pub struct Tween<S, V> {
+    from: V,
+    to: V,
+    duration: f32,
+    easing: Easing,
+    lens: fn(&mut S, V),
+}
+
+impl<S, V: Lerp> Tween<S, V> {
+    /// Create a new tween with linear easing.
+    pub fn new(from: V, to: V, duration: f32, lens: fn(&mut S, V)) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+            lens,
+        }
+    }
+
+    /// Set the easing curve.
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+impl<S, V: Lerp> Tweenable<S> for Tween<S, V> {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn sample(&self, time: f32, target: &mut S) {
+        let t = match self.duration > 0.0 {
+            true => time / self.duration,
+            false => 1.0,
+        };
+        let value = self.from.lerp(self.to, self.easing.apply(t));
+        (self.lens)(target, value);
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Sequence -
+// -----------------------------------------------------------------------------
+/// Play one tween after the other.
+///
+/// Tweens that haven't started are held at their start, and the tweens
+/// that have started are applied after them. Sampling an earlier time
+/// after a later one therefore resets the later tweens, and when two
+/// tweens change the same value the one that started last wins.
+pub struct Sequence<S> {
+    items: Vec<(f32, Box<dyn Tweenable<S>>)>,
+    duration: f32,
+}
+
+impl<S> Sequence<S> {
+    /// Create an empty sequence.
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            duration: 0.0,
+        }
+    }
+
+    /// Add a tween to the end of the sequence.
+    pub fn then(mut self, tween: impl Tweenable<S> + 'static) -> Self {
+        let start = self.duration;
+        self.duration += tween.duration();
+        self.items.push((start, Box::new(tween)));
+        self
+    }
+
+    /// Do nothing for a while.
+    pub fn wait(mut self, seconds: f32) -> Self {
+        self.duration += seconds.max(0.0);
+        self
+    }
+}
+
+impl<S> Default for Sequence<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Tweenable<S> for Sequence<S> {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn sample(&self, time: f32, target: &mut S) {
+        // In reverse, so the earliest tween that hasn't started wins
+        for (_, tween) in self.items.iter().rev().filter(|(start, _)| time < *start) {
+            tween.sample(0.0, target);
+        }
+
+        for (start, tween) in self.items.iter().filter(|(start, _)| time >= *start) {
+            tween.sample((time - start).min(tween.duration()), target);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Parallel -
+// -----------------------------------------------------------------------------
+/// Play tweens at the same time.
+/// The group lasts as long as the longest tween.
+pub struct Parallel<S> {
+    items: Vec<Box<dyn Tweenable<S>>>,
+}
+
+impl<S> Parallel<S> {
+    /// Create an empty group.
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Add a tween to the group.
+    pub fn with(mut self, tween: impl Tweenable<S> + 'static) -> Self {
+        self.items.push(Box::new(tween));
+        self
+    }
+}
+
+impl<S> Default for Parallel<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Tweenable<S> for Parallel<S> {
+    fn duration(&self) -> f32 {
+        self.items.iter().map(|t| t.duration()).fold(0.0, f32::max)
+    }
+
+    fn sample(&self, time: f32, target: &mut S) {
+        for tween in &self.items {
+            tween.sample(time.min(tween.duration()), target);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Repeat -
+// -----------------------------------------------------------------------------
+/// Play a tween a number of times, or forever.
+/// Created with [`Tweenable::repeat`], [`Tweenable::yoyo`] and [`Tweenable::forever`].
+pub struct Repeat<S> {
+    inner: Box<dyn Tweenable<S>>,
+    count: Option<u32>,
+    yoyo: bool,
+}
+
+impl<S> Repeat<S> {
+    /// Repeat `count` times, or forever if `count` is `None`.
+    /// With `yoyo` every other repetition plays backwards.
+    pub fn new(tween: impl Tweenable<S> + 'static, count: Option<u32>, yoyo: bool) -> Self {
+        Self {
+            inner: Box::new(tween),
+            count,
+            yoyo,
+        }
+    }
+}
+
+impl<S> Tweenable<S> for Repeat<S> {
+    fn duration(&self) -> f32 {
+        match self.count {
+            Some(count) => self.inner.duration() * count as f32,
+            None => f32::INFINITY,
+        }
+    }
+
+    fn sample(&self, time: f32, target: &mut S) {
+        let duration = self.inner.duration();
+        if duration <= 0.0 {
+            return self.inner.sample(0.0, target);
+        }
+
+        let time = time.max(0.0).min(self.duration());
+        let mut iteration = (time / duration).floor() as u32;
+        let mut local = time - iteration as f32 * duration;
+
+        // The very end of the last repetition
+        if let Some(count) = self.count {
+            if iteration >= count {
+                iteration = count.saturating_sub(1);
+                local = duration;
+            }
+        }
+
+        let local = match self.yoyo && iteration % 2 == 1 {
+            true => duration - local,
+            false => local,
+        };
+
+        self.inner.sample(local, target);
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Tween player -
+// -----------------------------------------------------------------------------
+/// Play a tween, driven by the delta time of each frame.
+pub struct TweenPlayer<S> {
+    tween: Box<dyn Tweenable<S>>,
+    time: f32,
+}
+
+impl<S> TweenPlayer<S> {
+    /// Create a new player, at the start of the tween.
+    pub fn new(tween: impl Tweenable<S> + 'static) -> Self {
+        Self {
+            tween: Box::new(tween),
+            time: 0.0,
+        }
+    }
+
+    /// Advance the time and apply the tween to the target.
+    pub fn update(&mut self, dt: f32, target: &mut S) {
+        self.time = (self.time + dt).min(self.tween.duration());
+        self.tween.sample(self.time, target);
+    }
+
+    /// The time since the start.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Returns true once the tween has played to the end.
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.tween.duration()
+    }
+
+    /// Go back to the start.
+    /// The target is changed on the next update.
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Sprite;
+
+    fn x(from: f32, to: f32, duration: f32) -> Tween<Transform<f32>, f32> {
+        Tween::new(from, to, duration, |t, v| t.translation.x = v)
+    }
+
+    fn sample(tween: &impl Tweenable<Transform<f32>>, time: f32) -> f32 {
+        let mut transform = Transform::default();
+        tween.sample(time, &mut transform);
+        transform.translation.x
+    }
+
+    #[test]
+    fn sequence_with_delay() {
+        let sequence = Sequence::new().then(x(0.0, 10.0, 1.0)).wait(1.0).then(x(20.0, 30.0, 1.0));
+
+        assert_eq!(sequence.duration(), 3.0);
+        assert_eq!(sample(&sequence, 0.5), 5.0);
+        assert_eq!(sample(&sequence, 1.5), 10.0);
+        assert_eq!(sample(&sequence, 2.5), 25.0);
+        assert_eq!(sample(&sequence, 4.0), 30.0);
+
+        let delayed = x(0.0, 10.0, 1.0).delay(0.5);
+        assert_eq!(delayed.duration(), 1.5);
+        assert_eq!(sample(&delayed, 1.0), 5.0);
+    }
+
+    #[test]
+    fn sequence_sampled_backwards() {
+        let scale = Tween::new(1.0, 3.0, 1.0, |t: &mut Transform<f32>, v| t.scale.x = v);
+        let sequence = Sequence::new().then(x(0.0, 10.0, 1.0)).then(scale).then(x(20.0, 30.0, 1.0));
+
+        let mut transform = Transform::default();
+        sequence.sample(sequence.duration(), &mut transform);
+        assert_eq!((transform.translation.x, transform.scale.x), (30.0, 3.0));
+
+        sequence.sample(0.0, &mut transform);
+        assert_eq!((transform.translation.x, transform.scale.x), (0.0, 1.0));
+
+        sequence.sample(1.5, &mut transform);
+        assert_eq!((transform.translation.x, transform.scale.x), (10.0, 2.0));
+    }
+
+    #[test]
+    fn parallel() {
+        let group = Parallel::new()
+            .with(x(0.0, 10.0, 1.0))
+            .with(Tween::new(1.0, 3.0, 2.0, |t: &mut Transform<f32>, v| t.scale.x = v));
+
+        let mut transform = Transform::default();
+        group.sample(1.0, &mut transform);
+        assert_eq!(group.duration(), 2.0);
+        assert_eq!(transform.translation.x, 10.0);
+        assert_eq!(transform.scale.x, 2.0);
+    }
+
+    #[test]
+    fn repeat_and_yoyo() {
+        let repeat = x(0.0, 10.0, 1.0).repeat(2);
+        assert_eq!(repeat.duration(), 2.0);
+        assert_eq!(sample(&repeat, 1.25), 2.5);
+        assert_eq!(sample(&repeat, 5.0), 10.0);
+
+        let yoyo = x(0.0, 10.0, 1.0).yoyo(2);
+        assert_eq!(sample(&yoyo, 1.25), 7.5);
+        assert_eq!(sample(&yoyo, 2.0), 0.0);
+
+        let forever = x(0.0, 10.0, 1.0).forever();
+        assert_eq!(sample(&forever, 100.5), 5.0);
+    }
+
+    #[test]
+    fn player_is_deterministic() {
+        let mut a = TweenPlayer::new(Sequence::new().then(x(0.0, 10.0, 1.0)).then(x(10.0, 0.0, 1.0)));
+        let mut b = TweenPlayer::new(Sequence::new().then(x(0.0, 10.0, 1.0)).then(x(10.0, 0.0, 1.0)));
+        let mut ta = Transform::default();
+        let mut tb = Transform::default();
+
+        a.update(1.5, &mut ta);
+        (0..3).for_each(|_| b.update(0.5, &mut tb));
+        assert_eq!(ta.translation, tb.translation);
+        assert_eq!(a.time(), b.time());
+
+        a.update(10.0, &mut ta);
+        assert!(a.is_finished());
+        assert_eq!(ta.translation.x, 0.0);
+
+    }
+
+    #[test]
+    fn sprite_size_and_anchor() {
+        let grow = Parallel::new()
+            .with(Tween::new(Size::new(8.0, 8.0), Size::new(16.0, 32.0), 1.0, |s: &mut Sprite<f32>, v| s.size = v))
+            .with(Tween::new(Vector::zero(), Vector::new(8.0, 16.0), 1.0, |s: &mut Sprite<f32>, v| s.anchor = v)
+                .easing(Easing::BounceOut));
+
+        let mut sprite = Sprite::from_size(Size::new(32.0, 32.0));
+        let mut player = TweenPlayer::new(grow);
+        player.update(0.5, &mut sprite);
+        assert_eq!(sprite.size, Size::new(12.0, 20.0));
+
+        player.update(0.5, &mut sprite);
+        assert!(player.is_finished());
+        assert_eq!(sprite.anchor, Vector::new(8.0, 16.0));
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Transform::new(Vector::new(0.0, 10.0));
+        let mut b = Transform::new(Vector::new(10.0, 20.0));
+        b.rotation = Rotation::radians(1.0);
+
+        let mid = a.lerp(b, 0.5);
+        assert_eq!(mid.translation, Vector::new(5.0, 15.0));
+        assert_eq!(mid.rotation, Rotation::radians(0.5));
+
+        let grey = Color::black().lerp(Color::white(), 0.5);
+        assert_eq!(grey.r, 0.5);
+    }
+}
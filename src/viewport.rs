@@ -13,6 +13,25 @@ use crate::{Position, Size};
 ///     Size::new(800, 600)
 /// );
 /// ```
+///
+/// ## Depth
+///
+/// The z index of a sprite is its distance from the camera. The projection
+/// maps z indices from [`Viewport::NEAR`] to [`Viewport::FAR`] on to depths
+/// from 0.0 (in front) to 1.0 (at the back), so a lower z index is drawn
+/// in front of a higher one.
+///
+/// Anything outside that range is clipped and not drawn at all.
+/// The depth buffer has at least 24 bits, so every z index in the
+/// range has a depth of its own.
+///
+/// ```
+/// use nightmaregl::Viewport;
+///
+/// assert_eq!(Viewport::depth(Viewport::NEAR), 0.0);
+/// assert_eq!(Viewport::depth(5000), 0.5);
+/// assert_eq!(Viewport::depth(Viewport::FAR), 1.0);
+/// ```
 #[derive(Debug, Clone)]
 pub struct Viewport {
     /// The screen position of the viewport
@@ -28,12 +47,25 @@ fn projection(size: Size<f32>) -> Matrix4<f32> {
         size.width,
         0.0,
         size.height,
-        0.0,
-        -10000.0,
+        -Viewport::NEAR as f32,
+        -Viewport::FAR as f32,
     )
 }
 
 impl Viewport {
+    /// The lowest z index that is drawn: closest to the camera.
+    pub const NEAR: i32 = 0;
+
+    /// The highest z index that is drawn: furthest from the camera.
+    pub const FAR: i32 = 10000;
+
+    /// The depth of a z index in the depth buffer, from 0.0 to 1.0.
+    /// Z indices outside [`Viewport::NEAR`] to [`Viewport::FAR`]
+    /// are outside that range, and are clipped.
+    pub fn depth(z_index: i32) -> f32 {
+        (z_index - Self::NEAR) as f32 / (Self::FAR - Self::NEAR) as f32
+    }
+
     /// Create a new viewport somewhere in screen space.
     pub fn new(position: impl Into<Position<i32>>, size: impl Into<Size<i32>> + Copy) -> Self {
         let size = size.into();
@@ -56,8 +88,8 @@ impl Viewport {
             size.width,
             size.height,
             0.0,
-            0.0,
-            -10000.0,
+            -Self::NEAR as f32,
+            -Self::FAR as f32,
         );
 
         self.projection = matrix;
@@ -70,6 +102,17 @@ impl Viewport {
         self.projection = projection(new_size.cast());
     }
 
+    /// Set the view matrix, transforming from the world to the viewport.
+    /// See [`crate::Camera2D`] for a way to create one.
+    pub fn set_view(&mut self, view: Matrix4<f32>) {
+        self.view = view;
+    }
+
+    /// Get a reference to the view matrix.
+    pub fn view(&self) -> &Matrix4<f32> {
+        &self.view
+    }
+
     /// Get a reference to the size of the viewport.
     pub fn size(&self) -> &Size<i32> {
         &self.size
@@ -80,3 +123,23 @@ impl Viewport {
         Position::new(self.size.width / 2, self.size.height / 2)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nalgebra::Vector4;
+
+    #[test]
+    fn z_index_to_depth() {
+        let mut viewport = Viewport::new(Position::zero(), Size::new(8, 8));
+
+        for _ in 0..2 {
+            for z_index in &[Viewport::NEAR, 1, 50, 9999, Viewport::FAR] {
+                let clip = viewport.projection * Vector4::new(0.0, 0.0, *z_index as f32, 1.0);
+                let depth = (clip.z / clip.w + 1.0) / 2.0;
+                assert!((depth - Viewport::depth(*z_index)).abs() < 1e-6, "{}", z_index);
+            }
+            viewport.swap_y();
+        }
+    }
+}
@@ -0,0 +1,261 @@
+#![deny(missing_docs)]
+//! # Virtual screen
+//!
+//! Render at a fixed, low resolution and scale it up to the window,
+//! for crisp pixel art at any window size.
+use crate::framebuffer::Framebuffer;
+use crate::texture::{Format, Texture};
+use crate::{Color, Context, Point, Position, Renderer, Result, Size, Sprite, Transform, Vector, VertexData, Viewport};
+
+// -----------------------------------------------------------------------------
+//     - Scale mode -
+// -----------------------------------------------------------------------------
+/// How a [`VirtualScreen`] is scaled up to the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleMode {
+    /// Scale by the largest whole number that fits the window,
+    /// so every virtual pixel is the same size.
+    Integer,
+    /// Scale as much as possible while keeping the aspect ratio.
+    /// Virtual pixels may differ in size by one window pixel.
+    Fit,
+}
+
+// -----------------------------------------------------------------------------
+//     - Layout -
+// -----------------------------------------------------------------------------
+/// Where the virtual screen is drawn in the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    /// Position in the window, from the bottom left corner.
+    pub position: Position<i32>,
+    /// Size in the window.
+    pub size: Size<i32>,
+    /// Window pixels per virtual pixel.
+    pub scale: f32,
+}
+
+impl Layout {
+    /// Fit the virtual size in the window, centred,
+    /// leaving bars on the sides or the top and bottom.
+    ///
+    /// ```
+    /// use nightmaregl::{Position, Size};
+    /// use nightmaregl::virtual_screen::{Layout, ScaleMode};
+    ///
+    /// let layout = Layout::new(Size::new(320, 180), Size::new(1366, 768), ScaleMode::Integer);
+    /// assert_eq!(layout.scale, 4.0);
+    /// assert_eq!(layout.size, Size::new(1280, 720));
+    /// assert_eq!(layout.position, Position::new(43, 24));
+    /// ```
+    pub fn new(virtual_size: Size<i32>, window_size: Size<i32>, mode: ScaleMode) -> Self {
+        let ratio_x = window_size.width as f32 / virtual_size.width.max(1) as f32;
+        let ratio_y = window_size.height as f32 / virtual_size.height.max(1) as f32;
+        let ratio = ratio_x.min(ratio_y);
+
+        // Never go below one, even if the window is smaller than the virtual screen
+        let scale = match mode {
+            ScaleMode::Integer => ratio.floor().max(1.0),
+            ScaleMode::Fit => ratio,
+        };
+
+        let size = (virtual_size.to_f32() * scale).round().to_i32();
+        let position = Position::new(
+            (window_size.width - size.width) / 2,
+            (window_size.height - size.height) / 2,
+        );
+
+        Self { position, size, scale }
+    }
+}
+
+// -----------------------------------------------------------------------------
+//     - Virtual screen -
+// -----------------------------------------------------------------------------
+/// Render at a fixed resolution, then scale the result up to the window.
+///
+/// Everything is rendered into a texture of the virtual size, using
+/// [`VirtualScreen::viewport`]. [`VirtualScreen::present`] then draws the
+/// texture to the window, filling the rest of the window with the bar colour.
+///
+/// ```
+/// # use nightmaregl::*;
+/// # fn run(mut context: Context, renderer: Renderer<VertexData>, texture: Texture<f32>, vertex_data: Vec<VertexData>) -> Result<()> {
+/// use nightmaregl::virtual_screen::{ScaleMode, VirtualScreen};
+///
+/// let mut screen = VirtualScreen::new(Size::new(320, 180), context.window_size(), ScaleMode::Integer);
+///
+/// // On `Event::Resize`
+/// screen.resize(context.window_size());
+///
+/// // On `Event::Draw`
+/// screen.begin(&context);
+/// context.clear(Color::black());
+/// renderer.render(&texture, &vertex_data, screen.viewport(), &mut context)?;
+/// screen.present(&renderer, &mut context)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct VirtualScreen {
+    framebuffer: Framebuffer,
+    texture: Texture<f32>,
+    viewport: Viewport,
+    window_size: Size<i32>,
+    layout: Layout,
+    mode: ScaleMode,
+    /// The colour of the bars around the virtual screen.
+    pub bar_color: Color,
+}
+
+impl VirtualScreen {
+    /// Create a virtual screen of a fixed size.
+    pub fn new(size: Size<i32>, window_size: Size<i32>, mode: ScaleMode) -> Self {
+        let texture = Texture::<f32>::new()
+            .with_format(Format::Rgba)
+            .with_no_data(size.to_f32());
+
+        let mut framebuffer = Framebuffer::default();
+        framebuffer.attach_texture(&texture);
+        framebuffer.attach_depth_buffer(size);
+
+        // Flip the y axis so the texture is the right way up when drawn
+        let mut viewport = Viewport::new(Position::zero(), size);
+        viewport.swap_y();
+
+        Self {
+            framebuffer,
+            texture,
+            viewport,
+            window_size,
+            layout: Layout::new(size, window_size, mode),
+            mode,
+            bar_color: Color::black(),
+        }
+    }
+
+    /// The viewport to render into the virtual screen with.
+    pub fn viewport(&self) -> &Viewport {
+        &self.viewport
+    }
+
+    /// The viewport to render into the virtual screen with.
+    /// Use this to apply a camera.
+    pub fn viewport_mut(&mut self) -> &mut Viewport {
+        &mut self.viewport
+    }
+
+    /// The fixed size.
+    pub fn size(&self) -> Size<i32> {
+        *self.viewport.size()
+    }
+
+    /// Where the virtual screen is drawn in the window.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Change the scale mode.
+    pub fn set_mode(&mut self, mode: ScaleMode) {
+        self.mode = mode;
+        self.layout = Layout::new(self.size(), self.window_size, mode);
+    }
+
+    /// Update the layout when the window size changes.
+    pub fn resize(&mut self, window_size: Size<i32>) {
+        self.window_size = window_size;
+        self.layout = Layout::new(self.size(), window_size, self.mode);
+    }
+
+    /// Start rendering into the virtual screen.
+    ///
+    /// This clears the depth buffer of the virtual screen, but not the
+    /// colour: call [`Context::clear`] to clear both.
+    pub fn begin(&mut self, context: &Context) {
+        self.framebuffer.bind();
+        context.clear_depth();
+    }
+
+    /// Stop rendering into the virtual screen and draw it to the window.
+    /// This clears the window with the bar colour.
+    pub fn present(&mut self, renderer: &Renderer<VertexData>, context: &mut Context) -> Result<()> {
+        self.framebuffer.unbind();
+        context.clear(self.bar_color);
+
+        let layout = self.layout;
+        let mut sprite = Sprite::new(&self.texture);
+        sprite.size = layout.size.to_f32();
+
+        // Undo the pixel size of the renderer
+        let mut transform = Transform::default();
+        let scale = 1.0 / renderer.pixel_size as f32;
+        transform.scale = Vector::new(scale, scale);
+
+        let viewport = Viewport::new(layout.position, layout.size);
+        let vertex_data = VertexData::new(&sprite, &transform);
+        renderer.render(&self.texture, &[vertex_data], &viewport, context)
+    }
+
+    /// Convert a position in the window (where 0, 0 is the top left corner,
+    /// same as `Event::MouseMoved`) to the virtual screen, where 0, 0 is also
+    /// the top left corner.
+    ///
+    /// Returns `None` if the position is on the bars.
+    pub fn window_to_virtual(&self, point: Point<f32>) -> Option<Point<f32>> {
+        window_to_virtual(point, self.window_size, self.layout, self.size())
+    }
+}
+
+fn window_to_virtual(point: Point<f32>, window_size: Size<i32>, layout: Layout, size: Size<i32>) -> Option<Point<f32>> {
+    // The layout position is from the bottom left
+    let top = (window_size.height - layout.position.y - layout.size.height) as f32;
+    let x = (point.x - layout.position.x as f32) / layout.scale;
+    let y = (point.y - top) / layout.scale;
+
+    let inside = x >= 0.0 && y >= 0.0 && x < size.width as f32 && y < size.height as f32;
+    match inside {
+        true => Some(Point::new(x, y)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integer_layout() {
+        let layout = Layout::new(Size::new(100, 100), Size::new(350, 220), ScaleMode::Integer);
+        assert_eq!(layout.scale, 2.0);
+        assert_eq!(layout.size, Size::new(200, 200));
+        assert_eq!(layout.position, Position::new(75, 10));
+
+        // Smaller than the virtual screen
+        let layout = Layout::new(Size::new(100, 100), Size::new(50, 50), ScaleMode::Integer);
+        assert_eq!(layout.scale, 1.0);
+        assert_eq!(layout.position, Position::new(-25, -25));
+    }
+
+    #[test]
+    fn fit_layout() {
+        let layout = Layout::new(Size::new(100, 50), Size::new(300, 400), ScaleMode::Fit);
+        assert_eq!(layout.scale, 3.0);
+        assert_eq!(layout.size, Size::new(300, 150));
+        assert_eq!(layout.position, Position::new(0, 125));
+    }
+
+    #[test]
+    fn mouse_to_virtual() {
+        let size = Size::new(100, 50);
+        let window = Size::new(300, 200);
+        let layout = Layout::new(size, window, ScaleMode::Integer);
+
+        // Bars of 25 pixels at the top and bottom
+        assert_eq!(layout.position, Position::new(0, 25));
+        assert_eq!(window_to_virtual(Point::new(150.0, 10.0), window, layout, size), None);
+        assert_eq!(window_to_virtual(Point::new(0.0, 25.0), window, layout, size), Some(Point::zero()));
+        assert_eq!(
+            window_to_virtual(Point::new(297.0, 172.0), window, layout, size),
+            Some(Point::new(99.0, 49.0))
+        );
+    }
+}